@@ -0,0 +1,56 @@
+use reqwest::Method;
+use serde::de::IgnoredAny;
+use zero2prod_types::{Activity, ActivityFormData};
+
+use crate::{Client, Error};
+
+/// Requests against `/activity-groups`, obtained from [`Client::activities`].
+pub struct Activities<'a> {
+    client: &'a Client,
+}
+
+impl<'a> Activities<'a> {
+    pub(crate) fn new(client: &'a Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn list(&self) -> Result<Vec<Activity>, Error> {
+        let request = self.client.request(Method::GET, "/activity-groups");
+        self.client.send(request).await
+    }
+
+    pub async fn get(&self, activity_id: i32) -> Result<Activity, Error> {
+        let request = self
+            .client
+            .request(Method::GET, &format!("/activity-groups/{}", activity_id));
+        self.client.send(request).await
+    }
+
+    pub async fn create(&self, form: &ActivityFormData) -> Result<Activity, Error> {
+        let request = self
+            .client
+            .request(Method::POST, "/activity-groups")
+            .json(form);
+        self.client.send(request).await
+    }
+
+    pub async fn update(
+        &self,
+        activity_id: i32,
+        form: &ActivityFormData,
+    ) -> Result<Activity, Error> {
+        let request = self
+            .client
+            .request(Method::PATCH, &format!("/activity-groups/{}", activity_id))
+            .json(form);
+        self.client.send(request).await
+    }
+
+    pub async fn delete(&self, activity_id: i32) -> Result<(), Error> {
+        let request = self
+            .client
+            .request(Method::DELETE, &format!("/activity-groups/{}", activity_id));
+        self.client.send::<IgnoredAny>(request).await?;
+        Ok(())
+    }
+}
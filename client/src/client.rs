@@ -0,0 +1,51 @@
+use serde::de::DeserializeOwned;
+use zero2prod_types::ResponseWithData;
+
+use crate::{Activities, Error, Todos};
+
+/// Typed client for the activity-group and todo-item endpoints.
+#[derive(Clone, Debug)]
+pub struct Client {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    pub fn with_http_client(base_url: impl Into<String>, http: reqwest::Client) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            http,
+        }
+    }
+
+    pub fn activities(&self) -> Activities<'_> {
+        Activities::new(self)
+    }
+
+    pub fn todos(&self) -> Todos<'_> {
+        Todos::new(self)
+    }
+
+    pub(crate) fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.http
+            .request(method, format!("{}{}", self.base_url, path))
+    }
+
+    /// Sends the request and unwraps the `data` field of a success envelope.
+    pub(crate) async fn send<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, Error> {
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(Error::from_response(response).await);
+        }
+
+        let body = response.json::<ResponseWithData<T>>().await?;
+        Ok(body.data)
+    }
+}
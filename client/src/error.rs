@@ -0,0 +1,57 @@
+use reqwest::StatusCode;
+use zero2prod_types::Response;
+
+/// Errors returned by [`Client`](crate::Client) calls.
+#[derive(Debug)]
+pub enum Error {
+    /// 400: the request body was rejected, e.g. a missing title.
+    BadRequest(String),
+    /// 404: the addressed activity group or todo does not exist.
+    NotFound(String),
+    /// Any other non-success status, with the envelope message when the body had one.
+    Api { status: StatusCode, message: String },
+    /// The request could not be sent or the body could not be decoded.
+    Transport(reqwest::Error),
+}
+
+impl Error {
+    pub(crate) async fn from_response(response: reqwest::Response) -> Error {
+        let status = response.status();
+        let message = match response.json::<Response>().await {
+            Ok(body) => body.message,
+            Err(_) => status.canonical_reason().unwrap_or_default().to_string(),
+        };
+
+        match status {
+            StatusCode::BAD_REQUEST => Error::BadRequest(message),
+            StatusCode::NOT_FOUND => Error::NotFound(message),
+            status => Error::Api { status, message },
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::BadRequest(message) => write!(f, "bad request: {}", message),
+            Error::NotFound(message) => write!(f, "not found: {}", message),
+            Error::Api { status, message } => write!(f, "{}: {}", status, message),
+            Error::Transport(e) => write!(f, "transport error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Transport(e)
+    }
+}
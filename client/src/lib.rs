@@ -0,0 +1,11 @@
+mod activity;
+mod client;
+mod error;
+mod todo;
+
+pub use activity::*;
+pub use client::*;
+pub use error::*;
+pub use todo::*;
+
+pub use zero2prod_types as types;
@@ -0,0 +1,53 @@
+use reqwest::Method;
+use serde::de::IgnoredAny;
+use zero2prod_types::{Todo, TodoFormData, TodoFormUpdateData};
+
+use crate::{Client, Error};
+
+/// Requests against `/todo-items`, obtained from [`Client::todos`].
+pub struct Todos<'a> {
+    client: &'a Client,
+}
+
+impl<'a> Todos<'a> {
+    pub(crate) fn new(client: &'a Client) -> Self {
+        Self { client }
+    }
+
+    /// Lists todos, restricted to one activity group when `activity_group_id` is set.
+    pub async fn list(&self, activity_group_id: Option<i32>) -> Result<Vec<Todo>, Error> {
+        let mut request = self.client.request(Method::GET, "/todo-items");
+        if let Some(activity_group_id) = activity_group_id {
+            request = request.query(&[("activity_group_id", activity_group_id)]);
+        }
+        self.client.send(request).await
+    }
+
+    pub async fn get(&self, todo_id: i32) -> Result<Todo, Error> {
+        let request = self
+            .client
+            .request(Method::GET, &format!("/todo-items/{}", todo_id));
+        self.client.send(request).await
+    }
+
+    pub async fn create(&self, form: &TodoFormData) -> Result<Todo, Error> {
+        let request = self.client.request(Method::POST, "/todo-items").json(form);
+        self.client.send(request).await
+    }
+
+    pub async fn update(&self, todo_id: i32, form: &TodoFormUpdateData) -> Result<Todo, Error> {
+        let request = self
+            .client
+            .request(Method::PATCH, &format!("/todo-items/{}", todo_id))
+            .json(form);
+        self.client.send(request).await
+    }
+
+    pub async fn delete(&self, todo_id: i32) -> Result<(), Error> {
+        let request = self
+            .client
+            .request(Method::DELETE, &format!("/todo-items/{}", todo_id));
+        self.client.send::<IgnoredAny>(request).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use secrecy::ExposeSecret;
+use sqlx::{Connection, Executor, MySqlConnection, MySqlPool};
+use std::net::TcpListener;
+use uuid::Uuid;
+use zero2prod::configuration::{get_configuration, DatabaseSettings};
+use zero2prod::startup::run;
+use zero2prod_client::types::{ActivityFormData, TodoFormData, TodoFormUpdateData};
+use zero2prod_client::{Client, Error};
+
+async fn spawn_app() -> Client {
+    // `get_configuration` reads `configuration/` relative to the working
+    // directory, which is the client crate when running these tests.
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
+        .expect("Failed to change to the workspace root");
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
+    let port = listener.local_addr().unwrap().port();
+
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.database_name = Uuid::new_v4().to_string();
+    let connection_pool = configure_database(&configuration.database).await;
+    let server = run(listener, connection_pool).expect("Failed to bind address");
+    tokio::spawn(server);
+
+    Client::new(format!("http://127.0.0.1:{}", port))
+}
+
+async fn configure_database(config: &DatabaseSettings) -> MySqlPool {
+    let mut connection =
+        MySqlConnection::connect(config.connection_string_without_db().expose_secret())
+            .await
+            .expect("Failed to connect to MySQL");
+
+    connection
+        .execute(format!("CREATE DATABASE `{}`;", config.database_name).as_str())
+        .await
+        .expect("Failed to create database.");
+
+    let connection_pool = MySqlPool::connect(config.connection_string().expose_secret())
+        .await
+        .expect("Failed to connect to MySQL.");
+
+    sqlx::migrate!("../migrations")
+        .run(&connection_pool)
+        .await
+        .expect("Failed to migrate the database");
+    connection_pool
+}
+
+fn activity_form(title: &str) -> ActivityFormData {
+    ActivityFormData {
+        title: title.into(),
+        email: Some("team@example.com".into()),
+    }
+}
+
+fn todo_form(title: &str, activity_group_id: i32) -> TodoFormData {
+    TodoFormData {
+        title: title.into(),
+        activity_group_id: Some(activity_group_id),
+        priority: "high".into(),
+        is_active: true,
+        due_at: None,
+    }
+}
+
+#[tokio::test]
+async fn activities_round_trip() {
+    let client = spawn_app().await;
+
+    let created = client
+        .activities()
+        .create(&activity_form("Groceries"))
+        .await
+        .expect("Failed to create activity");
+    assert_eq!(created.title, "Groceries");
+
+    let fetched = client.activities().get(created.id).await.unwrap();
+    assert_eq!(fetched.id, created.id);
+    assert_eq!(fetched.email.as_deref(), Some("team@example.com"));
+
+    let updated = client
+        .activities()
+        .update(created.id, &activity_form("Weekly groceries"))
+        .await
+        .unwrap();
+    assert_eq!(updated.title, "Weekly groceries");
+
+    let listed = client.activities().list().await.unwrap();
+    assert_eq!(listed.len(), 1);
+
+    client.activities().delete(created.id).await.unwrap();
+    assert!(client.activities().list().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn todos_round_trip() {
+    let client = spawn_app().await;
+    let activity = client
+        .activities()
+        .create(&activity_form("Chores"))
+        .await
+        .unwrap();
+
+    let created = client
+        .todos()
+        .create(&todo_form("Take out the trash", activity.id))
+        .await
+        .expect("Failed to create todo");
+    assert_eq!(created.activity_group_id, activity.id);
+    assert_eq!(created.priority.as_deref(), Some("high"));
+
+    let updated = client
+        .todos()
+        .update(
+            created.id,
+            &TodoFormUpdateData {
+                is_active: Some(false),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.is_active, Some(false));
+    assert_eq!(updated.title, "Take out the trash");
+
+    let listed = client.todos().list(Some(activity.id)).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert!(client
+        .todos()
+        .list(Some(activity.id + 1))
+        .await
+        .unwrap()
+        .is_empty());
+
+    client.todos().delete(created.id).await.unwrap();
+    assert!(client.todos().list(None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn validation_errors_are_mapped_to_bad_request() {
+    let client = spawn_app().await;
+
+    let error = client
+        .activities()
+        .create(&activity_form(" "))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::BadRequest(ref message) if message == "title cannot be null"));
+
+    let error = client
+        .todos()
+        .create(&TodoFormData {
+            activity_group_id: None,
+            ..todo_form("Orphan", 0)
+        })
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, Error::BadRequest(ref message) if message == "activity_group_id cannot be null")
+    );
+}
+
+#[tokio::test]
+async fn missing_records_are_mapped_to_not_found() {
+    let client = spawn_app().await;
+
+    let error = client.activities().get(42).await.unwrap_err();
+    assert!(
+        matches!(error, Error::NotFound(ref message) if message == "Activity with ID 42 Not Found")
+    );
+
+    let error = client.todos().delete(42).await.unwrap_err();
+    assert!(
+        matches!(error, Error::NotFound(ref message) if message == "Todo with ID 42 Not Found")
+    );
+}
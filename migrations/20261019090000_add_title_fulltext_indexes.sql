@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE activities ADD FULLTEXT INDEX activities_title_fulltext (title);
+ALTER TABLE todos ADD FULLTEXT INDEX todos_title_fulltext (title);
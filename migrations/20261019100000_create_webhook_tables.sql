@@ -0,0 +1,27 @@
+-- Add migration script here
+CREATE TABLE `webhooks` (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `url` varchar(2048) NOT NULL,
+  `event_types` varchar(1024) NOT NULL,
+  `secret` varchar(255) NOT NULL,
+  `created_at` datetime NOT NULL,
+  `updated_at` datetime DEFAULT NULL,
+  PRIMARY KEY (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=latin1;
+
+CREATE TABLE `webhook_deliveries` (
+  `id` bigint(20) NOT NULL AUTO_INCREMENT,
+  `webhook_id` int(11) NOT NULL,
+  `event_type` varchar(64) NOT NULL,
+  `payload` text NOT NULL,
+  `status` enum('pending','succeeded','failed') NOT NULL DEFAULT 'pending',
+  `attempts` int(11) NOT NULL DEFAULT '0',
+  `next_attempt_at` datetime NOT NULL,
+  `response_status` int(11) DEFAULT NULL,
+  `last_error` varchar(1024) DEFAULT NULL,
+  `created_at` datetime NOT NULL,
+  `delivered_at` datetime DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `webhook_deliveries_due` (`status`, `next_attempt_at`),
+  KEY `webhook_deliveries_webhook` (`webhook_id`, `id`)
+) ENGINE=InnoDB DEFAULT CHARSET=latin1;
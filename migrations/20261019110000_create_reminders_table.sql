@@ -0,0 +1,17 @@
+-- Add migration script here
+ALTER TABLE `todos` ADD COLUMN `due_at` datetime DEFAULT NULL;
+
+CREATE TABLE `reminders` (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `todo_id` int(11) NOT NULL,
+  `remind_at` datetime DEFAULT NULL,
+  `offset_minutes` int(11) DEFAULT NULL,
+  `channel` enum('log','email','webhook') NOT NULL DEFAULT 'email',
+  `recipient` varchar(2048) DEFAULT NULL,
+  `fired_at` datetime DEFAULT NULL,
+  `last_error` varchar(1024) DEFAULT NULL,
+  `created_at` datetime NOT NULL,
+  PRIMARY KEY (`id`),
+  KEY `reminders_todo` (`todo_id`),
+  KEY `reminders_pending` (`fired_at`, `remind_at`)
+) ENGINE=InnoDB DEFAULT CHARSET=latin1;
@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE `activity_digests` (
+  `activity_group_id` int(11) NOT NULL,
+  `token` varchar(64) NOT NULL,
+  `last_sent_at` datetime DEFAULT NULL,
+  `opted_out_at` datetime DEFAULT NULL,
+  `created_at` datetime NOT NULL,
+  PRIMARY KEY (`activity_group_id`),
+  UNIQUE KEY `activity_digests_token` (`token`)
+) ENGINE=InnoDB DEFAULT CHARSET=latin1;
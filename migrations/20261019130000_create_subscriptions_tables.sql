@@ -0,0 +1,19 @@
+-- Add migration script here
+CREATE TABLE `subscriptions` (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `email` varchar(320) NOT NULL,
+  `name` varchar(256) NOT NULL,
+  `status` enum('pending_confirmation','confirmed') NOT NULL DEFAULT 'pending_confirmation',
+  `subscribed_at` datetime NOT NULL,
+  `confirmed_at` datetime DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `subscriptions_email` (`email`)
+) ENGINE=InnoDB DEFAULT CHARSET=latin1;
+
+CREATE TABLE `subscription_tokens` (
+  `token` varchar(64) NOT NULL,
+  `subscription_id` int(11) NOT NULL,
+  `created_at` datetime NOT NULL,
+  PRIMARY KEY (`token`),
+  KEY `subscription_tokens_subscription` (`subscription_id`)
+) ENGINE=InnoDB DEFAULT CHARSET=latin1;
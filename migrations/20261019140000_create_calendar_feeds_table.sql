@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE `calendar_feeds` (
+  `activity_group_id` int(11) NOT NULL,
+  `token` varchar(64) NOT NULL,
+  `created_at` datetime NOT NULL,
+  PRIMARY KEY (`activity_group_id`),
+  UNIQUE KEY `calendar_feeds_token` (`token`)
+) ENGINE=InnoDB DEFAULT CHARSET=latin1;
@@ -0,0 +1,18 @@
+-- Add migration script here
+CREATE TABLE `calendar_objects` (
+  `todo_id` int(11) NOT NULL,
+  `activity_group_id` int(11) NOT NULL,
+  `name` varchar(255) NOT NULL,
+  `uid` varchar(255) NOT NULL,
+  PRIMARY KEY (`todo_id`),
+  UNIQUE KEY `calendar_objects_name` (`activity_group_id`, `name`)
+) ENGINE=InnoDB DEFAULT CHARSET=latin1;
+
+CREATE TABLE `calendar_tombstones` (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `activity_group_id` int(11) NOT NULL,
+  `name` varchar(255) NOT NULL,
+  `deleted_at` datetime NOT NULL,
+  PRIMARY KEY (`id`),
+  KEY `calendar_tombstones_activity_group` (`activity_group_id`, `deleted_at`)
+) ENGINE=InnoDB DEFAULT CHARSET=latin1;
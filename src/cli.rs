@@ -0,0 +1,212 @@
+//! The `zero2prod` command line: the server plus the admin tasks that used to
+//! live in shell scripts. Every command reads the same configuration.
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::configuration::Settings;
+use crate::services::{
+    check_readiness, export_all, import_export, purge_deleted, run_migrations, seed_sample_data,
+    validate_export, wait_for_database, EventBus, Export, HealthStatus, ImportMode, Mailer,
+    MIGRATION_LOCK_TIMEOUT, READINESS_TIMEOUT,
+};
+use crate::startup::{get_connection_pool, prepare_database, run_with_workers, WorkerSettings};
+use chrono::Utc;
+
+#[derive(clap::Parser, Debug)]
+#[command(name = "zero2prod", version, about = "Todo list API server")]
+pub struct Cli {
+    /// Defaults to `serve`.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug, PartialEq)]
+pub enum Command {
+    /// Start the HTTP server and background workers.
+    Serve,
+    /// Apply the pending migrations embedded in this binary.
+    Migrate {
+        /// How long to wait for MySQL to accept connections, in seconds.
+        #[arg(long, default_value_t = 60)]
+        wait: u64,
+    },
+    /// Add sample activity groups and todos to an empty database. Event
+    /// streams and webhooks of a running server don't see them.
+    Seed {
+        /// Seed even when activity groups already exist.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Write every activity group and todo as JSON.
+    Export {
+        /// File to write; stdout when omitted.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Load the activity groups and todos of an `export` file. Unlike
+    /// `POST /import`, this publishes no change events or webhooks.
+    Import {
+        /// File written by `export`; `-` reads stdin.
+        input: PathBuf,
+        /// `merge`, `replace` or `dry-run`.
+        #[arg(long, default_value = "merge", value_parser = parse_import_mode)]
+        mode: ImportMode,
+    },
+    /// Permanently remove soft-deleted activity groups and todos.
+    PurgeDeleted {
+        /// Only purge rows deleted at least this many days ago.
+        #[arg(long, default_value_t = 0)]
+        older_than_days: u32,
+    },
+    /// Validate the configuration and exit.
+    CheckConfig {
+        /// Also check that MySQL is reachable and fully migrated.
+        #[arg(long)]
+        connect: bool,
+    },
+}
+
+impl Command {
+    /// Whether the command writes its result to stdout, so logs must go
+    /// elsewhere.
+    pub fn is_admin(&self) -> bool {
+        *self != Command::Serve
+    }
+}
+
+pub async fn run(command: Command, settings: Settings) -> Result<(), String> {
+    match command {
+        Command::Serve => serve(settings).await,
+        Command::Migrate { wait } => {
+            let pool = get_connection_pool(&settings.database);
+            wait_for_database(&pool, Duration::from_secs(wait)).await?;
+            let applied = run_migrations(&pool, MIGRATION_LOCK_TIMEOUT).await?;
+            eprintln!("Applied {} pending migrations", applied);
+            Ok(())
+        }
+        Command::Seed { force } => {
+            let pool = get_connection_pool(&settings.database);
+            // The server's event subscribers live in its own process, so
+            // nothing hears these.
+            let seeded = seed_sample_data(&pool, force, &EventBus::default())
+                .await
+                .map_err(|e| format!("Failed to seed the database: {}", e))?;
+            if seeded {
+                eprintln!("Added sample activity groups");
+            } else {
+                eprintln!("Activity groups already exist; pass --force to seed anyway");
+            }
+            Ok(())
+        }
+        Command::Export { output } => {
+            let pool = get_connection_pool(&settings.database);
+            let export = export_all(&pool)
+                .await
+                .map_err(|e| format!("Failed to export: {}", e))?;
+            let json = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+            match output {
+                Some(path) => std::fs::write(&path, json)
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+                None => writeln!(std::io::stdout(), "{}", json).map_err(|e| e.to_string()),
+            }
+        }
+        Command::Import { input, mode } => {
+            let export = read_export(&input)?;
+            validate_export(&export)?;
+            let pool = get_connection_pool(&settings.database);
+            let report = import_export(&export, mode, &pool, &EventBus::default())
+                .await
+                .map_err(|e| format!("Failed to import: {}", e))?;
+            let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+            writeln!(std::io::stdout(), "{}", json).map_err(|e| e.to_string())
+        }
+        Command::PurgeDeleted { older_than_days } => {
+            let pool = get_connection_pool(&settings.database);
+            let cutoff = Utc::now() - chrono::Duration::days(older_than_days.into());
+            let summary = purge_deleted(cutoff, &pool)
+                .await
+                .map_err(|e| format!("Failed to purge: {}", e))?;
+            eprintln!(
+                "Purged {} activity groups and {} todos",
+                summary.activity_groups, summary.todos
+            );
+            Ok(())
+        }
+        Command::CheckConfig { connect } => {
+            let mut problems = check_settings(&settings);
+            if connect {
+                let pool = get_connection_pool(&settings.database);
+                let report = check_readiness(&pool, READINESS_TIMEOUT).await;
+                for (name, component) in report.components {
+                    if let (HealthStatus::Down, Some(message)) =
+                        (component.status, component.message)
+                    {
+                        problems.push(format!("{}: {}", name, message));
+                    }
+                }
+            }
+            if problems.is_empty() {
+                eprintln!("Configuration is valid");
+                Ok(())
+            } else {
+                Err(problems.join("\n"))
+            }
+        }
+    }
+}
+
+/// Everything wrong with `settings` that would only surface once the server
+/// is running.
+pub fn check_settings(settings: &Settings) -> Vec<String> {
+    let mut problems = Vec::new();
+    if let Err(e) = reqwest::Url::parse(&settings.application.base_url) {
+        problems.push(format!("application.base_url is not a URL: {}", e));
+    }
+    if let Some(email) = &settings.email {
+        if let Err(e) = Mailer::from_settings(email) {
+            problems.push(e);
+        }
+    }
+    if let Some(endpoint) = &settings.telemetry.otlp_endpoint {
+        if let Err(e) = reqwest::Url::parse(endpoint) {
+            problems.push(format!("telemetry.otlp_endpoint is not a URL: {}", e));
+        }
+    }
+    problems
+}
+
+async fn serve(settings: Settings) -> Result<(), String> {
+    let pool = get_connection_pool(&settings.database);
+    prepare_database(&pool, &settings.database).await?;
+    let address = format!(
+        "{}:{}",
+        settings.application.host, settings.application.port
+    );
+    let listener =
+        TcpListener::bind(&address).map_err(|e| format!("Failed to bind {}: {}", address, e))?;
+    run_with_workers(listener, pool, WorkerSettings::from(&settings))
+        .map_err(|e| e.to_string())?
+        .await
+        .map_err(|e| e.to_string())
+}
+
+fn read_export(input: &PathBuf) -> Result<Export, String> {
+    let mut json = String::new();
+    if input.as_os_str() == "-" {
+        std::io::stdin()
+            .read_to_string(&mut json)
+            .map_err(|e| format!("Failed to read stdin: {}", e))?;
+    } else {
+        json = std::fs::read_to_string(input)
+            .map_err(|e| format!("Failed to read {}: {}", input.display(), e))?;
+    }
+    serde_json::from_str(&json).map_err(|e| format!("{} is not an export: {}", input.display(), e))
+}
+
+fn parse_import_mode(s: &str) -> Result<ImportMode, String> {
+    serde_json::from_value(serde_json::Value::String(s.into()))
+        .map_err(|_| "expected merge, replace or dry-run".to_string())
+}
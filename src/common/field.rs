@@ -24,15 +24,45 @@ impl TitleField {
     }
 }
 
-#[derive(serde::Serialize)]
-pub struct Response {
-    pub status: String,
-    pub message: String,
+/// Characters a subscriber name may not contain.
+const FORBIDDEN_NAME_CHARACTERS: [char; 9] = ['/', '(', ')', '"', '<', '>', '\\', '{', '}'];
+
+pub struct SubscriberName(String);
+
+impl SubscriberName {
+    pub fn parse(s: String) -> Result<SubscriberName, String> {
+        let s = s.trim().to_string();
+        if s.is_empty() {
+            Err("name cannot be null".into())
+        } else if s.chars().count() > 256 {
+            Err("name cannot be longer than 256 characters".into())
+        } else if s.contains(FORBIDDEN_NAME_CHARACTERS) {
+            Err(format!("{} is not a valid subscriber name", s))
+        } else {
+            Ok(Self(s))
+        }
+    }
+
+    pub fn inner_ref(&self) -> &str {
+        &self.0
+    }
 }
 
-#[derive(serde::Serialize)]
-pub struct ResponseWithData<T> {
-    pub status: String,
-    pub message: String,
-    pub data: T,
+pub struct SubscriberEmail(String);
+
+impl SubscriberEmail {
+    pub fn parse(s: String) -> Result<SubscriberEmail, String> {
+        let s = s.trim().to_string();
+        if s.is_empty() {
+            Err("email cannot be null".into())
+        } else if s.parse::<lettre::Address>().is_err() {
+            Err(format!("{} is not an email address", s))
+        } else {
+            Ok(Self(s))
+        }
+    }
+
+    pub fn inner_ref(&self) -> &str {
+        &self.0
+    }
 }
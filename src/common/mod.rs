@@ -1,3 +1,16 @@
 mod field;
+mod request_id;
+mod timezone;
 
 pub use field::*;
+pub use request_id::*;
+pub use timezone::*;
+pub use zero2prod_types::{Response, ResponseWithData};
+
+/// The v1 404 for an activity group that doesn't exist.
+pub(crate) fn activity_not_found(activity_id: i32) -> actix_web::HttpResponse {
+    actix_web::HttpResponse::NotFound().json(Response {
+        status: "Not Found".into(),
+        message: format!("Activity with ID {} Not Found", activity_id),
+    })
+}
@@ -0,0 +1,190 @@
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Longest caller-supplied request id that is kept; longer ones are replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: RequestId;
+}
+
+/// Correlates a request with its log lines, its response and the work it
+/// starts. Taken from the `X-Request-Id` header when it holds a sane value,
+/// generated otherwise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    pub fn generate() -> RequestId {
+        Self(Uuid::new_v4().to_string())
+    }
+
+    /// Accepts up to 128 letters, digits and `-_.:/`, so a caller can't
+    /// inject anything into headers or logs.
+    pub fn parse(s: &str) -> Option<RequestId> {
+        let s = s.trim();
+        let valid = !s.is_empty()
+            && s.len() <= MAX_REQUEST_ID_LEN
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.:/".contains(c));
+        valid.then(|| Self(s.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The id of the request the calling task is serving, if any.
+    pub fn current() -> Option<RequestId> {
+        CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok()
+    }
+
+    /// Runs `future` with this as the current request id.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_REQUEST_ID.scope(self, future).await
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for RequestId {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let id = req.extensions().get::<RequestId>().cloned();
+        ready(Ok(id
+            .or_else(RequestId::current)
+            .unwrap_or_else(RequestId::generate)))
+    }
+}
+
+/// Wraps work a request hands off to another task, such as a WebSocket
+/// session, so that it keeps the request's id and logs under its span.
+pub fn in_current_request<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let span = tracing::Span::current();
+    let id = RequestId::current();
+    async move {
+        match id {
+            Some(id) => id.scope(future).await,
+            None => future.await,
+        }
+    }
+    .instrument(span)
+}
+
+/// Middleware that assigns every request a [`RequestId`], echoes it in the
+/// `X-Request-Id` response header and adds it as `request_id` to error
+/// bodies in the v1 `Response` shape. Register it outside `TracingLogger` so the root span can log it.
+pub struct RequestIdHeader;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIdHeader
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = actix_web::Error;
+    type Transform = RequestIdMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequestIdMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(RequestId::parse)
+            .unwrap_or_else(RequestId::generate);
+        req.extensions_mut().insert(id.clone());
+        let service = self.service.clone();
+
+        Box::pin(id.clone().scope(async move {
+            let res = service.call(req).await?.map_into_boxed_body();
+            let mut res = if res.status().is_client_error() || res.status().is_server_error() {
+                add_to_error_body(res, &id).await
+            } else {
+                res
+            };
+            if let Ok(value) = HeaderValue::from_str(id.as_str()) {
+                res.headers_mut()
+                    .insert(HeaderName::from_static("x-request-id"), value);
+            }
+            Ok(res)
+        }))
+    }
+}
+
+/// Adds `request_id` to [`crate::common::Response`] bodies; v2 error objects
+/// and everything else pass through untouched.
+async fn add_to_error_body(
+    res: ServiceResponse<BoxBody>,
+    id: &RequestId,
+) -> ServiceResponse<BoxBody> {
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !is_json {
+        return res;
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = match body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to read an error body: {:?}", e);
+            return ServiceResponse::new(req, res.set_body(BoxBody::new(())));
+        }
+    };
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(serde_json::Value::Object(mut object))
+            if object.contains_key("status") && object.contains_key("message") =>
+        {
+            object.insert("request_id".into(), id.as_str().into());
+            serde_json::Value::Object(object).to_string().into_bytes()
+        }
+        _ => bytes.to_vec(),
+    };
+    let mut res = res.set_body(BoxBody::new(body));
+    res.headers_mut().remove(header::CONTENT_LENGTH);
+    ServiceResponse::new(req, res)
+}
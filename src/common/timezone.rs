@@ -0,0 +1,143 @@
+use std::future::{ready, Ready};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::{FromRequest, HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::common::Response;
+
+pub const TIMEZONE_HEADER: &str = "X-Timezone";
+
+/// The caller's preferred timezone, taken from the `X-Timezone` header or
+/// the `tz` query parameter (an IANA name such as `Asia/Jakarta`).
+/// Defaults to UTC when neither is given.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timezone(Tz);
+
+impl Default for Timezone {
+    fn default() -> Self {
+        Self(Tz::UTC)
+    }
+}
+
+impl Timezone {
+    pub fn parse(s: &str) -> Result<Timezone, InvalidTimezone> {
+        Tz::from_str(s.trim())
+            .map(Self)
+            .map_err(|_| InvalidTimezone(s.to_string()))
+    }
+
+    /// Renders a stored UTC instant with the caller's offset.
+    pub fn localize(&self, instant: DateTime<Utc>) -> DateTime<FixedOffset> {
+        let offset = self.0.offset_from_utc_datetime(&instant.naive_utc()).fix();
+        instant.with_timezone(&offset)
+    }
+
+    /// The UTC instant of a wall-clock time in the caller's zone: the earlier
+    /// one when a DST change repeats it, `None` when one skips it.
+    pub fn utc_from_local(&self, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+        self.0
+            .from_local_datetime(&local)
+            .earliest()
+            .map(|instant| instant.with_timezone(&Utc))
+    }
+
+    /// The UTC instant at which `date` starts in the caller's zone.
+    pub fn start_of_day(&self, date: NaiveDate) -> DateTime<Utc> {
+        let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+        match self.0.from_local_datetime(&midnight).earliest() {
+            Some(start) => start.with_timezone(&Utc),
+            // Midnight skipped by a DST change: the day starts an hour later.
+            None => self.start_of_day_after_gap(midnight),
+        }
+    }
+
+    /// The UTC instant at which the day after `date` starts in the caller's
+    /// zone, i.e. the exclusive upper bound of an inclusive date filter.
+    pub fn end_of_day(&self, date: NaiveDate) -> DateTime<Utc> {
+        self.start_of_day(date.succ_opt().unwrap_or(date))
+    }
+
+    fn start_of_day_after_gap(&self, midnight: chrono::NaiveDateTime) -> DateTime<Utc> {
+        let later = midnight + Duration::hours(1);
+        self.0
+            .from_local_datetime(&later)
+            .earliest()
+            .map(|start| start.with_timezone(&Utc))
+            .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+    }
+
+    fn resolve(req: &HttpRequest) -> Result<Timezone, InvalidTimezone> {
+        if let Some(header) = req.headers().get(TIMEZONE_HEADER) {
+            let value = header
+                .to_str()
+                .map_err(|_| InvalidTimezone(String::from_utf8_lossy(header.as_bytes()).into()))?;
+            return Timezone::parse(value);
+        }
+
+        let query = actix_web::web::Query::<TimezoneParams>::from_query(req.query_string())
+            .map(|q| q.into_inner().tz)
+            .unwrap_or_default();
+        match query {
+            Some(tz) => Timezone::parse(&tz),
+            None => Ok(Timezone::default()),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Default)]
+struct TimezoneParams {
+    tz: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct InvalidTimezone(String);
+
+impl std::fmt::Display for InvalidTimezone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is not a valid timezone", self.0)
+    }
+}
+
+/// Configures how a rejected `Timezone` is reported, like
+/// `web::JsonConfig::error_handler`. Without one, the v1 envelope is used.
+#[derive(Clone, Default)]
+pub struct TimezoneConfig {
+    error_handler: Option<Arc<dyn Fn(InvalidTimezone) -> HttpResponse + Send + Sync>>,
+}
+
+impl TimezoneConfig {
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(InvalidTimezone) -> HttpResponse + Send + Sync + 'static,
+    {
+        self.error_handler = Some(Arc::new(f));
+        self
+    }
+}
+
+impl FromRequest for Timezone {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Timezone::resolve(req).map_err(|e| {
+            let message = e.to_string();
+            let response = match req
+                .app_data::<TimezoneConfig>()
+                .and_then(|config| config.error_handler.clone())
+            {
+                Some(handler) => handler(e),
+                None => HttpResponse::BadRequest().json(Response {
+                    status: "Bad Request".into(),
+                    message: message.clone(),
+                }),
+            };
+            InternalError::from_response(message, response).into()
+        }))
+    }
+}
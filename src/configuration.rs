@@ -4,12 +4,165 @@ use secrecy::{ExposeSecret, Secret};
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
+    #[serde(default)]
+    pub webhooks: WebhookSettings,
+    #[serde(default)]
+    pub reminders: ReminderSettings,
+    /// Outgoing mail. Email reminders and digests are not sent while this
+    /// is unset.
+    #[serde(default)]
+    pub email: Option<EmailSettings>,
+    #[serde(default)]
+    pub digest: DigestSettings,
+    #[serde(default)]
+    pub telemetry: TelemetrySettings,
 }
 
 #[derive(serde::Deserialize, Debug)]
 pub struct ApplicationSettings {
     pub port: u16,
     pub host: String,
+    /// Public address of this service, used for links in emails.
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+    /// Serves GraphiQL at `GET /graphql`. Meant for local development.
+    #[serde(default)]
+    pub graphiql: bool,
+}
+
+pub fn default_base_url() -> String {
+    "http://127.0.0.1:3030".into()
+}
+
+/// Delivery of outbound webhooks. Every field has a default.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct WebhookSettings {
+    /// Attempts before a delivery is marked failed.
+    pub max_attempts: i32,
+    /// Delay before the first retry; doubled for each one after that.
+    pub retry_base_seconds: u64,
+    pub retry_max_seconds: u64,
+    pub timeout_seconds: u64,
+    /// How often the worker looks for due retries.
+    pub poll_interval_milliseconds: u64,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            retry_base_seconds: 10,
+            retry_max_seconds: 3600,
+            timeout_seconds: 10,
+            poll_interval_milliseconds: 1000,
+        }
+    }
+}
+
+/// The reminder scheduler. Every field has a default.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ReminderSettings {
+    /// How often the scheduler looks for due reminders.
+    pub poll_interval_seconds: u64,
+    pub timeout_seconds: u64,
+    /// Signs webhook reminders with `X-Webhook-Signature` when set.
+    pub webhook_secret: Option<Secret<String>>,
+}
+
+impl Default for ReminderSettings {
+    fn default() -> Self {
+        Self {
+            poll_interval_seconds: 30,
+            timeout_seconds: 10,
+            webhook_secret: None,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct EmailSettings {
+    /// The `From` address, e.g. `Todo List <todo@example.com>`.
+    pub sender: String,
+    pub smtp: Option<SmtpSettings>,
+    /// Test mode: write each email to this directory as an `.eml` file
+    /// instead of sending it. Takes precedence over `smtp`.
+    pub outbox_dir: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    /// Upgrade the connection with STARTTLS; plain SMTP otherwise.
+    #[serde(default = "default_as_true")]
+    pub starttls: bool,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestFrequency {
+    Daily,
+    Weekly,
+}
+
+/// The digest of open todos mailed to each activity group with an email.
+/// Every field has a default.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DigestSettings {
+    pub enabled: bool,
+    pub frequency: DigestFrequency,
+    /// Hour of the day, in UTC, from which the digest goes out.
+    pub send_hour: u32,
+    /// Day weekly digests go out, e.g. `mon`.
+    pub weekday: chrono::Weekday,
+    /// How often the job checks whether digests are due.
+    pub poll_interval_seconds: u64,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            frequency: DigestFrequency::Daily,
+            send_hour: 8,
+            weekday: chrono::Weekday::Mon,
+            poll_interval_seconds: 300,
+        }
+    }
+}
+
+/// Trace export. Spans go to the bunyan logs only while `otlp_endpoint` is
+/// unset.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TelemetrySettings {
+    /// OTLP/gRPC collector, e.g. `http://127.0.0.1:4317`.
+    pub otlp_endpoint: Option<String>,
+    /// The `service.name` traces are reported under.
+    pub service_name: String,
+}
+
+impl Default for TelemetrySettings {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: "zero2prod".into(),
+        }
+    }
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_as_true() -> bool {
+    true
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -19,6 +172,10 @@ pub struct DatabaseSettings {
     pub port: u16,
     pub host: String,
     pub database_name: String,
+    /// Apply pending migrations when the server starts. Turn off to run
+    /// `zero2prod migrate` out of band instead.
+    #[serde(default = "default_as_true")]
+    pub migrate_on_startup: bool,
 }
 
 pub fn get_configuration() -> Result<Settings, config::ConfigError> {
@@ -1,5 +1,7 @@
+pub mod cli;
 pub mod common;
 pub mod configuration;
+pub mod metrics;
 pub mod routes;
 pub mod services;
 pub mod startup;
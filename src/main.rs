@@ -1,28 +1,44 @@
-use secrecy::ExposeSecret;
-use std::net::TcpListener;
-
-use sqlx::mysql::MySqlPoolOptions;
+use clap::Parser;
 
+use zero2prod::cli::{run, Cli, Command};
 use zero2prod::configuration::get_configuration;
-use zero2prod::startup::run;
-use zero2prod::telemetry::{get_subscriber, init_subscriber};
+use zero2prod::telemetry::{
+    get_otlp_tracer, get_subscriber_with_tracer, init_subscriber, shutdown_tracer,
+};
 
 // #[actix_web::main] // or
 #[tokio::main]
-async fn main() -> std::io::Result<()> {
-    let subscriber = get_subscriber("zero2prod".into(), "info".into(), std::io::stdout);
-    init_subscriber(subscriber);
+async fn main() {
+    let command = Cli::parse().command.unwrap_or(Command::Serve);
+    let configuration = match get_configuration() {
+        Ok(configuration) => configuration,
+        Err(e) => {
+            eprintln!("Failed to read configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let tracer = get_otlp_tracer(&configuration.telemetry).expect("Failed to set up tracing.");
+    // Admin commands may print their output, so their logs go to stderr.
+    if command.is_admin() {
+        init_subscriber(get_subscriber_with_tracer(
+            "zero2prod".into(),
+            "info".into(),
+            std::io::stderr,
+            tracer,
+        ));
+    } else {
+        init_subscriber(get_subscriber_with_tracer(
+            "zero2prod".into(),
+            "info".into(),
+            std::io::stdout,
+            tracer,
+        ));
+    }
 
-    let configuration = get_configuration().expect("Failed to read configuration.");
-    let pool = MySqlPoolOptions::new()
-        .connect_timeout(std::time::Duration::from_secs(2))
-        .connect_lazy(&configuration.database.connection_string().expose_secret())
-        .expect("Failed to connect to MySQL.");
-    print!("{:?}", pool);
-    let address = format!(
-        "{}:{}",
-        configuration.application.host, configuration.application.port
-    );
-    let listener = TcpListener::bind(address)?;
-    run(listener, pool)?.await
+    let result = run(command, configuration).await;
+    shutdown_tracer();
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,259 @@
+//! Prometheus metrics, registered in the default registry and served at
+//! `/metrics`.
+
+use std::time::Duration;
+
+use actix_web::http::StatusCode;
+use futures_util::future::BoxFuture;
+use futures_util::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Encoder, Histogram, HistogramTimer, HistogramVec, IntCounterVec,
+    IntGauge, IntGaugeVec, TextEncoder,
+};
+use sqlx::mysql::{MySql, MySqlQueryResult, MySqlRow, MySqlStatement, MySqlTypeInfo};
+use sqlx::pool::PoolConnection;
+use sqlx::{Describe, Either, Execute, Executor, MySqlPool, Transaction};
+
+use crate::telemetry::QuerySpan;
+
+/// Label for requests that matched no route, so unknown paths can't blow up
+/// the number of series.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+static HTTP_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "http_requests_total",
+        "HTTP requests served, by route pattern and status",
+        &["method", "route", "status"]
+    )
+    .expect("metric can be registered")
+});
+
+static HTTP_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "http_request_duration_seconds",
+        "Time spent serving HTTP requests, by route pattern",
+        &["method", "route"]
+    )
+    .expect("metric can be registered")
+});
+
+static DB_QUERY_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "db_query_duration_seconds",
+        "Time spent in each service function that queries the database",
+        &["function"],
+        vec![0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]
+    )
+    .expect("metric can be registered")
+});
+
+static DB_POOL_CONNECTIONS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "db_pool_connections",
+        "Connections held by the pool, by state",
+        &["state"]
+    )
+    .expect("metric can be registered")
+});
+
+static DB_POOL_ACQUIRE_DURATION: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "db_pool_acquire_seconds",
+        "Time spent waiting for a pooled connection, including waits given up on",
+        vec![0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0]
+    )
+    .expect("metric can be registered")
+});
+
+static TODOS_OPEN: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("todos_open", "Todos not yet completed").expect("metric can be registered")
+});
+
+static TODOS_OVERDUE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("todos_overdue", "Open todos past their due_at")
+        .expect("metric can be registered")
+});
+
+static ACTIVITY_GROUPS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("activity_groups", "Activity groups").expect("metric can be registered")
+});
+
+/// Counts a served request under its route pattern, e.g.
+/// `/todo-items/{id}`, rather than the raw path.
+pub fn record_request(method: &str, route: Option<&str>, status: StatusCode, elapsed: Duration) {
+    let route = route.unwrap_or(UNMATCHED_ROUTE);
+    HTTP_REQUESTS
+        .with_label_values(&[method, route, status.as_str()])
+        .inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[method, route])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// Times a service function until the returned timer is dropped.
+pub fn query_timer(function: &str) -> HistogramTimer {
+    DB_QUERY_DURATION
+        .with_label_values(&[function])
+        .start_timer()
+}
+
+/// Refreshes the pool and business gauges, then renders every metric in the
+/// Prometheus text format. Gauges that can't be read keep their last value.
+pub async fn render(pool: &MySqlPool) -> String {
+    let size = i64::from(pool.size());
+    let idle = pool.num_idle() as i64;
+    DB_POOL_CONNECTIONS.with_label_values(&["idle"]).set(idle);
+    DB_POOL_CONNECTIONS
+        .with_label_values(&["in_use"])
+        .set(size - idle);
+
+    match tokio::time::timeout(Duration::from_secs(2), refresh_business_gauges(pool)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => tracing::error!("Failed to execute query: {:?}", e),
+        Err(_) => tracing::warn!("Timed out refreshing the business gauges"),
+    }
+
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        tracing::error!("Failed to encode metrics: {:?}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+async fn refresh_business_gauges(pool: &MySqlPool) -> Result<(), sqlx::Error> {
+    let todos = sqlx::query!(
+        r#"
+        select count(*) as open, count(case when due_at < utc_timestamp() then 1 end) as overdue
+        from todos where is_active = 1
+        "#
+    )
+    .in_span(|query| query.fetch_one(TimedPool(pool)))
+    .await?;
+    TODOS_OPEN.set(todos.open);
+    TODOS_OVERDUE.set(todos.overdue);
+
+    let activities = sqlx::query!("select count(*) as count from activities")
+        .in_span(|query| query.fetch_one(TimedPool(pool)))
+        .await?;
+    ACTIVITY_GROUPS.set(activities.count);
+
+    Ok(())
+}
+
+/// Runs queries on the pool like `&MySqlPool` does, recording how long each
+/// one waited for a connection in `db_pool_acquire_seconds`.
+///
+/// Results are read into memory before the connection goes back to the pool,
+/// so `fetch` doesn't stream.
+#[derive(Debug, Clone, Copy)]
+pub struct TimedPool<'p>(pub &'p MySqlPool);
+
+impl<'p> TimedPool<'p> {
+    /// Times the wait even if the caller gives up on it, e.g. on a timeout.
+    pub async fn acquire(self) -> Result<PoolConnection<MySql>, sqlx::Error> {
+        let _timer = DB_POOL_ACQUIRE_DURATION.start_timer();
+        self.0.acquire().await
+    }
+
+    /// Like [`MySqlPool::begin`]; the recorded wait includes the `BEGIN`
+    /// round trip.
+    pub async fn begin(self) -> Result<Transaction<'static, MySql>, sqlx::Error> {
+        let _timer = DB_POOL_ACQUIRE_DURATION.start_timer();
+        self.0.begin().await
+    }
+}
+
+impl<'p> Executor<'p> for TimedPool<'_> {
+    type Database = MySql;
+
+    fn execute<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxFuture<'e, Result<MySqlQueryResult, sqlx::Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, MySql>,
+    {
+        let pool = self.0.clone();
+        Box::pin(async move { TimedPool(&pool).acquire().await?.execute(query).await })
+    }
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxStream<'e, Result<Either<MySqlQueryResult, MySqlRow>, sqlx::Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, MySql>,
+    {
+        let pool = self.0.clone();
+        let results = async move {
+            let mut connection = TimedPool(&pool).acquire().await?;
+            connection.fetch_many(query).try_collect::<Vec<_>>().await
+        };
+        Box::pin(stream::once(results).flat_map(|results| match results {
+            Ok(results) => stream::iter(results.into_iter().map(Ok)).left_stream(),
+            Err(e) => stream::once(async { Err(e) }).right_stream(),
+        }))
+    }
+
+    fn fetch_all<'e, 'q: 'e, E>(self, query: E) -> BoxFuture<'e, Result<Vec<MySqlRow>, sqlx::Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, MySql>,
+    {
+        let pool = self.0.clone();
+        Box::pin(async move { TimedPool(&pool).acquire().await?.fetch_all(query).await })
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxFuture<'e, Result<Option<MySqlRow>, sqlx::Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, MySql>,
+    {
+        let pool = self.0.clone();
+        Box::pin(async move {
+            TimedPool(&pool)
+                .acquire()
+                .await?
+                .fetch_optional(query)
+                .await
+        })
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [MySqlTypeInfo],
+    ) -> BoxFuture<'e, Result<MySqlStatement<'q>, sqlx::Error>>
+    where
+        'p: 'e,
+    {
+        let pool = self.0.clone();
+        Box::pin(async move {
+            TimedPool(&pool)
+                .acquire()
+                .await?
+                .prepare_with(sql, parameters)
+                .await
+        })
+    }
+
+    #[doc(hidden)]
+    fn describe<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+    ) -> BoxFuture<'e, Result<Describe<MySql>, sqlx::Error>>
+    where
+        'p: 'e,
+    {
+        let pool = self.0.clone();
+        Box::pin(async move { TimedPool(&pool).acquire().await?.describe(sql).await })
+    }
+}
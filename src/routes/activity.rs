@@ -1,27 +1,38 @@
-use crate::common::{Response, ResponseWithData, TitleField};
+use crate::common::{activity_not_found, Response, ResponseWithData, Timezone, TitleField};
 use crate::services::{
-    delete_activity_by_id, get_activities, get_activity_by_id, insert_activity,
-    update_activity_by_id,
+    delete_activity_by_id, get_activities, get_activity_by_id, get_todos, import_markdown,
+    insert_activity, render_markdown, update_activity_by_id, EventBus,
+};
+use crate::services::{
+    Activity, ExportedActivityGroup, ImportLineError, MarkdownImportError, NewActivity, TodoFilter,
+    UpdateActivity,
 };
-use crate::services::{Activity, NewActivity, UpdateActivity};
 use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
 use serde_json::{Map, Value};
 use sqlx::MySqlPool;
+use zero2prod_types::ActivityFormData;
 
-#[derive(serde::Deserialize)]
-struct FormData {
-    #[serde(default)]
-    title: String,
-    email: Option<String>,
-}
-
+#[utoipa::path(
+    tag = "activity-groups",
+    responses(
+        (status = 200, description = "List all activity groups", body = ResponseWithData<Vec<Activity>>),
+        (status = 500, description = "Database error"),
+    )
+)]
 #[get("/activity-groups")]
-pub async fn activity_list(_req: HttpRequest, pool: web::Data<MySqlPool>) -> HttpResponse {
+pub async fn activity_list(
+    _req: HttpRequest,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
     match get_activities(&pool).await {
         Ok(data) => HttpResponse::Ok().json(ResponseWithData::<Vec<Activity>> {
             status: "Success".into(),
             message: "Success".into(),
-            data: data,
+            data: data
+                .into_iter()
+                .map(|activity| activity.into_v1(&tz))
+                .collect(),
         }),
         Err(e) => {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -30,10 +41,21 @@ pub async fn activity_list(_req: HttpRequest, pool: web::Data<MySqlPool>) -> Htt
     }
 }
 
+#[utoipa::path(
+    tag = "activity-groups",
+    request_body = ActivityFormData,
+    responses(
+        (status = 201, description = "Activity group created", body = ResponseWithData<Activity>),
+        (status = 400, description = "Missing title", body = Response),
+        (status = 500, description = "Database error"),
+    )
+)]
 #[post("/activity-groups")]
 pub async fn activity_create(
-    form: web::Json<FormData>,
+    form: web::Json<ActivityFormData>,
     pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
 ) -> HttpResponse {
     let title = match TitleField::parse(form.0.title.clone()) {
         Ok(title) => title,
@@ -51,11 +73,11 @@ pub async fn activity_create(
         email: form.0.email,
     };
 
-    match insert_activity(&pool, &activity).await {
+    match insert_activity(&pool, &activity, &events).await {
         Ok(data) => HttpResponse::Created().json(ResponseWithData::<Activity> {
             status: "Success".into(),
             message: "Success".into(),
-            data: data,
+            data: data.into_v1(&tz),
         }),
         Err(e) => {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -64,14 +86,25 @@ pub async fn activity_create(
     }
 }
 
+#[utoipa::path(
+    tag = "activity-groups",
+    responses(
+        (status = 200, description = "Activity group found", body = ResponseWithData<Activity>),
+        (status = 404, description = "Activity group not found", body = Response),
+    )
+)]
 #[get("/activity-groups/{activity_id}")]
-pub async fn activity_detail(path: web::Path<i32>, pool: web::Data<MySqlPool>) -> HttpResponse {
+pub async fn activity_detail(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
     let activity_id = path.into_inner();
     match get_activity_by_id(activity_id, &pool).await {
         Ok(data) => HttpResponse::Ok().json(ResponseWithData::<Activity> {
             status: "Success".into(),
             message: "Success".into(),
-            data: data,
+            data: data.into_v1(&tz),
         }),
         Err(e) => {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -83,11 +116,22 @@ pub async fn activity_detail(path: web::Path<i32>, pool: web::Data<MySqlPool>) -
     }
 }
 
+#[utoipa::path(
+    tag = "activity-groups",
+    request_body = ActivityFormData,
+    responses(
+        (status = 200, description = "Activity group updated", body = ResponseWithData<Activity>),
+        (status = 400, description = "Missing title", body = Response),
+        (status = 404, description = "Activity group not found", body = Response),
+    )
+)]
 #[patch("/activity-groups/{activity_id}")]
 pub async fn activity_update(
     path: web::Path<i32>,
-    form: web::Json<FormData>,
+    form: web::Json<ActivityFormData>,
     pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
 ) -> HttpResponse {
     let activity_id = path.into_inner();
 
@@ -106,11 +150,11 @@ pub async fn activity_update(
         title,
         email: form.0.email,
     };
-    match update_activity_by_id(activity_id, &pool, &activity).await {
+    match update_activity_by_id(activity_id, &pool, &activity, &events).await {
         Ok(data) => HttpResponse::Ok().json(ResponseWithData::<Activity> {
             status: "Success".into(),
             message: "Success".into(),
-            data: data,
+            data: data.into_v1(&tz),
         }),
         Err(e) => {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -122,10 +166,21 @@ pub async fn activity_update(
     }
 }
 
+#[utoipa::path(
+    tag = "activity-groups",
+    responses(
+        (status = 200, description = "Activity group deleted", body = ResponseWithData<std::collections::HashMap<String, String>>),
+        (status = 404, description = "Activity group not found", body = Response),
+    )
+)]
 #[delete("/activity-groups/{activity_id}")]
-pub async fn activity_destroy(path: web::Path<i32>, pool: web::Data<MySqlPool>) -> HttpResponse {
+pub async fn activity_destroy(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+) -> HttpResponse {
     let activity_id = path.into_inner();
-    match delete_activity_by_id(activity_id, &pool).await {
+    match delete_activity_by_id(activity_id, &pool, &events).await {
         Ok(_) => HttpResponse::Ok().json(ResponseWithData::<Map<String, Value>> {
             status: "Success".into(),
             message: "Success".into(),
@@ -140,3 +195,81 @@ pub async fn activity_destroy(path: web::Path<i32>, pool: web::Data<MySqlPool>)
         }
     }
 }
+
+#[utoipa::path(
+    tag = "activity-groups",
+    responses(
+        (status = 200, description = "The activity group as a heading and a checklist of its todos, each ending in a `!priority` marker", content_type = "text/markdown", body = String),
+        (status = 404, description = "Activity group not found", body = Response),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[get("/activity-groups/{activity_id}.md")]
+pub async fn activity_markdown_export(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+) -> HttpResponse {
+    let activity_id = path.into_inner();
+    let activity = match get_activity_by_id(activity_id, &pool).await {
+        Ok(activity) => activity,
+        Err(sqlx::Error::RowNotFound) => return activity_not_found(activity_id),
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+    let filter = TodoFilter {
+        activity_group_id: Some(activity_id),
+        ..TodoFilter::default()
+    };
+    match get_todos(&filter, &pool).await {
+        Ok(todos) => HttpResponse::Ok()
+            .content_type("text/markdown; charset=utf-8")
+            .body(render_markdown(&activity, &todos)),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "activity-groups",
+    request_body(content = String, content_type = "text/markdown", description = "Markdown such as meeting notes. Every heading with `- [ ]` or `- [x]` items below it becomes an activity group, and each item a todo, finished when ticked. A trailing `!very-high` to `!very-low` sets the priority; prose, other headings and code blocks are skipped"),
+    responses(
+        (status = 201, description = "The activity groups created, with their todos", body = ResponseWithData<Vec<ExportedActivityGroup>>),
+        (status = 400, description = "Invalid lines by number; nothing was imported", body = ResponseWithData<Vec<ImportLineError>>),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[post("/activity-groups/import/markdown")]
+pub async fn activity_import_markdown(
+    body: String,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
+) -> HttpResponse {
+    match import_markdown(&body, &pool, &events).await {
+        Ok(groups) => {
+            HttpResponse::Created().json(ResponseWithData::<Vec<ExportedActivityGroup>> {
+                status: "Success".into(),
+                message: "Success".into(),
+                data: groups
+                    .into_iter()
+                    .map(|(activity, todos)| ExportedActivityGroup {
+                        activity_group: activity.into_v1(&tz),
+                        todos: todos.into_iter().map(|todo| todo.into_v1(&tz)).collect(),
+                    })
+                    .collect(),
+            })
+        }
+        Err(MarkdownImportError::Lines(errors, invalid)) => {
+            HttpResponse::BadRequest().json(ResponseWithData::<Vec<ImportLineError>> {
+                status: "Bad Request".into(),
+                message: match invalid {
+                    1 => "1 line is invalid; nothing was imported".into(),
+                    n => format!("{} lines are invalid; nothing was imported", n),
+                },
+                data: errors,
+            })
+        }
+        Err(MarkdownImportError::Database(_)) => HttpResponse::InternalServerError().finish(),
+    }
+}
@@ -0,0 +1,410 @@
+use crate::common::Timezone;
+use crate::services::{
+    calendar_uid_host, dav_error, delete_todo_by_id, get_activities, get_calendar_collection,
+    is_valid_object_name, is_valid_uid, parse_calendar, parse_propfind, parse_report,
+    parse_sync_token, put_calendar_object, removed_calendar_objects, render_calendar_object,
+};
+use crate::services::{
+    CalendarCollection, CalendarImportError, DavResource, EventBus, Multistatus, PropName, Report,
+    CALDAV_NS, CALDAV_ROOT, DAV_NS,
+};
+use crate::startup::ApplicationBaseUrl;
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::MySqlPool;
+
+const DAV_HEADER: &str = "1, 3, calendar-access";
+const ALLOW_HEADER: &str = "OPTIONS, GET, PUT, DELETE, PROPFIND, REPORT";
+
+/// Serves every activity group as a CalDAV calendar collection of VTODOs
+/// below `/caldav/`, for two-way sync with task apps. There is no
+/// authentication, as with the rest of the API; credentials clients send
+/// are ignored.
+pub fn caldav_routes(cfg: &mut web::ServiceConfig) {
+    let propfind = || web::method(Method::from_bytes(b"PROPFIND").expect("PROPFIND is a method"));
+    let report = || web::method(Method::from_bytes(b"REPORT").expect("REPORT is a method"));
+
+    cfg.service(web::resource("/.well-known/caldav").to(caldav_well_known))
+        .service(
+            web::resource(["/caldav", "/caldav/"])
+                .route(web::route().method(Method::OPTIONS).to(caldav_options))
+                .route(propfind().to(caldav_home_propfind)),
+        )
+        .service(
+            web::resource(["/caldav/{activity_id}", "/caldav/{activity_id}/"])
+                .route(web::route().method(Method::OPTIONS).to(caldav_options))
+                .route(propfind().to(caldav_collection_propfind))
+                .route(report().to(caldav_collection_report)),
+        )
+        .service(
+            web::resource("/caldav/{activity_id}/{name}")
+                .route(web::route().method(Method::OPTIONS).to(caldav_options))
+                .route(web::get().to(caldav_object_get))
+                .route(web::put().to(caldav_object_put))
+                .route(web::delete().to(caldav_object_delete))
+                .route(propfind().to(caldav_object_propfind)),
+        );
+}
+
+async fn caldav_well_known() -> HttpResponse {
+    HttpResponse::MovedPermanently()
+        .insert_header((header::LOCATION, CALDAV_ROOT))
+        .finish()
+}
+
+async fn caldav_options() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(("DAV", DAV_HEADER))
+        .insert_header((header::ALLOW, ALLOW_HEADER))
+        .finish()
+}
+
+async fn caldav_home_propfind(
+    req: HttpRequest,
+    body: web::Bytes,
+    pool: web::Data<MySqlPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> HttpResponse {
+    let props = match parse_propfind(&body) {
+        Ok(props) => props,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+    let mut multistatus = Multistatus::default();
+    multistatus.resource(&DavResource::Home, &props);
+    if depth(&req) == 0 {
+        return multi_status(multistatus.finish(None));
+    }
+
+    let activities = match get_activities(&pool).await {
+        Ok(activities) => activities,
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+    let host = calendar_uid_host(&base_url.0);
+    for activity in activities {
+        match get_calendar_collection(activity.id, &host, &pool).await {
+            Ok(collection) => multistatus.resource(&DavResource::Collection(&collection), &props),
+            // Deleted since it was listed.
+            Err(sqlx::Error::RowNotFound) => {}
+            Err(_) => return HttpResponse::InternalServerError().finish(),
+        }
+    }
+    multi_status(multistatus.finish(None))
+}
+
+async fn caldav_collection_propfind(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Bytes,
+    pool: web::Data<MySqlPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> HttpResponse {
+    let props = match parse_propfind(&body) {
+        Ok(props) => props,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+    let collection = match collection(path.into_inner(), &pool, &base_url).await {
+        Ok(collection) => collection,
+        Err(response) => return response,
+    };
+
+    let mut multistatus = Multistatus::default();
+    multistatus.resource(&DavResource::Collection(&collection), &props);
+    if depth(&req) > 0 {
+        for object in &collection.objects {
+            multistatus.resource(&DavResource::Object(&collection, object), &props);
+        }
+    }
+    multi_status(multistatus.finish(None))
+}
+
+async fn caldav_collection_report(
+    path: web::Path<i32>,
+    body: web::Bytes,
+    pool: web::Data<MySqlPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> HttpResponse {
+    let report = match parse_report(&body) {
+        Ok(Some(report)) => report,
+        Ok(None) => {
+            return precondition_failed(
+                StatusCode::FORBIDDEN,
+                PropName::new(DAV_NS, "supported-report"),
+            )
+        }
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+    let collection = match collection(path.into_inner(), &pool, &base_url).await {
+        Ok(collection) => collection,
+        Err(response) => return response,
+    };
+
+    let mut multistatus = Multistatus::default();
+    match report {
+        Report::CalendarQuery { props, component } => {
+            if matches!(component.as_deref(), None | Some("VTODO")) {
+                for object in &collection.objects {
+                    multistatus.resource(&DavResource::Object(&collection, object), &props);
+                }
+            }
+            multi_status(multistatus.finish(None))
+        }
+        Report::CalendarMultiget { props, hrefs } => {
+            for href in hrefs {
+                // Clients send paths or full URLs.
+                let name = href.split_once(&collection.href()).map(|(_, name)| name);
+                match name.and_then(|name| collection.object(name)) {
+                    Some(object) => {
+                        multistatus.resource(&DavResource::Object(&collection, object), &props)
+                    }
+                    None => multistatus.status(&href, "404 Not Found"),
+                }
+            }
+            multi_status(multistatus.finish(None))
+        }
+        Report::SyncCollection { props, sync_token } => {
+            let since = if sync_token.is_empty() {
+                None
+            } else {
+                match parse_sync_token(&sync_token) {
+                    Some(since) => Some(since),
+                    None => {
+                        return precondition_failed(
+                            StatusCode::FORBIDDEN,
+                            PropName::new(DAV_NS, "valid-sync-token"),
+                        )
+                    }
+                }
+            };
+            for object in &collection.objects {
+                if since.is_none_or(|since| object.modified_at() >= since) {
+                    multistatus.resource(&DavResource::Object(&collection, object), &props);
+                }
+            }
+            if let Some(since) = since {
+                match removed_calendar_objects(&collection, since, &pool).await {
+                    Ok(names) => {
+                        for name in names {
+                            let href = format!("{}{}", collection.href(), name);
+                            multistatus.status(&href, "404 Not Found");
+                        }
+                    }
+                    Err(_) => return HttpResponse::InternalServerError().finish(),
+                }
+            }
+            multi_status(multistatus.finish(Some(&collection.sync_token())))
+        }
+    }
+}
+
+async fn caldav_object_get(
+    req: HttpRequest,
+    path: web::Path<(i32, String)>,
+    pool: web::Data<MySqlPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> HttpResponse {
+    let (activity_id, name) = path.into_inner();
+    let collection = match collection(activity_id, &pool, &base_url).await {
+        Ok(collection) => collection,
+        Err(response) => return response,
+    };
+    let object = match collection.object(&name) {
+        Some(object) => object,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    let etag = object.etag();
+    if etag_matches(&req, header::IF_NONE_MATCH, &etag) {
+        return HttpResponse::NotModified()
+            .insert_header((header::ETAG, etag))
+            .finish();
+    }
+    HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .insert_header((header::ETAG, etag))
+        .body(render_calendar_object(object))
+}
+
+async fn caldav_object_put(
+    req: HttpRequest,
+    path: web::Path<(i32, String)>,
+    body: String,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    tz: Timezone,
+) -> HttpResponse {
+    let (activity_id, name) = path.into_inner();
+    if !is_valid_object_name(&name) {
+        return HttpResponse::BadRequest()
+            .body("Object names are letters, digits and -._~@+= only");
+    }
+    let collection = match collection(activity_id, &pool, &base_url).await {
+        Ok(collection) => collection,
+        Err(response) => return response,
+    };
+    let existing = collection.object(&name);
+    if let Some(response) = failed_precondition(&req, existing.map(|object| object.etag())) {
+        return response;
+    }
+
+    let mut entries = match parse_calendar(&body, &tz) {
+        Ok(entries) => entries,
+        Err(CalendarImportError::Lines(..)) => {
+            return precondition_failed(
+                StatusCode::FORBIDDEN,
+                PropName::new(CALDAV_NS, "valid-calendar-data"),
+            )
+        }
+        Err(CalendarImportError::Database(_)) => {
+            return HttpResponse::InternalServerError().finish()
+        }
+    };
+    if entries.len() != 1 {
+        return precondition_failed(
+            StatusCode::FORBIDDEN,
+            PropName::new(CALDAV_NS, "valid-calendar-object-resource"),
+        );
+    }
+    let entry = entries.remove(0);
+    if entry.uid.as_deref().is_some_and(|uid| !is_valid_uid(uid)) {
+        return precondition_failed(
+            StatusCode::FORBIDDEN,
+            PropName::new(CALDAV_NS, "valid-calendar-object-resource"),
+        );
+    }
+    let uid_taken = entry.uid.as_ref().is_some_and(|uid| {
+        collection
+            .objects
+            .iter()
+            .any(|object| object.uid == *uid && object.name != name)
+    });
+    if uid_taken {
+        return precondition_failed(
+            StatusCode::FORBIDDEN,
+            PropName::new(CALDAV_NS, "no-uid-conflict"),
+        );
+    }
+
+    let host = calendar_uid_host(&base_url.0);
+    match put_calendar_object(activity_id, &name, existing, entry, &host, &pool, &events).await {
+        // The stored object is re-rendered rather than kept as sent, so no
+        // ETag is returned (RFC 4791, section 5.3.4); clients fetch it anew.
+        Ok(_) if existing.is_some() => HttpResponse::NoContent().finish(),
+        Ok(_) => HttpResponse::Created().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+async fn caldav_object_delete(
+    req: HttpRequest,
+    path: web::Path<(i32, String)>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> HttpResponse {
+    let (activity_id, name) = path.into_inner();
+    let collection = match collection(activity_id, &pool, &base_url).await {
+        Ok(collection) => collection,
+        Err(response) => return response,
+    };
+    let object = match collection.object(&name) {
+        Some(object) => object,
+        None => return HttpResponse::NotFound().finish(),
+    };
+    if let Some(response) = failed_precondition(&req, Some(object.etag())) {
+        return response;
+    }
+
+    match delete_todo_by_id(object.todo.id, &pool, &events).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+async fn caldav_object_propfind(
+    path: web::Path<(i32, String)>,
+    body: web::Bytes,
+    pool: web::Data<MySqlPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> HttpResponse {
+    let (activity_id, name) = path.into_inner();
+    let props = match parse_propfind(&body) {
+        Ok(props) => props,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+    let collection = match collection(activity_id, &pool, &base_url).await {
+        Ok(collection) => collection,
+        Err(response) => return response,
+    };
+    let object = match collection.object(&name) {
+        Some(object) => object,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    let mut multistatus = Multistatus::default();
+    multistatus.resource(&DavResource::Object(&collection, object), &props);
+    multi_status(multistatus.finish(None))
+}
+
+async fn collection(
+    activity_id: i32,
+    pool: &MySqlPool,
+    base_url: &ApplicationBaseUrl,
+) -> Result<CalendarCollection, HttpResponse> {
+    match get_calendar_collection(activity_id, &calendar_uid_host(&base_url.0), pool).await {
+        Ok(collection) => Ok(collection),
+        Err(sqlx::Error::RowNotFound) => Err(HttpResponse::NotFound().finish()),
+        Err(_) => Err(HttpResponse::InternalServerError().finish()),
+    }
+}
+
+/// The `Depth` header: 0 or 1, with `infinity` treated as 1 since
+/// collections hold no collections. Defaults to 0.
+fn depth(req: &HttpRequest) -> u8 {
+    match req
+        .headers()
+        .get("Depth")
+        .and_then(|depth| depth.to_str().ok())
+    {
+        Some("1") | Some("infinity") => 1,
+        _ => 0,
+    }
+}
+
+/// Whether an `If-Match` or `If-None-Match` header lists `etag` or `*`.
+fn etag_matches(req: &HttpRequest, name: header::HeaderName, etag: &str) -> bool {
+    req.headers()
+        .get_all(name)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|candidate| {
+            let candidate = candidate.trim();
+            candidate == "*" || candidate.trim_start_matches("W/") == etag
+        })
+}
+
+/// A `412 Precondition Failed` when the request's `If-Match` or
+/// `If-None-Match` does not hold for the object's current etag.
+fn failed_precondition(req: &HttpRequest, etag: Option<String>) -> Option<HttpResponse> {
+    let if_match = req.headers().contains_key(header::IF_MATCH);
+    let failed = match etag {
+        Some(etag) => {
+            (if_match && !etag_matches(req, header::IF_MATCH, &etag))
+                || etag_matches(req, header::IF_NONE_MATCH, &etag)
+        }
+        None => if_match,
+    };
+    failed.then(|| HttpResponse::PreconditionFailed().finish())
+}
+
+fn precondition_failed(status: StatusCode, precondition: PropName) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type("application/xml; charset=utf-8")
+        .body(dav_error(&precondition))
+}
+
+fn multi_status(body: String) -> HttpResponse {
+    HttpResponse::build(StatusCode::MULTI_STATUS)
+        .content_type("application/xml; charset=utf-8")
+        .body(body)
+}
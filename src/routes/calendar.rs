@@ -0,0 +1,159 @@
+use crate::common::{activity_not_found, Response, ResponseWithData, Timezone};
+use crate::services::{
+    calendar_feed, calendar_feed_matches, calendar_feed_token, calendar_uid_host,
+    get_activity_by_id, get_calendar_objects, import_calendar, render_calendar,
+    revoke_calendar_feed,
+};
+use crate::services::{CalendarFeed, CalendarImportError, EventBus, ImportLineError, Todo};
+use crate::startup::ApplicationBaseUrl;
+use actix_web::{delete, get, post, web, HttpResponse};
+use chrono::Utc;
+use sqlx::MySqlPool;
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CalendarParams {
+    /// The token from the activity group's subscribe URL.
+    token: String,
+}
+
+#[utoipa::path(
+    tag = "calendars",
+    params(CalendarParams),
+    responses(
+        (status = 200, description = "The activity group's todos as RFC 5545 VTODO components", body = String, content_type = "text/calendar"),
+        (status = 404, description = "Activity group not found, or the token is not its feed token", body = Response),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[get("/activity-groups/{activity_id}/calendar.ics")]
+pub async fn calendar_export(
+    path: web::Path<i32>,
+    params: web::Query<CalendarParams>,
+    pool: web::Data<MySqlPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> HttpResponse {
+    let activity_id = path.into_inner();
+    match calendar_feed_matches(activity_id, &params.token, &pool).await {
+        Ok(true) => {}
+        Ok(false) => return calendar_not_found(activity_id),
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    }
+    let activity = match get_activity_by_id(activity_id, &pool).await {
+        Ok(activity) => activity,
+        Err(sqlx::Error::RowNotFound) => return calendar_not_found(activity_id),
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+    match get_calendar_objects(activity_id, &calendar_uid_host(&base_url.0), &pool).await {
+        Ok(objects) => HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            .body(render_calendar(&activity.title, &objects, Utc::now())),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[utoipa::path(
+    tag = "calendars",
+    responses(
+        (status = 200, description = "The subscribe URL of the activity group's calendar, created on first use", body = ResponseWithData<CalendarFeed>),
+        (status = 404, description = "Activity group not found", body = Response),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[post("/activity-groups/{activity_id}/calendar-feed")]
+pub async fn calendar_feed_create(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> HttpResponse {
+    let activity_id = path.into_inner();
+    match get_activity_by_id(activity_id, &pool).await {
+        Ok(_) => {}
+        Err(sqlx::Error::RowNotFound) => return activity_not_found(activity_id),
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+    match calendar_feed_token(activity_id, &pool).await {
+        Ok(token) => HttpResponse::Ok().json(ResponseWithData::<CalendarFeed> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: calendar_feed(&base_url.0, activity_id, &token),
+        }),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[utoipa::path(
+    tag = "calendars",
+    responses(
+        (status = 200, description = "The subscribe URL no longer works; creating the feed again gives a new one", body = Response),
+        (status = 404, description = "The activity group has no calendar feed", body = Response),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[delete("/activity-groups/{activity_id}/calendar-feed")]
+pub async fn calendar_feed_destroy(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+) -> HttpResponse {
+    let activity_id = path.into_inner();
+    match revoke_calendar_feed(activity_id, &pool).await {
+        Ok(true) => HttpResponse::Ok().json(Response {
+            status: "Success".into(),
+            message: "Success".into(),
+        }),
+        Ok(false) => calendar_not_found(activity_id),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[utoipa::path(
+    tag = "calendars",
+    request_body(content = String, content_type = "text/calendar", description = "An iCalendar object. Each VTODO becomes a todo: SUMMARY is its title, PRIORITY 1-2, 3-4, 5, 6-7 and 8-9 map to very-high to very-low, STATUS:COMPLETED finishes it, and DUE, CREATED and LAST-MODIFIED set its timestamps. Floating times and dates are in the caller's timezone"),
+    responses(
+        (status = 201, description = "Every VTODO was added to the activity group", body = ResponseWithData<Vec<Todo>>),
+        (status = 400, description = "Invalid VTODOs by the line they begin on; nothing was imported", body = ResponseWithData<Vec<ImportLineError>>),
+        (status = 404, description = "Activity group not found", body = Response),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[post("/activity-groups/{activity_id}/calendar.ics")]
+pub async fn calendar_import(
+    path: web::Path<i32>,
+    body: String,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
+) -> HttpResponse {
+    let activity_id = path.into_inner();
+    match get_activity_by_id(activity_id, &pool).await {
+        Ok(_) => {}
+        Err(sqlx::Error::RowNotFound) => return activity_not_found(activity_id),
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+
+    match import_calendar(&body, activity_id, &tz, &pool, &events).await {
+        Ok(todos) => HttpResponse::Created().json(ResponseWithData::<Vec<Todo>> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: todos.into_iter().map(|todo| todo.into_v1(&tz)).collect(),
+        }),
+        Err(CalendarImportError::Lines(errors, invalid)) => {
+            HttpResponse::BadRequest().json(ResponseWithData::<Vec<ImportLineError>> {
+                status: "Bad Request".into(),
+                message: match invalid {
+                    1 => "1 VTODO is invalid; nothing was imported".into(),
+                    n => format!("{} VTODOs are invalid; nothing was imported", n),
+                },
+                data: errors,
+            })
+        }
+        Err(CalendarImportError::Database(_)) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+fn calendar_not_found(activity_id: i32) -> HttpResponse {
+    HttpResponse::NotFound().json(Response {
+        status: "Not Found".into(),
+        message: format!("Calendar of activity with ID {} Not Found", activity_id),
+    })
+}
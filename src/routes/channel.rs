@@ -0,0 +1,272 @@
+use std::sync::Arc;
+
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use actix_ws::{Message, MessageStream, Session};
+use futures_util::StreamExt;
+use sqlx::MySqlPool;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+use crate::common::{activity_not_found, in_current_request, Response, Timezone};
+use crate::services::{
+    get_activity_by_id, get_todo_by_id, insert_todo, update_todo_by_id, EventBus, Member, NewTodo,
+    Presence, PresenceChange, StoredEvent, Todo, UpdateTodo,
+};
+use zero2prod_types::{ChannelCommand, ChannelMessage, TodoFormData};
+
+/// Longest display name kept from `?name=`, in characters.
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ChannelParams {
+    /// Display name announced to the other members.
+    name: Option<String>,
+}
+
+#[utoipa::path(
+    tag = "channels",
+    params(ChannelParams),
+    responses(
+        (status = 101, description = "Switched to WebSocket. Clients send ChannelCommand and receive ChannelMessage JSON text frames"),
+        (status = 404, description = "Activity group not found", body = Response),
+    )
+)]
+#[get("/ws/activity-groups/{activity_id}")]
+#[allow(clippy::too_many_arguments)]
+pub async fn activity_channel(
+    req: HttpRequest,
+    body: web::Payload,
+    path: web::Path<i32>,
+    params: web::Query<ChannelParams>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    presence: web::Data<Presence>,
+    tz: Timezone,
+) -> HttpResponse {
+    let activity_group_id = path.into_inner();
+    if let Err(e) = get_activity_by_id(activity_group_id, &pool).await {
+        tracing::error!("Failed to execute query: {:?}", e);
+        return activity_not_found(activity_group_id);
+    }
+
+    let (response, session, messages) = match actix_ws::handle(&req, body) {
+        Ok(handshake) => handshake,
+        Err(e) => return e.error_response(),
+    };
+
+    let member = Member {
+        connection_id: Uuid::new_v4().to_string(),
+        name: display_name(params.into_inner().name),
+    };
+    let channel = Channel {
+        activity_group_id,
+        pool: pool.into_inner(),
+        events: events.into_inner(),
+        tz,
+        session,
+    };
+    actix_web::rt::spawn(in_current_request(channel.run(
+        member,
+        messages,
+        presence.into_inner(),
+    )));
+
+    response
+}
+
+fn display_name(name: Option<String>) -> String {
+    match name.as_deref().map(str::trim) {
+        Some(name) if !name.is_empty() => name.chars().take(MAX_NAME_LEN).collect(),
+        _ => "anonymous".into(),
+    }
+}
+
+struct Channel {
+    activity_group_id: i32,
+    pool: Arc<MySqlPool>,
+    events: Arc<EventBus>,
+    tz: Timezone,
+    session: Session,
+}
+
+impl Channel {
+    async fn run(mut self, member: Member, mut messages: MessageStream, presence: Arc<Presence>) {
+        let joined = presence.join(self.activity_group_id, member);
+        let mut presence_changes = joined.receiver;
+        let subscription = self.events.subscribe(None);
+        let mut cursor = subscription.newest_id;
+        let mut changes = subscription.receiver;
+
+        let welcome = ChannelMessage::Welcome {
+            connection_id: joined.guard.member.connection_id.clone(),
+            members: joined.members,
+        };
+        if self.send(&welcome).await.is_err() {
+            return;
+        }
+
+        loop {
+            let result = tokio::select! {
+                message = messages.next() => match message {
+                    Some(Ok(Message::Text(text))) => self.handle_command(&text).await,
+                    Some(Ok(Message::Ping(bytes))) => self.session.pong(&bytes).await,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => Ok(()),
+                },
+                change = changes.recv() => match change {
+                    Ok(stored) => {
+                        cursor = stored.id;
+                        self.forward(&stored).await
+                    }
+                    // Fell behind the channel: pick up again from the buffer.
+                    Err(RecvError::Lagged(_)) => {
+                        let subscription = self.events.subscribe(Some(cursor));
+                        changes = subscription.receiver;
+                        self.forward_missed(subscription.missed, &mut cursor).await
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                change = presence_changes.recv() => match change {
+                    Ok(PresenceChange::Joined(member)) => {
+                        self.send(&ChannelMessage::Joined { member }).await
+                    }
+                    Ok(PresenceChange::Left(member)) => {
+                        self.send(&ChannelMessage::Left { member }).await
+                    }
+                    Err(RecvError::Lagged(_)) => Ok(()),
+                    Err(RecvError::Closed) => break,
+                },
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+
+        drop(joined.guard);
+        let _ = self.session.close(None).await;
+    }
+
+    async fn forward_missed(
+        &mut self,
+        missed: Vec<Arc<StoredEvent>>,
+        cursor: &mut u64,
+    ) -> Result<(), actix_ws::Closed> {
+        for stored in missed {
+            *cursor = stored.id;
+            self.forward(&stored).await?;
+        }
+        Ok(())
+    }
+
+    async fn forward(&mut self, stored: &StoredEvent) -> Result<(), actix_ws::Closed> {
+        if stored.event.activity_group_id != self.activity_group_id {
+            return Ok(());
+        }
+        self.send(&ChannelMessage::Change {
+            event: stored.event.clone(),
+        })
+        .await
+    }
+
+    async fn handle_command(&mut self, text: &str) -> Result<(), actix_ws::Closed> {
+        let reply = match serde_json::from_str::<ChannelCommand>(text) {
+            Ok(command) => self.execute(command).await,
+            Err(e) => ChannelMessage::Error {
+                request_id: None,
+                message: format!("Invalid command: {}", e),
+            },
+        };
+        self.send(&reply).await
+    }
+
+    /// Runs a command through the same validation and services as the v1
+    /// todo handlers.
+    async fn execute(&self, command: ChannelCommand) -> ChannelMessage {
+        let (request_id, result) = match command {
+            ChannelCommand::Create {
+                request_id,
+                mut todo,
+            } => {
+                todo.activity_group_id = Some(self.activity_group_id);
+                (request_id, self.create(todo).await)
+            }
+            ChannelCommand::Update {
+                request_id,
+                id,
+                changes,
+            } => (request_id, self.update(id, UpdateTodo::from(changes)).await),
+            ChannelCommand::Toggle { request_id, id } => (request_id, self.toggle(id).await),
+        };
+
+        match result {
+            Ok(todo) => ChannelMessage::Result { request_id, todo },
+            Err(message) => ChannelMessage::Error {
+                request_id,
+                message,
+            },
+        }
+    }
+
+    async fn create(&self, form: TodoFormData) -> Result<Todo, String> {
+        let todo = NewTodo::parse(form)?;
+        insert_todo(&self.pool, &todo, &self.events)
+            .await
+            .map(|todo| todo.into_v1(&self.tz))
+            .map_err(|e| {
+                tracing::error!("Failed to execute query: {:?}", e);
+                "Failed to create todo".to_string()
+            })
+    }
+
+    async fn update(&self, todo_id: i32, changes: UpdateTodo) -> Result<Todo, String> {
+        // Members may only see this group, so todos can't leave it.
+        if changes
+            .activity_group_id
+            .is_some_and(|id| id != self.activity_group_id)
+        {
+            return Err(format!(
+                "activity_group_id must be {} on this channel",
+                self.activity_group_id
+            ));
+        }
+        self.find_todo(todo_id).await?;
+        update_todo_by_id(todo_id, &self.pool, &changes, &self.events)
+            .await
+            .map(|todo| todo.into_v1(&self.tz))
+            .map_err(|e| {
+                tracing::error!("Failed to execute query: {:?}", e);
+                format!("Todo with ID {} Not Found", todo_id)
+            })
+    }
+
+    async fn toggle(&self, todo_id: i32) -> Result<Todo, String> {
+        let is_active = self.find_todo(todo_id).await?;
+        let changes = UpdateTodo {
+            title: None,
+            activity_group_id: None,
+            is_active: Some(!is_active),
+            priority: None,
+            due_at: None,
+        };
+        self.update(todo_id, changes).await
+    }
+
+    /// Checks that the todo belongs to this channel's group, returning
+    /// whether it is active.
+    async fn find_todo(&self, todo_id: i32) -> Result<bool, String> {
+        match get_todo_by_id(todo_id, &self.pool).await {
+            Ok(todo) if todo.activity_group_id == self.activity_group_id => Ok(todo.is_active),
+            Ok(_) => Err(format!("Todo with ID {} Not Found", todo_id)),
+            Err(e) => {
+                tracing::error!("Failed to execute query: {:?}", e);
+                Err(format!("Todo with ID {} Not Found", todo_id))
+            }
+        }
+    }
+
+    async fn send(&mut self, message: &ChannelMessage) -> Result<(), actix_ws::Closed> {
+        let text = serde_json::to_string(message).expect("channel messages serialize to JSON");
+        self.session.text(text).await
+    }
+}
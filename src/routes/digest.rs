@@ -0,0 +1,71 @@
+use crate::common::Response;
+use crate::services::{escape_html, get_activity_by_id, opt_out_of_digest};
+use actix_web::{get, post, web, HttpResponse};
+use sqlx::MySqlPool;
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct OptOutParams {
+    /// The token from the link at the bottom of a digest.
+    token: String,
+}
+
+/// Where the link at the bottom of a digest leads. Nothing changes until the
+/// form on the page is submitted, so mail scanners following the link don't
+/// opt anyone out.
+#[utoipa::path(
+    tag = "digests",
+    params(OptOutParams),
+    responses(
+        (status = 200, description = "A page asking to confirm, with a form that POSTs the token to `/digests/opt-out`", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/digests/opt-out")]
+pub async fn digest_opt_out_confirm(params: web::Query<OptOutParams>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(format!(
+            "<!DOCTYPE html>\n\
+             <title>Stop digests</title>\n\
+             <form method=\"post\" action=\"/digests/opt-out\">\n\
+             <input type=\"hidden\" name=\"token\" value=\"{}\">\n\
+             <p>Stop sending digests for this activity group?</p>\n\
+             <button type=\"submit\">Stop these emails</button>\n\
+             </form>\n",
+            escape_html(&params.token)
+        ))
+}
+
+#[utoipa::path(
+    tag = "digests",
+    request_body(content = String, content_type = "application/x-www-form-urlencoded", description = "`token`: the token from the link at the bottom of a digest"),
+    responses(
+        (status = 200, description = "The activity group gets no more digests", body = Response),
+        (status = 404, description = "Unknown token", body = Response),
+    )
+)]
+#[post("/digests/opt-out")]
+pub async fn digest_opt_out(
+    form: web::Form<OptOutParams>,
+    pool: web::Data<MySqlPool>,
+) -> HttpResponse {
+    let activity_group_id = match opt_out_of_digest(&form.token, &pool).await {
+        Ok(activity_group_id) => activity_group_id,
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            return HttpResponse::NotFound().json(Response {
+                status: "Not Found".into(),
+                message: "Digest opt-out token Not Found".into(),
+            });
+        }
+    };
+
+    let message = match get_activity_by_id(activity_group_id, &pool).await {
+        Ok(activity) => format!("{} will no longer send digests", activity.title),
+        Err(_) => "Digests will no longer be sent".into(),
+    };
+    HttpResponse::Ok().json(Response {
+        status: "Success".into(),
+        message,
+    })
+}
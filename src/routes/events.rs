@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::web::Bytes;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use futures_util::{stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+
+use crate::common::Response;
+use crate::services::{ChangeEvent, EventBus, StoredEvent};
+
+/// Idle streams get a comment this often so proxies don't close them.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Reconnection delay suggested to clients, in milliseconds.
+const RETRY_MS: u64 = 3000;
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct EventParams {
+    /// Only changes to this group and its todos.
+    activity_group_id: Option<i32>,
+}
+
+#[utoipa::path(
+    tag = "events",
+    params(
+        EventParams,
+        ("Last-Event-ID" = Option<u64>, Header, description = "Resume after this event id"),
+    ),
+    responses(
+        (status = 200, description = "`text/event-stream` of changes; each `data` line is a JSON ChangeEvent and the event name is e.g. `todo.created`", body = ChangeEvent, content_type = "text/event-stream"),
+        (status = 400, description = "Malformed Last-Event-ID", body = Response),
+    )
+)]
+#[get("/events")]
+pub async fn event_stream(
+    req: HttpRequest,
+    params: web::Query<EventParams>,
+    events: web::Data<EventBus>,
+) -> HttpResponse {
+    let last_event_id = match req.headers().get("Last-Event-ID") {
+        Some(header) => match header.to_str().ok().and_then(|id| id.trim().parse().ok()) {
+            Some(id) => Some(id),
+            None => {
+                return HttpResponse::BadRequest().json(Response {
+                    status: "Bad Request".into(),
+                    message: "Last-Event-ID must be an event id".into(),
+                })
+            }
+        },
+        None => None,
+    };
+
+    let subscription = events.subscribe(last_event_id);
+    let state = EventStream {
+        bus: events.into_inner(),
+        activity_group_id: params.activity_group_id,
+        cursor: last_event_id.unwrap_or(subscription.newest_id),
+        pending: subscription.missed.into(),
+        receiver: subscription.receiver,
+    };
+
+    let retry = Bytes::from(format!("retry: {}\n\n", RETRY_MS));
+    let body = stream::once(async { Ok::<_, actix_web::Error>(retry) }).chain(stream::unfold(
+        state,
+        |mut state| async move {
+            let frame = state.next_frame().await?;
+            Some((Ok(frame), state))
+        },
+    ));
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(body)
+}
+
+struct EventStream {
+    bus: Arc<EventBus>,
+    activity_group_id: Option<i32>,
+    /// Id of the last event handled, sent or filtered out.
+    cursor: u64,
+    pending: VecDeque<Arc<StoredEvent>>,
+    receiver: Receiver<Arc<StoredEvent>>,
+}
+
+impl EventStream {
+    /// The next chunk to send, or `None` once the bus is gone.
+    async fn next_frame(&mut self) -> Option<Bytes> {
+        loop {
+            if let Some(stored) = self.pending.pop_front() {
+                if stored.id <= self.cursor {
+                    continue;
+                }
+                self.cursor = stored.id;
+                if self.wants(&stored.event) {
+                    return Some(frame(&stored));
+                }
+                continue;
+            }
+
+            match tokio::time::timeout(KEEP_ALIVE, self.receiver.recv()).await {
+                Err(_) => return Some(Bytes::from_static(b": keep-alive\n\n")),
+                Ok(Ok(stored)) => self.pending.push_back(stored),
+                // Fell behind the channel: pick up again from the buffer.
+                Ok(Err(RecvError::Lagged(_))) => {
+                    let subscription = self.bus.subscribe(Some(self.cursor));
+                    self.pending.extend(subscription.missed);
+                    self.receiver = subscription.receiver;
+                }
+                Ok(Err(RecvError::Closed)) => return None,
+            }
+        }
+    }
+
+    fn wants(&self, event: &ChangeEvent) -> bool {
+        self.activity_group_id
+            .is_none_or(|id| event.activity_group_id == id)
+    }
+}
+
+fn frame(stored: &StoredEvent) -> Bytes {
+    let data = serde_json::to_string(&stored.event).expect("events serialize to JSON");
+    Bytes::from(format!(
+        "id: {}\nevent: {}\ndata: {}\n\n",
+        stored.id,
+        stored.event.name(),
+        data
+    ))
+}
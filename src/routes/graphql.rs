@@ -0,0 +1,27 @@
+use crate::common::Timezone;
+use crate::services::{graphql_request, GraphqlSchema};
+use actix_web::{get, post, web, HttpResponse};
+use async_graphql::http::GraphiQLSource;
+use sqlx::MySqlPool;
+
+/// Runs a GraphQL query or mutation. Timestamps are in the caller's
+/// timezone, as in the REST API; errors come back in the response body.
+#[post("/graphql")]
+pub async fn graphql_execute(
+    request: web::Json<async_graphql::Request>,
+    schema: web::Data<GraphqlSchema>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
+    let request = graphql_request(request.into_inner(), &pool, tz);
+    HttpResponse::Ok().json(schema.execute(request).await)
+}
+
+/// GraphiQL, for trying out queries. Only served with
+/// `application.graphiql` on.
+#[get("/graphql")]
+pub async fn graphiql() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(GraphiQLSource::build().endpoint("/graphql").finish())
+}
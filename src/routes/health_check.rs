@@ -1,10 +1,44 @@
-use actix_web::{HttpRequest, HttpResponse, Responder};
+use crate::services::{check_readiness, HealthReport, HealthStatus, READINESS_TIMEOUT};
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use sqlx::MySqlPool;
+use std::collections::BTreeMap;
 
 pub async fn greet(req: HttpRequest) -> impl Responder {
     let name = req.match_info().get("name").unwrap_or("World");
     format!("Hello {}!", &name)
 }
 
+#[utoipa::path(
+    get,
+    path = "/health_check",
+    tag = "health",
+    responses((status = 200, description = "Service is up"))
+)]
 pub async fn health_check(_req: HttpRequest) -> HttpResponse {
     HttpResponse::Ok().finish()
 }
+
+#[utoipa::path(
+    tag = "health",
+    responses((status = 200, description = "The process is running", body = HealthReport))
+)]
+#[get("/health/live")]
+pub async fn health_live() -> HttpResponse {
+    HttpResponse::Ok().json(HealthReport::new(BTreeMap::new()))
+}
+
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, description = "MySQL answers and the schema is up to date", body = HealthReport),
+        (status = 503, description = "A dependency is down; stop routing traffic here", body = HealthReport),
+    )
+)]
+#[get("/health/ready")]
+pub async fn health_ready(pool: web::Data<MySqlPool>) -> HttpResponse {
+    let report = check_readiness(&pool, READINESS_TIMEOUT).await;
+    match report.status {
+        HealthStatus::Up => HttpResponse::Ok().json(report),
+        HealthStatus::Down => HttpResponse::ServiceUnavailable().json(report),
+    }
+}
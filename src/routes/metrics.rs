@@ -0,0 +1,13 @@
+use actix_web::{get, web, HttpResponse};
+use sqlx::MySqlPool;
+
+#[utoipa::path(
+    tag = "health",
+    responses((status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"))
+)]
+#[get("/metrics")]
+pub async fn prometheus_metrics(pool: web::Data<MySqlPool>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(prometheus::TEXT_FORMAT)
+        .body(crate::metrics::render(&pool).await)
+}
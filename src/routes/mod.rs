@@ -1,9 +1,34 @@
-mod health_check;
-// mod subscriptions;
 mod activity;
+mod caldav;
+mod calendar;
+mod channel;
+mod digest;
+mod events;
+mod graphql;
+mod health_check;
+mod metrics;
+mod openapi;
+mod reminder;
+mod search;
+mod subscriptions;
 mod todo;
+mod transfer;
+pub mod v2;
+mod webhook;
 
-pub use health_check::*;
-// pub use subscriptions::*;
 pub use activity::*;
+pub use caldav::*;
+pub use calendar::*;
+pub use channel::*;
+pub use digest::*;
+pub use events::*;
+pub use graphql::*;
+pub use health_check::*;
+pub use metrics::*;
+pub use openapi::*;
+pub use reminder::*;
+pub use search::*;
+pub use subscriptions::*;
 pub use todo::*;
+pub use transfer::*;
+pub use webhook::*;
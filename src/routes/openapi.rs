@@ -0,0 +1,91 @@
+use actix_web::{get, HttpResponse};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Todo List API",
+        description = "Timestamps are RFC 3339. They are rendered in UTC unless the request names \
+            an IANA timezone in the `X-Timezone` header or the `tz` query parameter, which also \
+            applies to date filters such as `created_from`/`created_to`. Every response carries an \
+            `X-Request-Id` header, echoing the request's own when it sent one, and v1 error bodies \
+            repeat it as `request_id`."
+    ),
+    paths(
+        crate::routes::health_check,
+        crate::routes::health_live,
+        crate::routes::health_ready,
+        crate::routes::prometheus_metrics,
+        crate::routes::activity_list,
+        crate::routes::activity_create,
+        crate::routes::activity_detail,
+        crate::routes::activity_update,
+        crate::routes::activity_destroy,
+        crate::routes::activity_markdown_export,
+        crate::routes::activity_import_markdown,
+        crate::routes::todo_list,
+        crate::routes::todo_list_csv,
+        crate::routes::todo_import_csv,
+        crate::routes::todo_txt_export,
+        crate::routes::todo_txt_import,
+        crate::routes::calendar_export,
+        crate::routes::calendar_import,
+        crate::routes::calendar_feed_create,
+        crate::routes::calendar_feed_destroy,
+        crate::routes::todo_create,
+        crate::routes::todo_detail,
+        crate::routes::todo_update,
+        crate::routes::todo_destroy,
+        crate::routes::search_list,
+        crate::routes::event_stream,
+        crate::routes::activity_channel,
+        crate::routes::webhook_list,
+        crate::routes::webhook_create,
+        crate::routes::webhook_detail,
+        crate::routes::webhook_update,
+        crate::routes::webhook_destroy,
+        crate::routes::webhook_deliveries,
+        crate::routes::reminder_list,
+        crate::routes::reminder_create,
+        crate::routes::reminder_destroy,
+        crate::routes::digest_opt_out_confirm,
+        crate::routes::digest_opt_out,
+        crate::routes::subscribe,
+        crate::routes::subscription_confirm,
+        crate::routes::export_data,
+        crate::routes::import_data,
+        crate::routes::import_trello,
+        crate::routes::import_todoist,
+        crate::routes::v2::activity_list,
+        crate::routes::v2::activity_create,
+        crate::routes::v2::activity_detail,
+        crate::routes::v2::activity_update,
+        crate::routes::v2::activity_destroy,
+        crate::routes::v2::todo_list,
+        crate::routes::v2::todo_create,
+        crate::routes::v2::todo_detail,
+        crate::routes::v2::todo_update,
+        crate::routes::v2::todo_destroy,
+    ),
+    tags(
+        (name = "health", description = "Service health"),
+        (name = "activity-groups", description = "Activity groups (v1, also served under /v1)"),
+        (name = "todo-items", description = "Todo items belonging to an activity group (v1, also served under /v1)"),
+        (name = "search", description = "Keyword search over todo and activity group titles"),
+        (name = "events", description = "Server-Sent Events stream of todo and activity group changes"),
+        (name = "channels", description = "WebSocket collaboration channel per activity group"),
+        (name = "webhooks", description = "Outbound webhooks: HMAC-SHA256 signed POSTs of change events, retried with exponential backoff"),
+        (name = "reminders", description = "Reminders on todos, sent once by log, email or webhook at a set time or before the todo's due_at"),
+        (name = "digests", description = "Daily or weekly email digests of open, overdue and recently completed todos, sent to activity groups with an email"),
+        (name = "subscriptions", description = "Newsletter subscriptions with double opt-in: a confirmation link is emailed before the subscription counts"),
+        (name = "calendars", description = "Activity groups as RFC 5545 calendars of VTODOs: a token-protected subscribe URL and .ics import"),
+        (name = "transfer", description = "Versioned JSON export of every activity group and todo, and transactional import with merge, replace and dry-run modes; imports from Trello and Todoist JSON exports"),
+        (name = "v2", description = "Activity groups and todo items with RFC 3339 timestamps and error objects"),
+    )
+)]
+pub struct ApiDoc;
+
+#[get("/openapi.json")]
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
@@ -0,0 +1,133 @@
+use crate::common::{Response, ResponseWithData, Timezone};
+use crate::services::{
+    delete_reminder_by_id, get_activity_by_id, get_reminders_by_todo, get_todo_by_id,
+    insert_reminder,
+};
+use crate::services::{NewReminder, Reminder, ReminderFormData};
+use actix_web::{delete, get, post, web, HttpResponse};
+use serde_json::{Map, Value};
+use sqlx::MySqlPool;
+
+#[utoipa::path(
+    tag = "reminders",
+    responses(
+        (status = 200, description = "Reminders on the todo, fired or not", body = ResponseWithData<Vec<Reminder>>),
+        (status = 404, description = "Todo not found", body = Response),
+    )
+)]
+#[get("/todo-items/{todo_id}/reminders")]
+pub async fn reminder_list(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
+    let todo_id = path.into_inner();
+    if let Err(e) = get_todo_by_id(todo_id, &pool).await {
+        tracing::error!("Failed to execute query: {:?}", e);
+        return todo_not_found(todo_id);
+    }
+
+    match get_reminders_by_todo(todo_id, &pool).await {
+        Ok(data) => HttpResponse::Ok().json(ResponseWithData::<Vec<Reminder>> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: data
+                .into_iter()
+                .map(|reminder| reminder.into_v1(&tz))
+                .collect(),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "reminders",
+    request_body = ReminderFormData,
+    responses(
+        (status = 201, description = "Reminder created", body = ResponseWithData<Reminder>),
+        (status = 400, description = "Missing or conflicting time, or missing or invalid recipient", body = Response),
+        (status = 404, description = "Todo not found", body = Response),
+    )
+)]
+#[post("/todo-items/{todo_id}/reminders")]
+pub async fn reminder_create(
+    path: web::Path<i32>,
+    form: web::Json<ReminderFormData>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
+    let todo_id = path.into_inner();
+    let todo = match get_todo_by_id(todo_id, &pool).await {
+        Ok(todo) => todo,
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            return todo_not_found(todo_id);
+        }
+    };
+    let activity = match get_activity_by_id(todo.activity_group_id, &pool).await {
+        Ok(activity) => activity,
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let reminder = match NewReminder::parse(form.into_inner(), &todo, &activity) {
+        Ok(reminder) => reminder,
+        Err(message) => {
+            let error = Response {
+                status: "Bad Request".into(),
+                message,
+            };
+            return HttpResponse::BadRequest().json(error);
+        }
+    };
+
+    match insert_reminder(todo_id, &pool, &reminder).await {
+        Ok(data) => HttpResponse::Created().json(ResponseWithData::<Reminder> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: data.into_v1(&tz),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "reminders",
+    responses(
+        (status = 200, description = "Reminder deleted", body = ResponseWithData<std::collections::HashMap<String, String>>),
+        (status = 404, description = "Reminder not found", body = Response),
+    )
+)]
+#[delete("/reminders/{reminder_id}")]
+pub async fn reminder_destroy(path: web::Path<i32>, pool: web::Data<MySqlPool>) -> HttpResponse {
+    let reminder_id = path.into_inner();
+    match delete_reminder_by_id(reminder_id, &pool).await {
+        Ok(_) => HttpResponse::Ok().json(ResponseWithData::<Map<String, Value>> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: Map::<String, Value>::new(),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::NotFound().json(Response {
+                status: "Not Found".into(),
+                message: format!("Reminder with ID {} Not Found", reminder_id),
+            })
+        }
+    }
+}
+
+fn todo_not_found(todo_id: i32) -> HttpResponse {
+    HttpResponse::NotFound().json(Response {
+        status: "Not Found".into(),
+        message: format!("Todo with ID {} Not Found", todo_id),
+    })
+}
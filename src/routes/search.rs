@@ -0,0 +1,63 @@
+use crate::common::{Response, ResponseWithData};
+use crate::services::{search, SearchHit, SearchQuery};
+use actix_web::{get, web, HttpResponse};
+use sqlx::MySqlPool;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SearchParams {
+    /// Keywords matched against todo and activity group titles.
+    q: String,
+    /// Only todos of this group, and only this group itself.
+    activity_group_id: Option<i32>,
+    /// Only todos with this status; activity groups are left out when set.
+    is_active: Option<bool>,
+    /// Maximum number of hits, 20 by default and at most 100.
+    limit: Option<i64>,
+}
+
+#[utoipa::path(
+    tag = "search",
+    params(SearchParams),
+    responses(
+        (status = 200, description = "Hits ordered by relevance", body = ResponseWithData<Vec<SearchHit>>),
+        (status = 400, description = "Empty query", body = Response),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[get("/search")]
+pub async fn search_list(
+    params: web::Query<SearchParams>,
+    pool: web::Data<MySqlPool>,
+) -> HttpResponse {
+    let params = params.into_inner();
+    let terms = SearchQuery::parse_terms(&params.q);
+    if terms.is_empty() {
+        return HttpResponse::BadRequest().json(Response {
+            status: "Bad Request".into(),
+            message: "q cannot be empty".into(),
+        });
+    }
+
+    let query = SearchQuery {
+        terms,
+        activity_group_id: params.activity_group_id,
+        is_active: params.is_active,
+        limit: params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+    };
+
+    match search(&query, &pool).await {
+        Ok(data) => HttpResponse::Ok().json(ResponseWithData::<Vec<SearchHit>> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data,
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
@@ -1,60 +1,101 @@
-use actix_web::{web, HttpResponse};
-use chrono::Utc;
-use sqlx::{MySqlPool};
-use uuid::Uuid;
-use tracing::Instrument;
-
-#[derive(serde::Deserialize)]
-pub struct FormData {
-    email: String,
-    name: String
-}
+use crate::common::Response;
+use crate::services::{
+    confirm_subscription, confirmation_email, insert_subscriber, store_subscription_token,
+    subscription_confirm_url, EmailSender, NewSubscriber, SubscriptionFormData,
+};
+use crate::startup::ApplicationBaseUrl;
+use actix_web::{get, post, web, HttpResponse};
+use sqlx::MySqlPool;
 
+#[utoipa::path(
+    tag = "subscriptions",
+    request_body(content = SubscriptionFormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Confirmation email sent, or already subscribed", body = Response),
+        (status = 400, description = "Missing or invalid email or name", body = Response),
+    )
+)]
+#[post("/subscriptions")]
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(form, pool),
-    fields(
-        subscriber_email = %form.email,
-        subscriber_name= %form.name
-    ) 
+    skip(form, pool, email_sender, base_url),
+    fields(subscriber_email = ?form.email, subscriber_name = ?form.name)
 )]
-pub async fn subscribe(form: web::Form<FormData>, pool: web::Data<MySqlPool>) -> HttpResponse {
-    let query_span = tracing::info_span!(
-        "Saving new subscriber details in the database"
-    );
-    match insert_subscriber(&pool, &form).await
-    {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(e) => {
-            tracing::error!("Failed to execute query: {:?}", e);
-            HttpResponse::InternalServerError().finish()
+pub async fn subscribe(
+    form: web::Form<SubscriptionFormData>,
+    pool: web::Data<MySqlPool>,
+    email_sender: web::Data<dyn EmailSender>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> HttpResponse {
+    let subscriber = match NewSubscriber::parse(form.into_inner()) {
+        Ok(subscriber) => subscriber,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(Response {
+                status: "Bad Request".into(),
+                message,
+            })
         }
+    };
+
+    let subscription = match insert_subscriber(&subscriber, &pool).await {
+        Ok(subscription) => subscription,
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+    if subscription.is_confirmed() {
+        return HttpResponse::Ok().json(Response {
+            status: "Success".into(),
+            message: format!("{} is already subscribed", subscription.email),
+        });
+    }
+
+    let token = match store_subscription_token(subscription.id, &pool).await {
+        Ok(token) => token,
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+    let email = confirmation_email(
+        &subscription,
+        &subscription_confirm_url(&base_url.0, &token),
+    );
+    if let Err(e) = email_sender.send_email(email).await {
+        tracing::error!("Failed to send a confirmation email: {}", e);
+        return HttpResponse::InternalServerError().finish();
     }
+
+    HttpResponse::Ok().json(Response {
+        status: "Success".into(),
+        message: format!("Check {} to confirm the subscription", subscription.email),
+    })
 }
 
-#[tracing::instrument(
-    name = "Saving new subscriber details in the database",
-    skip(form, pool)
-)]
-pub async fn insert_subscriber(
-    pool: &MySqlPool,
-    form: &FormData,
-) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"
-        INSERT INTO subscriptions (id, email, name, subscribed_at)
-        VALUES ($1, $2, $3, $4)
-        "#,
-        Uuid::new_v4(),
-        form.email,
-        form.name,
-        Utc::now()
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ConfirmParams {
+    /// The token from the link in the confirmation email.
+    token: String,
+}
+
+#[utoipa::path(
+    tag = "subscriptions",
+    params(ConfirmParams),
+    responses(
+        (status = 200, description = "The subscription is confirmed", body = Response),
+        (status = 404, description = "Unknown token", body = Response),
     )
-    .execute(pool)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to execute query: {:?}", e);
-        e
-    })?;
-    Ok(())
-}
\ No newline at end of file
+)]
+#[get("/subscriptions/confirm")]
+pub async fn subscription_confirm(
+    params: web::Query<ConfirmParams>,
+    pool: web::Data<MySqlPool>,
+) -> HttpResponse {
+    match confirm_subscription(&params.token, &pool).await {
+        Ok(email) => HttpResponse::Ok().json(Response {
+            status: "Success".into(),
+            message: format!("{} is now subscribed", email),
+        }),
+        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().json(Response {
+            status: "Not Found".into(),
+            message: "Subscription token Not Found".into(),
+        }),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
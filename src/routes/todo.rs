@@ -1,48 +1,59 @@
-use crate::common::{Response, ResponseWithData, TitleField};
+use crate::common::{activity_not_found, Response, ResponseWithData, Timezone};
 use crate::services::{
-    default_as_true, default_as_very_high, delete_todo_by_id, get_todo_by_id, get_todos,
-    insert_todo, update_todo_by_id,
+    delete_todo_by_id, format_todo_txt, get_activity_by_id, get_todo_by_id, get_todos,
+    import_todo_txt, import_todos_csv, insert_todo, todo_txt_project, update_todo_by_id,
+    write_todos_csv, EventBus,
 };
-use crate::services::{NewTodo, Todo, UpdateTodo};
-use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse, Responder};
+use crate::services::{
+    CsvColumnNames, CsvImportError, CsvImportSummary, ImportLineError, NewTodo, Todo, TodoFilter,
+    TodoTxtImportError, UpdateTodo,
+};
+use actix_web::{
+    delete, get, http::header, patch, post, web, HttpRequest, HttpResponse, Responder,
+};
+use chrono::NaiveDate;
+use futures_util::StreamExt;
 use serde_json::{Map, Value};
 use sqlx::MySqlPool;
+use tokio::sync::mpsc;
+use tokio_util::io::StreamReader;
+use zero2prod_types::{TodoFormData, TodoFormUpdateData};
 
-#[derive(serde::Deserialize)]
-struct FormData {
-    #[serde(default)]
-    title: String,
-    activity_group_id: Option<i32>,
-    #[serde(default = "default_as_very_high")]
-    priority: String,
-    #[serde(default = "default_as_true")]
-    is_active: bool,
-}
-
-#[derive(serde::Deserialize)]
-struct FormUpdateData {
-    title: Option<String>,
-    activity_group_id: Option<i32>,
-    priority: Option<String>,
-    is_active: Option<bool>,
-}
-
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 struct Params {
     activity_group_id: Option<i32>,
+    /// Only todos created on or after this date, in the caller's timezone.
+    created_from: Option<NaiveDate>,
+    /// Only todos created on or before this date, in the caller's timezone.
+    created_to: Option<NaiveDate>,
 }
 
+#[utoipa::path(
+    tag = "todo-items",
+    params(Params),
+    responses(
+        (status = 200, description = "List todos, optionally filtered by activity group", body = ResponseWithData<Vec<Todo>>),
+        (status = 500, description = "Database error"),
+    )
+)]
 #[get("/todo-items")]
 pub async fn todo_list(
     _req: HttpRequest,
     params: web::Query<Params>,
     pool: web::Data<MySqlPool>,
+    tz: Timezone,
 ) -> HttpResponse {
-    match get_todos(params.activity_group_id, &pool).await {
+    let filter = TodoFilter {
+        activity_group_id: params.activity_group_id,
+        created_from: params.created_from.map(|date| tz.start_of_day(date)),
+        created_before: params.created_to.map(|date| tz.end_of_day(date)),
+    };
+    match get_todos(&filter, &pool).await {
         Ok(data) => HttpResponse::Ok().json(ResponseWithData::<Vec<Todo>> {
             status: "Success".into(),
             message: "Success".into(),
-            data: data,
+            data: data.into_iter().map(|todo| todo.into_v1(&tz)).collect(),
         }),
         Err(e) => {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -51,42 +62,268 @@ pub async fn todo_list(
     }
 }
 
-#[post("/todo-items")]
-pub async fn todo_create(form: web::Json<FormData>, pool: web::Data<MySqlPool>) -> HttpResponse {
-    let title = match TitleField::parse(form.0.title.clone()) {
-        Ok(title) => title,
-        Err(_) => {
-            let error = Response {
+#[utoipa::path(
+    tag = "todo-items",
+    params(Params),
+    responses(
+        (status = 200, description = "The todos `todo_list` returns, as CSV with timestamps in the caller's timezone", content_type = "text/csv", body = String),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[get("/todo-items.csv")]
+pub async fn todo_list_csv(
+    params: web::Query<Params>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
+    let filter = TodoFilter {
+        activity_group_id: params.activity_group_id,
+        created_from: params.created_from.map(|date| tz.start_of_day(date)),
+        created_before: params.created_to.map(|date| tz.end_of_day(date)),
+    };
+    let todos = match get_todos(&filter, &pool).await {
+        Ok(todos) => todos,
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    match write_todos_csv(todos, &tz).await {
+        Ok(csv) => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"todo-items.csv\"",
+            ))
+            .body(csv),
+        Err(e) => {
+            tracing::error!("Failed to write CSV: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CsvImportParams {
+    /// Header of the title column; `title`, `name` or `task` by default.
+    title_column: Option<String>,
+    /// Header of the priority column; `priority` by default.
+    priority_column: Option<String>,
+    /// Header of the is_active column; `is_active` or `active` by default.
+    is_active_column: Option<String>,
+    /// Header of the due date column; `due_at`, `due`, `due date` or
+    /// `due_date` by default.
+    due_at_column: Option<String>,
+}
+
+#[utoipa::path(
+    tag = "todo-items",
+    params(CsvImportParams),
+    request_body(content = String, content_type = "text/csv", description = "A header row, then one todo per row. Unknown columns are ignored; `GET /todo-items.csv` output is accepted as is"),
+    responses(
+        (status = 201, description = "Every row was added to the activity group", body = ResponseWithData<CsvImportSummary>),
+        (status = 400, description = "No title column, or invalid rows by line number; nothing was imported", body = ResponseWithData<Vec<ImportLineError>>),
+        (status = 404, description = "Activity group not found", body = Response),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[post("/activity-groups/{activity_id}/todo-items/import")]
+pub async fn todo_import_csv(
+    path: web::Path<i32>,
+    params: web::Query<CsvImportParams>,
+    mut body: web::Payload,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
+) -> HttpResponse {
+    let activity_id = path.into_inner();
+    match get_activity_by_id(activity_id, &pool).await {
+        Ok(_) => {}
+        Err(sqlx::Error::RowNotFound) => return activity_not_found(activity_id),
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    }
+    let params = params.into_inner();
+    let names = CsvColumnNames {
+        title: params.title_column,
+        priority: params.priority_column,
+        is_active: params.is_active_column,
+        due_at: params.due_at_column,
+    };
+
+    // The payload can't leave this task, so it is fed through a channel to
+    // the reader, a few chunks at a time.
+    let (chunks, received) = mpsc::channel::<std::io::Result<web::Bytes>>(8);
+    let upload = async move {
+        while let Some(chunk) = body.next().await {
+            if chunks
+                .send(chunk.map_err(std::io::Error::other))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    };
+    let received = futures_util::stream::unfold(received, |mut received| async move {
+        received.recv().await.map(|chunk| (chunk, received))
+    });
+    let import = import_todos_csv(
+        StreamReader::new(Box::pin(received)),
+        activity_id,
+        &names,
+        &tz,
+        &pool,
+        &events,
+    );
+    let ((), result) = futures_util::future::join(upload, import).await;
+
+    match result {
+        Ok(summary) => HttpResponse::Created().json(ResponseWithData::<CsvImportSummary> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: summary,
+        }),
+        Err(CsvImportError::Header(message) | CsvImportError::Read(message)) => {
+            HttpResponse::BadRequest().json(Response {
                 status: "Bad Request".into(),
-                message: "title cannot be null".into(),
-            };
-            return HttpResponse::BadRequest().json(error);
+                message,
+            })
         }
+        Err(CsvImportError::Rows(errors, invalid)) => {
+            HttpResponse::BadRequest().json(ResponseWithData::<Vec<ImportLineError>> {
+                status: "Bad Request".into(),
+                message: match invalid {
+                    1 => "1 row is invalid; nothing was imported".into(),
+                    n => format!("{} rows are invalid; nothing was imported", n),
+                },
+                data: errors,
+            })
+        }
+        Err(CsvImportError::Database(_)) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[utoipa::path(
+    tag = "todo-items",
+    responses(
+        (status = 200, description = "The activity group's todos, one todo.txt line each, dates in the caller's timezone", content_type = "text/plain", body = String),
+        (status = 404, description = "Activity group not found", body = Response),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[get("/activity-groups/{activity_id}/todo.txt")]
+pub async fn todo_txt_export(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
+    let activity_id = path.into_inner();
+    let activity = match get_activity_by_id(activity_id, &pool).await {
+        Ok(activity) => activity,
+        Err(sqlx::Error::RowNotFound) => return activity_not_found(activity_id),
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+    let filter = TodoFilter {
+        activity_group_id: Some(activity_id),
+        ..TodoFilter::default()
+    };
+    match get_todos(&filter, &pool).await {
+        Ok(todos) => {
+            let project = todo_txt_project(&activity.title);
+            let text: String = todos
+                .iter()
+                .map(|todo| format_todo_txt(todo, &project, &tz) + "\n")
+                .collect();
+            HttpResponse::Ok()
+                .content_type("text/plain; charset=utf-8")
+                .body(text)
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "todo-items",
+    request_body(content = String, content_type = "text/plain", description = "todo.txt lines. `(A)` to `(E)` map to very-high to very-low and later letters to very-low, `x` marks a finished todo, and the group's own `+project` is dropped; `@contexts` and other tags stay in the title"),
+    responses(
+        (status = 201, description = "Every line was added to the activity group", body = ResponseWithData<Vec<Todo>>),
+        (status = 400, description = "Invalid lines by number; nothing was imported", body = ResponseWithData<Vec<ImportLineError>>),
+        (status = 404, description = "Activity group not found", body = Response),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[post("/activity-groups/{activity_id}/todo.txt")]
+pub async fn todo_txt_import(
+    path: web::Path<i32>,
+    body: String,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
+) -> HttpResponse {
+    let activity_id = path.into_inner();
+    let activity = match get_activity_by_id(activity_id, &pool).await {
+        Ok(activity) => activity,
+        Err(sqlx::Error::RowNotFound) => return activity_not_found(activity_id),
+        Err(_) => return HttpResponse::InternalServerError().finish(),
     };
+    let project = todo_txt_project(&activity.title);
 
-    let activity_group_id = match form.0.activity_group_id {
-        Some(activity_group_id) => activity_group_id,
-        None => {
+    match import_todo_txt(&body, activity_id, &project, &tz, &pool, &events).await {
+        Ok(todos) => HttpResponse::Created().json(ResponseWithData::<Vec<Todo>> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: todos.into_iter().map(|todo| todo.into_v1(&tz)).collect(),
+        }),
+        Err(TodoTxtImportError::Lines(errors, invalid)) => {
+            HttpResponse::BadRequest().json(ResponseWithData::<Vec<ImportLineError>> {
+                status: "Bad Request".into(),
+                message: match invalid {
+                    1 => "1 line is invalid; nothing was imported".into(),
+                    n => format!("{} lines are invalid; nothing was imported", n),
+                },
+                data: errors,
+            })
+        }
+        Err(TodoTxtImportError::Database(_)) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[utoipa::path(
+    tag = "todo-items",
+    request_body = TodoFormData,
+    responses(
+        (status = 201, description = "Todo created", body = ResponseWithData<Todo>),
+        (status = 400, description = "Missing title or activity_group_id", body = Response),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[post("/todo-items")]
+pub async fn todo_create(
+    form: web::Json<TodoFormData>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
+) -> HttpResponse {
+    let activity = match NewTodo::parse(form.into_inner()) {
+        Ok(todo) => todo,
+        Err(message) => {
             let error = Response {
                 status: "Bad Request".into(),
-                message: "activity_group_id cannot be null".into(),
+                message,
             };
             return HttpResponse::BadRequest().json(error);
         }
     };
 
-    let activity = NewTodo {
-        title,
-        activity_group_id: activity_group_id,
-        priority: Some(form.0.priority.clone()),
-        is_active: Some(form.0.is_active),
-    };
-
-    match insert_todo(&pool, &activity).await {
+    match insert_todo(&pool, &activity, &events).await {
         Ok(data) => HttpResponse::Created().json(ResponseWithData::<Todo> {
             status: "Success".into(),
             message: "Success".into(),
-            data: data,
+            data: data.into_v1(&tz),
         }),
         Err(e) => {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -95,14 +332,25 @@ pub async fn todo_create(form: web::Json<FormData>, pool: web::Data<MySqlPool>)
     }
 }
 
+#[utoipa::path(
+    tag = "todo-items",
+    responses(
+        (status = 200, description = "Todo found", body = ResponseWithData<Todo>),
+        (status = 404, description = "Todo not found", body = Response),
+    )
+)]
 #[get("/todo-items/{todo_id}")] // <- define path parameters
-pub async fn todo_detail(path: web::Path<i32>, pool: web::Data<MySqlPool>) -> HttpResponse {
+pub async fn todo_detail(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
     let todo_id = path.into_inner();
     match get_todo_by_id(todo_id, &pool).await {
         Ok(data) => HttpResponse::Ok().json(ResponseWithData::<Todo> {
             status: "Success".into(),
             message: "Success".into(),
-            data: data,
+            data: data.into_v1(&tz),
         }),
         Err(e) => {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -114,11 +362,21 @@ pub async fn todo_detail(path: web::Path<i32>, pool: web::Data<MySqlPool>) -> Ht
     }
 }
 
+#[utoipa::path(
+    tag = "todo-items",
+    request_body = TodoFormUpdateData,
+    responses(
+        (status = 200, description = "Todo updated", body = ResponseWithData<Todo>),
+        (status = 404, description = "Todo not found", body = Response),
+    )
+)]
 #[patch("/todo-items/{todo_id}")] // <- define path parameters
 pub async fn todo_update(
     path: web::Path<i32>,
-    form: web::Json<FormUpdateData>,
+    form: web::Json<TodoFormUpdateData>,
     pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
 ) -> HttpResponse {
     let todo_id = path.into_inner();
     // let title = match TitleField::parse(form.0.title.clone()) {
@@ -129,17 +387,12 @@ pub async fn todo_update(
     //     }
     // };
 
-    let todo = UpdateTodo {
-        title: form.0.title,
-        activity_group_id: form.0.activity_group_id,
-        is_active: form.0.is_active,
-        priority: form.0.priority,
-    };
-    match update_todo_by_id(todo_id, &pool, &todo).await {
+    let todo = UpdateTodo::from(form.into_inner());
+    match update_todo_by_id(todo_id, &pool, &todo, &events).await {
         Ok(data) => HttpResponse::Ok().json(ResponseWithData::<Todo> {
             status: "Success".into(),
             message: "Success".into(),
-            data: data,
+            data: data.into_v1(&tz),
         }),
         Err(e) => {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -151,10 +404,21 @@ pub async fn todo_update(
     }
 }
 
+#[utoipa::path(
+    tag = "todo-items",
+    responses(
+        (status = 200, description = "Todo deleted", body = ResponseWithData<std::collections::HashMap<String, String>>),
+        (status = 404, description = "Todo not found", body = Response),
+    )
+)]
 #[delete("/todo-items/{todo_id}")] // <- define path parameters
-pub async fn todo_destroy(path: web::Path<i32>, pool: web::Data<MySqlPool>) -> impl Responder {
+pub async fn todo_destroy(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+) -> impl Responder {
     let todo_id = path.into_inner();
-    match delete_todo_by_id(todo_id, &pool).await {
+    match delete_todo_by_id(todo_id, &pool, &events).await {
         Ok(_) => HttpResponse::Ok().json(ResponseWithData::<Map<String, Value>> {
             status: "Success".into(),
             message: "Success".into(),
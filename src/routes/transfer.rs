@@ -0,0 +1,161 @@
+use crate::common::{Response, ResponseWithData, Timezone};
+use crate::services::{
+    export_all, import_export, insert_external_import, todoist_import, trello_import,
+    validate_export, EventBus, Export, ExportedActivityGroup, ExternalImport, ExternalImportReport,
+    ImportMode, ImportReport, TodoistBackup, TrelloBoard,
+};
+use actix_web::{get, http::header, post, web, HttpResponse};
+use sqlx::MySqlPool;
+
+/// Largest JSON body the import routes accept, well above actix's 2 MB
+/// default so full exports and board backups fit.
+pub const IMPORT_JSON_LIMIT: usize = 32 * 1024 * 1024;
+
+#[utoipa::path(
+    tag = "transfer",
+    responses(
+        (status = 200, description = "Every activity group with its todos, timestamps in UTC", body = Export),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[get("/export")]
+pub async fn export_data(pool: web::Data<MySqlPool>) -> HttpResponse {
+    match export_all(&pool).await {
+        Ok(export) => HttpResponse::Ok()
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"todo-export.json\"",
+            ))
+            .json(export),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ImportParams {
+    /// `merge` (the default), `replace` or `dry-run`.
+    #[serde(default)]
+    #[param(inline)]
+    mode: ImportMode,
+}
+
+#[utoipa::path(
+    context_path = "/import",
+    tag = "transfer",
+    params(ImportParams),
+    request_body = Export,
+    responses(
+        (status = 200, description = "Where each imported entry went, and which matched existing data", body = ResponseWithData<ImportReport>),
+        (status = 400, description = "Unsupported version, duplicate ids, empty titles or unknown priorities", body = Response),
+        (status = 413, description = "Body larger than 32 MiB"),
+        (status = 500, description = "Database error; nothing was imported"),
+    )
+)]
+#[post("")]
+pub async fn import_data(
+    export: web::Json<Export>,
+    params: web::Query<ImportParams>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+) -> HttpResponse {
+    if let Err(message) = validate_export(&export) {
+        return HttpResponse::BadRequest().json(Response {
+            status: "Bad Request".into(),
+            message,
+        });
+    }
+
+    match import_export(&export, params.mode, &pool, &events).await {
+        Ok(report) => HttpResponse::Ok().json(ResponseWithData::<ImportReport> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: report,
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[utoipa::path(
+    context_path = "/import",
+    tag = "transfer",
+    request_body(content = Object, description = "A board exported from Trello as JSON. Cards become todos ordered by list and place, followed by their checklist items; a card is done when its due date is marked complete. Archived lists and cards are skipped"),
+    responses(
+        (status = 201, description = "The activity group created, and the fields left unmapped", body = ResponseWithData<ExternalImportReport>),
+        (status = 400, description = "Not a Trello board, lists sharing an id, or a card or checklist item without a name", body = Response),
+        (status = 413, description = "Body larger than 32 MiB"),
+        (status = 500, description = "Database error; nothing was imported"),
+    )
+)]
+#[post("/trello")]
+pub async fn import_trello(
+    board: web::Json<TrelloBoard>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
+) -> HttpResponse {
+    external_import(trello_import(&board), &pool, &events, &tz).await
+}
+
+#[utoipa::path(
+    context_path = "/import",
+    tag = "transfer",
+    request_body(content = Object, description = "A Todoist backup in the JSON shape of its Sync API: `projects`, `sections`, `items` and `notes`. Every project becomes an activity group, with tasks ordered by section and place and subtasks after their parent. Priorities 4 to 1 map to very-high, high, normal and low; due dates without a time end that day in the caller's timezone. Archived projects and deleted entries are skipped"),
+    responses(
+        (status = 201, description = "The activity groups created, and the fields left unmapped", body = ResponseWithData<ExternalImportReport>),
+        (status = 400, description = "Not a Todoist backup, projects, sections or tasks sharing an id, or a task without content or with an invalid priority or due date", body = Response),
+        (status = 413, description = "Body larger than 32 MiB"),
+        (status = 500, description = "Database error; nothing was imported"),
+    )
+)]
+#[post("/todoist")]
+pub async fn import_todoist(
+    backup: web::Json<TodoistBackup>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
+) -> HttpResponse {
+    external_import(todoist_import(&backup, &tz), &pool, &events, &tz).await
+}
+
+async fn external_import(
+    import: Result<ExternalImport, String>,
+    pool: &MySqlPool,
+    events: &EventBus,
+    tz: &Timezone,
+) -> HttpResponse {
+    let import = match import {
+        Ok(import) => import,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(Response {
+                status: "Bad Request".into(),
+                message,
+            })
+        }
+    };
+
+    match insert_external_import(import.groups, pool, events).await {
+        Ok(groups) => HttpResponse::Created().json(ResponseWithData::<ExternalImportReport> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: ExternalImportReport {
+                activity_groups: groups
+                    .into_iter()
+                    .map(|(activity, todos)| ExportedActivityGroup {
+                        activity_group: activity.into_v1(tz),
+                        todos: todos.into_iter().map(|todo| todo.into_v1(tz)).collect(),
+                    })
+                    .collect(),
+                skipped: import.skipped,
+                unmapped: import.unmapped.into_vec(),
+            },
+        }),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
@@ -0,0 +1,168 @@
+use crate::common::{Timezone, TitleField};
+use crate::routes::v2::{error_response, internal_error_response, lookup_error_response};
+use crate::services::{
+    delete_activity_by_id, get_activities, get_activity_by_id, insert_activity,
+    update_activity_by_id, EventBus,
+};
+use crate::services::{NewActivity, UpdateActivity};
+use actix_web::http::StatusCode;
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+use sqlx::MySqlPool;
+use zero2prod_types::v2::{Activity, Data, ErrorBody};
+use zero2prod_types::ActivityFormData;
+
+#[utoipa::path(
+    context_path = "/v2",
+    tag = "v2",
+    responses(
+        (status = 200, description = "List all activity groups", body = Data<Vec<Activity>>),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+#[get("/activity-groups")]
+pub async fn activity_list(pool: web::Data<MySqlPool>, tz: Timezone) -> HttpResponse {
+    match get_activities(&pool).await {
+        Ok(data) => HttpResponse::Ok().json(Data {
+            data: data
+                .into_iter()
+                .map(|activity| activity.into_v2(&tz))
+                .collect::<Vec<_>>(),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            internal_error_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    context_path = "/v2",
+    tag = "v2",
+    request_body = ActivityFormData,
+    responses(
+        (status = 201, description = "Activity group created", body = Data<Activity>),
+        (status = 422, description = "Missing title", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+#[post("/activity-groups")]
+pub async fn activity_create(
+    form: web::Json<ActivityFormData>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
+) -> HttpResponse {
+    let form = form.into_inner();
+    let title = match TitleField::parse(form.title) {
+        Ok(title) => title,
+        Err(_) => return missing_title(),
+    };
+
+    let activity = NewActivity {
+        title,
+        email: form.email,
+    };
+
+    match insert_activity(&pool, &activity, &events).await {
+        Ok(data) => HttpResponse::Created().json(Data {
+            data: data.into_v2(&tz),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            internal_error_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    context_path = "/v2",
+    tag = "v2",
+    responses(
+        (status = 200, description = "Activity group found", body = Data<Activity>),
+        (status = 404, description = "Activity group not found", body = ErrorBody),
+    )
+)]
+#[get("/activity-groups/{activity_id}")]
+pub async fn activity_detail(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
+    let activity_id = path.into_inner();
+    match get_activity_by_id(activity_id, &pool).await {
+        Ok(data) => HttpResponse::Ok().json(Data {
+            data: data.into_v2(&tz),
+        }),
+        Err(e) => lookup_error_response(e, not_found_message(activity_id)),
+    }
+}
+
+#[utoipa::path(
+    context_path = "/v2",
+    tag = "v2",
+    request_body = ActivityFormData,
+    responses(
+        (status = 200, description = "Activity group updated", body = Data<Activity>),
+        (status = 404, description = "Activity group not found", body = ErrorBody),
+        (status = 422, description = "Missing title", body = ErrorBody),
+    )
+)]
+#[patch("/activity-groups/{activity_id}")]
+pub async fn activity_update(
+    path: web::Path<i32>,
+    form: web::Json<ActivityFormData>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
+) -> HttpResponse {
+    let activity_id = path.into_inner();
+    let form = form.into_inner();
+    let title = match TitleField::parse(form.title) {
+        Ok(title) => title,
+        Err(_) => return missing_title(),
+    };
+
+    let activity = UpdateActivity {
+        title,
+        email: form.email,
+    };
+    match update_activity_by_id(activity_id, &pool, &activity, &events).await {
+        Ok(data) => HttpResponse::Ok().json(Data {
+            data: data.into_v2(&tz),
+        }),
+        Err(e) => lookup_error_response(e, not_found_message(activity_id)),
+    }
+}
+
+#[utoipa::path(
+    context_path = "/v2",
+    tag = "v2",
+    responses(
+        (status = 204, description = "Activity group deleted"),
+        (status = 404, description = "Activity group not found", body = ErrorBody),
+    )
+)]
+#[delete("/activity-groups/{activity_id}")]
+pub async fn activity_destroy(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+) -> HttpResponse {
+    let activity_id = path.into_inner();
+    match delete_activity_by_id(activity_id, &pool, &events).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => lookup_error_response(e, not_found_message(activity_id)),
+    }
+}
+
+fn missing_title() -> HttpResponse {
+    error_response(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "validation_failed",
+        "title cannot be empty",
+    )
+}
+
+fn not_found_message(activity_id: i32) -> String {
+    format!("Activity with ID {} not found", activity_id)
+}
@@ -0,0 +1,65 @@
+mod activity;
+mod todo;
+
+pub use activity::*;
+pub use todo::*;
+
+use actix_web::error::{InternalError, JsonPayloadError, PathError, QueryPayloadError};
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse};
+use zero2prod_types::v2::{Error, ErrorBody};
+
+use crate::common::InvalidTimezone;
+
+pub(crate) fn error_response(
+    status: StatusCode,
+    code: &str,
+    message: impl Into<String>,
+) -> HttpResponse {
+    HttpResponse::build(status).json(ErrorBody {
+        error: Error {
+            code: code.into(),
+            message: message.into(),
+        },
+    })
+}
+
+/// Maps a failed lookup onto 404 and anything else onto 500.
+pub(crate) fn lookup_error_response(e: sqlx::Error, not_found_message: String) -> HttpResponse {
+    match e {
+        sqlx::Error::RowNotFound => {
+            error_response(StatusCode::NOT_FOUND, "not_found", not_found_message)
+        }
+        e => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            internal_error_response()
+        }
+    }
+}
+
+pub(crate) fn internal_error_response() -> HttpResponse {
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "internal_error",
+        "Internal Server Error",
+    )
+}
+
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let response = error_response(StatusCode::BAD_REQUEST, "invalid_body", err.to_string());
+    InternalError::from_response(err, response).into()
+}
+
+pub fn path_error_handler(err: PathError, _req: &HttpRequest) -> actix_web::Error {
+    let response = error_response(StatusCode::NOT_FOUND, "invalid_path", err.to_string());
+    InternalError::from_response(err, response).into()
+}
+
+pub fn query_error_handler(err: QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let response = error_response(StatusCode::BAD_REQUEST, "invalid_query", err.to_string());
+    InternalError::from_response(err, response).into()
+}
+
+pub fn timezone_error_handler(err: InvalidTimezone) -> HttpResponse {
+    error_response(StatusCode::BAD_REQUEST, "invalid_timezone", err.to_string())
+}
@@ -0,0 +1,186 @@
+use crate::common::{Timezone, TitleField};
+use crate::routes::v2::{error_response, internal_error_response, lookup_error_response};
+use crate::services::{
+    delete_todo_by_id, get_todo_by_id, get_todos, insert_todo, update_todo_by_id, EventBus,
+};
+use crate::services::{NewTodo, TodoFilter, UpdateTodo};
+use actix_web::http::StatusCode;
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+use chrono::{NaiveDate, Utc};
+use sqlx::MySqlPool;
+use zero2prod_types::v2::{Data, ErrorBody, Todo};
+use zero2prod_types::{TodoFormData, TodoFormUpdateData};
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct Params {
+    activity_group_id: Option<i32>,
+    /// Only todos created on or after this date, in the caller's timezone.
+    created_from: Option<NaiveDate>,
+    /// Only todos created on or before this date, in the caller's timezone.
+    created_to: Option<NaiveDate>,
+}
+
+#[utoipa::path(
+    context_path = "/v2",
+    tag = "v2",
+    params(Params),
+    responses(
+        (status = 200, description = "List todos, optionally filtered by activity group", body = Data<Vec<Todo>>),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+#[get("/todo-items")]
+pub async fn todo_list(
+    params: web::Query<Params>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
+    let filter = TodoFilter {
+        activity_group_id: params.activity_group_id,
+        created_from: params.created_from.map(|date| tz.start_of_day(date)),
+        created_before: params.created_to.map(|date| tz.end_of_day(date)),
+    };
+    match get_todos(&filter, &pool).await {
+        Ok(data) => HttpResponse::Ok().json(Data {
+            data: data
+                .into_iter()
+                .map(|todo| todo.into_v2(&tz))
+                .collect::<Vec<_>>(),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            internal_error_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    context_path = "/v2",
+    tag = "v2",
+    request_body = TodoFormData,
+    responses(
+        (status = 201, description = "Todo created", body = Data<Todo>),
+        (status = 422, description = "Missing title or activity_group_id", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody),
+    )
+)]
+#[post("/todo-items")]
+pub async fn todo_create(
+    form: web::Json<TodoFormData>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
+) -> HttpResponse {
+    let form = form.into_inner();
+    let title = match TitleField::parse(form.title) {
+        Ok(title) => title,
+        Err(_) => return validation_failed("title cannot be empty"),
+    };
+
+    let activity_group_id = match form.activity_group_id {
+        Some(activity_group_id) => activity_group_id,
+        None => return validation_failed("activity_group_id is required"),
+    };
+
+    let todo = NewTodo {
+        title,
+        activity_group_id,
+        priority: Some(form.priority),
+        is_active: Some(form.is_active),
+        due_at: form.due_at.map(|due_at| due_at.with_timezone(&Utc)),
+    };
+
+    match insert_todo(&pool, &todo, &events).await {
+        Ok(data) => HttpResponse::Created().json(Data {
+            data: data.into_v2(&tz),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            internal_error_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    context_path = "/v2",
+    tag = "v2",
+    responses(
+        (status = 200, description = "Todo found", body = Data<Todo>),
+        (status = 404, description = "Todo not found", body = ErrorBody),
+    )
+)]
+#[get("/todo-items/{todo_id}")]
+pub async fn todo_detail(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
+    let todo_id = path.into_inner();
+    match get_todo_by_id(todo_id, &pool).await {
+        Ok(data) => HttpResponse::Ok().json(Data {
+            data: data.into_v2(&tz),
+        }),
+        Err(e) => lookup_error_response(e, not_found_message(todo_id)),
+    }
+}
+
+#[utoipa::path(
+    context_path = "/v2",
+    tag = "v2",
+    request_body = TodoFormUpdateData,
+    responses(
+        (status = 200, description = "Todo updated", body = Data<Todo>),
+        (status = 404, description = "Todo not found", body = ErrorBody),
+    )
+)]
+#[patch("/todo-items/{todo_id}")]
+pub async fn todo_update(
+    path: web::Path<i32>,
+    form: web::Json<TodoFormUpdateData>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+    tz: Timezone,
+) -> HttpResponse {
+    let todo_id = path.into_inner();
+    let todo = UpdateTodo::from(form.into_inner());
+    match update_todo_by_id(todo_id, &pool, &todo, &events).await {
+        Ok(data) => HttpResponse::Ok().json(Data {
+            data: data.into_v2(&tz),
+        }),
+        Err(e) => lookup_error_response(e, not_found_message(todo_id)),
+    }
+}
+
+#[utoipa::path(
+    context_path = "/v2",
+    tag = "v2",
+    responses(
+        (status = 204, description = "Todo deleted"),
+        (status = 404, description = "Todo not found", body = ErrorBody),
+    )
+)]
+#[delete("/todo-items/{todo_id}")]
+pub async fn todo_destroy(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    events: web::Data<EventBus>,
+) -> HttpResponse {
+    let todo_id = path.into_inner();
+    match delete_todo_by_id(todo_id, &pool, &events).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => lookup_error_response(e, not_found_message(todo_id)),
+    }
+}
+
+fn validation_failed(message: &str) -> HttpResponse {
+    error_response(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "validation_failed",
+        message,
+    )
+}
+
+fn not_found_message(todo_id: i32) -> String {
+    format!("Todo with ID {} not found", todo_id)
+}
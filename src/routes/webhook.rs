@@ -0,0 +1,222 @@
+use crate::common::{Response, ResponseWithData, Timezone};
+use crate::services::{
+    delete_webhook_by_id, get_webhook_by_id, get_webhook_deliveries, get_webhooks, insert_webhook,
+    update_webhook_by_id,
+};
+use crate::services::{NewWebhook, UpdateWebhook, Webhook, WebhookDelivery, WebhookFormData};
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+use serde_json::{Map, Value};
+use sqlx::MySqlPool;
+
+const DEFAULT_DELIVERY_LIMIT: i64 = 50;
+const MAX_DELIVERY_LIMIT: i64 = 500;
+
+#[utoipa::path(
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "List all webhooks", body = ResponseWithData<Vec<Webhook>>),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[get("/webhooks")]
+pub async fn webhook_list(pool: web::Data<MySqlPool>, tz: Timezone) -> HttpResponse {
+    match get_webhooks(&pool).await {
+        Ok(data) => HttpResponse::Ok().json(ResponseWithData::<Vec<Webhook>> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: data
+                .into_iter()
+                .map(|webhook| webhook.into_v1(&tz))
+                .collect(),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    request_body = WebhookFormData,
+    responses(
+        (status = 201, description = "Webhook created", body = ResponseWithData<Webhook>),
+        (status = 400, description = "Missing or invalid url, event_types or secret", body = Response),
+        (status = 500, description = "Database error"),
+    )
+)]
+#[post("/webhooks")]
+pub async fn webhook_create(
+    form: web::Json<WebhookFormData>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
+    let webhook = match NewWebhook::parse(form.into_inner()) {
+        Ok(webhook) => webhook,
+        Err(message) => {
+            let error = Response {
+                status: "Bad Request".into(),
+                message,
+            };
+            return HttpResponse::BadRequest().json(error);
+        }
+    };
+
+    match insert_webhook(&pool, &webhook).await {
+        Ok(data) => HttpResponse::Created().json(ResponseWithData::<Webhook> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: data.into_v1(&tz),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "Webhook found", body = ResponseWithData<Webhook>),
+        (status = 404, description = "Webhook not found", body = Response),
+    )
+)]
+#[get("/webhooks/{webhook_id}")]
+pub async fn webhook_detail(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
+    let webhook_id = path.into_inner();
+    match get_webhook_by_id(webhook_id, &pool).await {
+        Ok(data) => HttpResponse::Ok().json(ResponseWithData::<Webhook> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: data.into_v1(&tz),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            webhook_not_found(webhook_id)
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    request_body = WebhookFormData,
+    responses(
+        (status = 200, description = "Webhook updated", body = ResponseWithData<Webhook>),
+        (status = 400, description = "Invalid url, event_types or secret", body = Response),
+        (status = 404, description = "Webhook not found", body = Response),
+    )
+)]
+#[patch("/webhooks/{webhook_id}")]
+pub async fn webhook_update(
+    path: web::Path<i32>,
+    form: web::Json<WebhookFormData>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
+    let webhook_id = path.into_inner();
+    let webhook = match UpdateWebhook::parse(form.into_inner()) {
+        Ok(webhook) => webhook,
+        Err(message) => {
+            let error = Response {
+                status: "Bad Request".into(),
+                message,
+            };
+            return HttpResponse::BadRequest().json(error);
+        }
+    };
+
+    match update_webhook_by_id(webhook_id, &pool, &webhook).await {
+        Ok(data) => HttpResponse::Ok().json(ResponseWithData::<Webhook> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: data.into_v1(&tz),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            webhook_not_found(webhook_id)
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "Webhook and its delivery log deleted", body = ResponseWithData<std::collections::HashMap<String, String>>),
+        (status = 404, description = "Webhook not found", body = Response),
+    )
+)]
+#[delete("/webhooks/{webhook_id}")]
+pub async fn webhook_destroy(path: web::Path<i32>, pool: web::Data<MySqlPool>) -> HttpResponse {
+    let webhook_id = path.into_inner();
+    match delete_webhook_by_id(webhook_id, &pool).await {
+        Ok(_) => HttpResponse::Ok().json(ResponseWithData::<Map<String, Value>> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: Map::<String, Value>::new(),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            webhook_not_found(webhook_id)
+        }
+    }
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DeliveryParams {
+    /// Maximum number of deliveries, 50 by default and at most 500.
+    limit: Option<i64>,
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    params(DeliveryParams),
+    responses(
+        (status = 200, description = "Most recent deliveries first", body = ResponseWithData<Vec<WebhookDelivery>>),
+        (status = 404, description = "Webhook not found", body = Response),
+    )
+)]
+#[get("/webhooks/{webhook_id}/deliveries")]
+pub async fn webhook_deliveries(
+    path: web::Path<i32>,
+    params: web::Query<DeliveryParams>,
+    pool: web::Data<MySqlPool>,
+    tz: Timezone,
+) -> HttpResponse {
+    let webhook_id = path.into_inner();
+    if let Err(e) = get_webhook_by_id(webhook_id, &pool).await {
+        tracing::error!("Failed to execute query: {:?}", e);
+        return webhook_not_found(webhook_id);
+    }
+
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_DELIVERY_LIMIT)
+        .clamp(1, MAX_DELIVERY_LIMIT);
+    match get_webhook_deliveries(webhook_id, limit, &pool).await {
+        Ok(data) => HttpResponse::Ok().json(ResponseWithData::<Vec<WebhookDelivery>> {
+            status: "Success".into(),
+            message: "Success".into(),
+            data: data
+                .into_iter()
+                .map(|delivery| delivery.into_v1(&tz))
+                .collect(),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to execute query: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+fn webhook_not_found(webhook_id: i32) -> HttpResponse {
+    HttpResponse::NotFound().json(Response {
+        status: "Not Found".into(),
+        message: format!("Webhook with ID {} Not Found", webhook_id),
+    })
+}
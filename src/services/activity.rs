@@ -1,16 +1,43 @@
-use crate::common::TitleField;
-use chrono::Utc;
-use sqlx::MySqlPool;
-
-#[derive(serde::Serialize)]
-pub struct Activity {
-    id: i32,
-    title: String,
-    email: Option<String>,
-    #[serde(rename = "createdAt")]
-    created_at: String,
-    #[serde(rename = "updatedAt")]
-    updated_at: Option<String>,
+use crate::common::{Timezone, TitleField};
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{ChangeAction, EventBus};
+use crate::telemetry::QuerySpan;
+use chrono::{DateTime, NaiveDateTime, SubsecRound, TimeZone, Utc};
+use sqlx::{MySqlPool, Row};
+
+use zero2prod_types::v2;
+pub use zero2prod_types::Activity;
+
+/// An activity group as stored, with timestamps in UTC.
+#[derive(Clone, Debug)]
+pub struct ActivityRecord {
+    pub id: i32,
+    pub title: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl ActivityRecord {
+    pub fn into_v1(self, tz: &Timezone) -> Activity {
+        Activity {
+            id: self.id,
+            title: self.title,
+            email: self.email,
+            created_at: tz.localize(self.created_at),
+            updated_at: self.updated_at.map(|updated_at| tz.localize(updated_at)),
+        }
+    }
+
+    pub fn into_v2(self, tz: &Timezone) -> v2::Activity {
+        v2::Activity {
+            id: self.id,
+            title: self.title,
+            email: self.email,
+            created_at: tz.localize(self.created_at),
+            updated_at: self.updated_at.map(|updated_at| tz.localize(updated_at)),
+        }
+    }
 }
 
 pub struct NewActivity {
@@ -23,148 +50,258 @@ pub struct UpdateActivity {
     pub email: Option<String>,
 }
 
-pub async fn get_activities(pool: &MySqlPool) -> Result<Vec<Activity>, sqlx::Error> {
+#[tracing::instrument(skip_all)]
+pub async fn get_activities(pool: &MySqlPool) -> Result<Vec<ActivityRecord>, sqlx::Error> {
+    let _timer = query_timer("get_activities");
     let query = sqlx::query!("select * from activities")
-        .fetch_all(pool)
+        .in_span(|query| query.fetch_all(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
             e
         })?;
 
-    let mut activities = Vec::<Activity>::new();
+    let mut activities = Vec::<ActivityRecord>::new();
 
     for q in query {
-        activities.push(Activity {
+        activities.push(ActivityRecord {
             id: q.id,
             title: q.title,
             email: q.email,
-            created_at: q.created_at.to_string(),
-            updated_at: match q.updated_at {
-                Some(updated_at) => Some(updated_at.to_string()),
-                None => None,
-            },
+            created_at: Utc.from_utc_datetime(&q.created_at),
+            updated_at: q
+                .updated_at
+                .map(|updated_at| Utc.from_utc_datetime(&updated_at)),
         });
     }
 
     Ok(activities)
 }
 
+/// `?, ?, ?` for `count` values, to bind one by one in an `in (...)` list.
+pub(crate) fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
+
+/// Every activity group in `activity_ids` that exists, in a single query.
+#[tracing::instrument(skip(pool))]
+pub async fn get_activities_by_ids(
+    activity_ids: &[i32],
+    pool: &MySqlPool,
+) -> Result<Vec<ActivityRecord>, sqlx::Error> {
+    if activity_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let _timer = query_timer("get_activities_by_ids");
+    let sql = format!(
+        "select * from activities where id in ({})",
+        placeholders(activity_ids.len())
+    );
+    let query = activity_ids
+        .iter()
+        .fold(sqlx::query(&sql), |query, id| query.bind(id))
+        .in_span(|query| query.fetch_all(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+    query
+        .iter()
+        .map(|row| {
+            Ok(ActivityRecord {
+                id: row.try_get("id")?,
+                title: row.try_get("title")?,
+                email: row.try_get("email")?,
+                created_at: Utc.from_utc_datetime(&row.try_get("created_at")?),
+                updated_at: row
+                    .try_get::<Option<NaiveDateTime>, _>("updated_at")?
+                    .map(|updated_at| Utc.from_utc_datetime(&updated_at)),
+            })
+        })
+        .collect()
+}
+
+#[tracing::instrument(skip(pool))]
 pub async fn get_activity_by_id(
     activity_id: i32,
     pool: &MySqlPool,
-) -> Result<Activity, sqlx::Error> {
+) -> Result<ActivityRecord, sqlx::Error> {
+    let _timer = query_timer("get_activity_by_id");
     let query = sqlx::query!("select * from activities where id = ?", activity_id)
-        .fetch_one(pool)
+        .in_span(|query| query.fetch_one(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
             e
         })?;
 
-    Ok(Activity {
+    Ok(ActivityRecord {
         id: query.id,
         title: query.title,
         email: query.email,
-        created_at: query.created_at.to_string(),
-        updated_at: match query.updated_at {
-            Some(updated_at) => Some(updated_at.to_string()),
-            None => None,
-        },
+        created_at: Utc.from_utc_datetime(&query.created_at),
+        updated_at: query
+            .updated_at
+            .map(|updated_at| Utc.from_utc_datetime(&updated_at)),
     })
 }
 
+#[tracing::instrument(skip(pool, form, events))]
 pub async fn update_activity_by_id(
     activity_id: i32,
     pool: &MySqlPool,
     form: &UpdateActivity,
-) -> Result<Activity, sqlx::Error> {
+    events: &EventBus,
+) -> Result<ActivityRecord, sqlx::Error> {
+    let _timer = query_timer("update_activity_by_id");
     let query = match &form.email {
         Some(email) => sqlx::query!(
             "update activities set title = ?, email = ? where id = ?",
-            form.title.inner_ref().clone(),
+            form.title.inner_ref(),
             email.clone(),
             activity_id
         ),
         None => sqlx::query!(
             "update activities set title = ? where id = ?",
-            form.title.inner_ref().clone(),
+            form.title.inner_ref(),
             activity_id
         ),
     };
 
-    let _query = query.execute(pool).await.map_err(|e| {
-        tracing::error!("Failed to execute query: {:?}", e);
-        e
-    })?;
+    let _query = query
+        .in_span(|query| query.execute(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
 
     let record = sqlx::query!("select * from activities where id = ?", activity_id)
-        .fetch_one(pool)
+        .in_span(|query| query.fetch_one(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
             e
         })?;
 
-    Ok(Activity {
+    let activity = ActivityRecord {
         id: record.id,
         title: record.title,
         email: record.email,
-        created_at: record.created_at.to_string(),
-        updated_at: match record.updated_at {
-            Some(updated_at) => Some(updated_at.to_string()),
-            None => None,
-        },
-    })
+        created_at: Utc.from_utc_datetime(&record.created_at),
+        updated_at: record
+            .updated_at
+            .map(|updated_at| Utc.from_utc_datetime(&updated_at)),
+    };
+    events.activity_changed(ChangeAction::Updated, &activity);
+
+    Ok(activity)
 }
 
-pub async fn delete_activity_by_id(activity_id: i32, pool: &MySqlPool) -> Result<(), sqlx::Error> {
+#[tracing::instrument(skip(pool, events))]
+pub async fn delete_activity_by_id(
+    activity_id: i32,
+    pool: &MySqlPool,
+    events: &EventBus,
+) -> Result<(), sqlx::Error> {
+    let _timer = query_timer("delete_activity_by_id");
     let _record = sqlx::query!("select * from activities where id = ?", activity_id)
-        .fetch_one(pool)
+        .in_span(|query| query.fetch_one(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
             e
         })?;
+    sqlx::query!(
+        "delete from activity_digests where activity_group_id = ?",
+        activity_id
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    sqlx::query!(
+        "delete from calendar_feeds where activity_group_id = ?",
+        activity_id
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    sqlx::query!(
+        "delete from calendar_objects where activity_group_id = ?",
+        activity_id
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    sqlx::query!(
+        "delete from calendar_tombstones where activity_group_id = ?",
+        activity_id
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
     sqlx::query!("delete from activities where id = ?", activity_id)
-        .execute(pool)
+        .in_span(|query| query.execute(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
             e
         })?;
+    events.activity_deleted(activity_id);
 
     Ok(())
 }
 
+#[tracing::instrument(skip_all)]
 pub async fn insert_activity(
     pool: &MySqlPool,
     form: &NewActivity,
-) -> Result<Activity, sqlx::Error> {
-    let utc_now = Utc::now();
+    events: &EventBus,
+) -> Result<ActivityRecord, sqlx::Error> {
+    let _timer = query_timer("insert_activity");
+    // `datetime` columns hold whole seconds; truncate so the returned record
+    // matches what a later read yields.
+    let utc_now = Utc::now().trunc_subsecs(0);
 
     let query = sqlx::query!(
         r#"
         insert into activities (title, email, created_at, updated_at)
         values (?, ?, ?, ?)
         "#,
-        form.title.inner_ref().clone(),
+        form.title.inner_ref(),
         form.email.clone(),
         utc_now.clone(),
         utc_now.clone()
     )
-    .execute(pool)
+    .in_span(|query| query.execute(TimedPool(pool)))
     .await
     .map_err(|e| {
         tracing::error!("Failed to execute query: {:?}", e);
         e
     })?;
 
-    Ok(Activity {
+    let activity = ActivityRecord {
         id: query.last_insert_id() as i32,
         title: form.title.inner_ref().into(),
         email: form.email.clone(),
-        created_at: utc_now.to_string(),
-        updated_at: Some(utc_now.to_string()),
-    })
+        created_at: utc_now,
+        updated_at: Some(utc_now),
+    };
+    events.activity_changed(ChangeAction::Created, &activity);
+
+    Ok(activity)
 }
@@ -0,0 +1,721 @@
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{
+    get_activity_by_id, get_calendar_objects, render_calendar_object, ActivityRecord,
+    CalendarObject, CalendarTodo, ChangeAction, EventBus, TodoRecord,
+};
+use crate::telemetry::QuerySpan;
+use chrono::{DateTime, SubsecRound, TimeZone, Utc};
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::ResolveResult;
+use quick_xml::NsReader;
+use sha2::{Digest, Sha256};
+use sqlx::MySqlPool;
+
+pub const DAV_NS: &str = "DAV:";
+pub const CALDAV_NS: &str = "urn:ietf:params:xml:ns:caldav";
+pub const CALENDARSERVER_NS: &str = "http://calendarserver.org/ns/";
+
+/// Where the CalDAV tree is served: the principal and calendar home, with
+/// one calendar collection per activity group below it.
+pub const CALDAV_ROOT: &str = "/caldav/";
+
+const SYNC_TOKEN_PREFIX: &str = "urn:zero2prod:sync:";
+
+/// An element of a WebDAV request body, with its namespace resolved.
+#[derive(Debug, PartialEq)]
+pub struct XmlElement {
+    pub namespace: String,
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlElement>,
+    pub text: String,
+}
+
+impl XmlElement {
+    pub fn is(&self, namespace: &str, name: &str) -> bool {
+        self.namespace == namespace && self.name == name
+    }
+
+    pub fn child(&self, namespace: &str, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.is(namespace, name))
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn start(namespace: ResolveResult, start: &BytesStart) -> Result<XmlElement, String> {
+        let namespace = match namespace {
+            ResolveResult::Bound(namespace) => String::from_utf8_lossy(namespace.as_ref()).into(),
+            _ => String::new(),
+        };
+        let mut attributes = Vec::new();
+        for attribute in start.attributes() {
+            let attribute = attribute.map_err(|e| e.to_string())?;
+            let value = attribute.unescape_value().map_err(|e| e.to_string())?;
+            attributes.push((
+                String::from_utf8_lossy(attribute.key.local_name().as_ref()).into(),
+                value.into(),
+            ));
+        }
+        Ok(XmlElement {
+            namespace,
+            name: String::from_utf8_lossy(start.local_name().as_ref()).into(),
+            attributes,
+            children: Vec::new(),
+            text: String::new(),
+        })
+    }
+}
+
+/// Parses a request body into its root element; an empty body has none.
+pub fn parse_xml(body: &[u8]) -> Result<Option<XmlElement>, String> {
+    let mut reader = NsReader::from_reader(body);
+    reader.trim_text(true);
+    let mut buf = Vec::new();
+    let mut open: Vec<XmlElement> = Vec::new();
+    let mut root = None;
+    loop {
+        let closed = match reader.read_resolved_event_into(&mut buf) {
+            Ok((namespace, Event::Start(start))) => {
+                open.push(XmlElement::start(namespace, &start)?);
+                None
+            }
+            Ok((namespace, Event::Empty(start))) => Some(XmlElement::start(namespace, &start)?),
+            Ok((_, Event::End(_))) => open.pop(),
+            Ok((_, Event::Text(text))) => {
+                let text = text.unescape().map_err(|e| e.to_string())?;
+                if let Some(element) = open.last_mut() {
+                    element.text.push_str(&text);
+                }
+                None
+            }
+            Ok((_, Event::CData(text))) => {
+                if let Some(element) = open.last_mut() {
+                    element.text.push_str(&String::from_utf8_lossy(&text));
+                }
+                None
+            }
+            Ok((_, Event::Eof)) => break,
+            Ok(_) => None,
+            Err(e) => {
+                return Err(format!(
+                    "invalid XML at byte {}: {}",
+                    reader.buffer_position(),
+                    e
+                ))
+            }
+        };
+        if let Some(element) = closed {
+            match open.last_mut() {
+                Some(parent) => parent.children.push(element),
+                None => root = Some(element),
+            }
+        }
+        buf.clear();
+    }
+    if let Some(element) = open.last() {
+        return Err(format!("<{}> is never closed", element.name));
+    }
+    Ok(root)
+}
+
+/// A property name: its namespace and local name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PropName {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl PropName {
+    pub fn new(namespace: &str, name: &str) -> PropName {
+        PropName {
+            namespace: namespace.into(),
+            name: name.into(),
+        }
+    }
+
+    fn is(&self, namespace: &str, name: &str) -> bool {
+        self.namespace == namespace && self.name == name
+    }
+
+    /// The property as an element holding `value`, which is XML already.
+    fn element(&self, value: &str) -> String {
+        let (prefix, declaration) = match self.namespace.as_str() {
+            DAV_NS => ("d", String::new()),
+            CALDAV_NS => ("c", String::new()),
+            CALENDARSERVER_NS => ("cs", String::new()),
+            namespace => ("x", format!(" xmlns:x=\"{}\"", escape(namespace))),
+        };
+        if value.is_empty() {
+            format!("<{}:{}{}/>", prefix, self.name, declaration)
+        } else {
+            format!(
+                "<{p}:{n}{d}>{v}</{p}:{n}>",
+                p = prefix,
+                n = self.name,
+                d = declaration,
+                v = value
+            )
+        }
+    }
+}
+
+/// The properties a PROPFIND or REPORT asks for.
+#[derive(Debug, PartialEq)]
+pub enum PropRequest {
+    /// `allprop`, `propname` or no body: every property but calendar data.
+    All,
+    Props(Vec<PropName>),
+}
+
+impl PropRequest {
+    fn from_parent(element: &XmlElement) -> PropRequest {
+        match element.child(DAV_NS, "prop") {
+            Some(prop) => PropRequest::Props(
+                prop.children
+                    .iter()
+                    .map(|child| PropName::new(&child.namespace, &child.name))
+                    .collect(),
+            ),
+            None => PropRequest::All,
+        }
+    }
+}
+
+pub fn parse_propfind(body: &[u8]) -> Result<PropRequest, String> {
+    match parse_xml(body)? {
+        None => Ok(PropRequest::All),
+        Some(root) if root.is(DAV_NS, "propfind") => Ok(PropRequest::from_parent(&root)),
+        Some(root) => Err(format!("<{}> is not a DAV:propfind", root.name)),
+    }
+}
+
+/// A REPORT on a calendar collection.
+#[derive(Debug, PartialEq)]
+pub enum Report {
+    /// Objects holding a component, or every object when no component is
+    /// named. Filters below the component are not applied, so clients get a
+    /// superset they narrow down themselves.
+    CalendarQuery {
+        props: PropRequest,
+        component: Option<String>,
+    },
+    CalendarMultiget {
+        props: PropRequest,
+        hrefs: Vec<String>,
+    },
+    /// Objects changed or removed since `sync_token`; everything when empty.
+    SyncCollection {
+        props: PropRequest,
+        sync_token: String,
+    },
+}
+
+/// Parses a REPORT body; `None` when it asks for a report this server does
+/// not support.
+pub fn parse_report(body: &[u8]) -> Result<Option<Report>, String> {
+    let root = parse_xml(body)?.ok_or("the REPORT has no body")?;
+    let props = PropRequest::from_parent(&root);
+    let report = if root.is(CALDAV_NS, "calendar-query") {
+        let component = root
+            .child(CALDAV_NS, "filter")
+            .and_then(|filter| filter.child(CALDAV_NS, "comp-filter"))
+            .and_then(|calendar| calendar.child(CALDAV_NS, "comp-filter"))
+            .and_then(|component| component.attribute("name"))
+            .map(|name| name.to_ascii_uppercase());
+        Report::CalendarQuery { props, component }
+    } else if root.is(CALDAV_NS, "calendar-multiget") {
+        let hrefs = root
+            .children
+            .iter()
+            .filter(|child| child.is(DAV_NS, "href"))
+            .map(|href| href.text.trim().to_string())
+            .collect();
+        Report::CalendarMultiget { props, hrefs }
+    } else if root.is(DAV_NS, "sync-collection") {
+        let sync_token = root
+            .child(DAV_NS, "sync-token")
+            .map(|token| token.text.trim().to_string())
+            .unwrap_or_default();
+        Report::SyncCollection { props, sync_token }
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(report))
+}
+
+/// A `DAV:error` body naming the precondition a request failed.
+pub fn dav_error(precondition: &PropName) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+        <d:error xmlns:d=\"DAV:\" xmlns:c=\"{}\" xmlns:cs=\"{}\">{}</d:error>\n",
+        CALDAV_NS,
+        CALENDARSERVER_NS,
+        precondition.element("")
+    )
+}
+
+/// A `207 Multi-Status` body under construction.
+#[derive(Default)]
+pub struct Multistatus {
+    responses: String,
+}
+
+impl Multistatus {
+    /// Adds the requested properties of a resource, found or not.
+    pub fn resource(&mut self, resource: &DavResource, props: &PropRequest) {
+        let names = match props {
+            PropRequest::All => resource.all_props(),
+            PropRequest::Props(names) => names.clone(),
+        };
+        let mut found = String::new();
+        let mut missing = String::new();
+        for name in names {
+            match resource.prop(&name) {
+                Some(value) => found.push_str(&name.element(&value)),
+                None => missing.push_str(&name.element("")),
+            }
+        }
+
+        self.responses.push_str("<d:response><d:href>");
+        self.responses.push_str(&escape(&resource.href()));
+        self.responses.push_str("</d:href>");
+        for (props, status) in [(found, "200 OK"), (missing, "404 Not Found")] {
+            if !props.is_empty() {
+                self.responses.push_str(&format!(
+                    "<d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 {}</d:status></d:propstat>",
+                    props, status
+                ));
+            }
+        }
+        self.responses.push_str("</d:response>\n");
+    }
+
+    /// Adds a bare status for `href`, such as a removed or unknown object.
+    pub fn status(&mut self, href: &str, status: &str) {
+        self.responses.push_str(&format!(
+            "<d:response><d:href>{}</d:href><d:status>HTTP/1.1 {}</d:status></d:response>\n",
+            escape(href),
+            status
+        ));
+    }
+
+    pub fn finish(self, sync_token: Option<&str>) -> String {
+        let sync_token = sync_token
+            .map(|token| format!("<d:sync-token>{}</d:sync-token>\n", escape(token)))
+            .unwrap_or_default();
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+            <d:multistatus xmlns:d=\"DAV:\" xmlns:c=\"{}\" xmlns:cs=\"{}\">\n{}{}</d:multistatus>\n",
+            CALDAV_NS, CALENDARSERVER_NS, self.responses, sync_token
+        )
+    }
+}
+
+/// An activity group as a calendar collection.
+pub struct CalendarCollection {
+    pub activity: ActivityRecord,
+    pub objects: Vec<CalendarObject>,
+    /// When an object last left the collection.
+    pub last_removed_at: Option<DateTime<Utc>>,
+}
+
+impl CalendarCollection {
+    pub fn href(&self) -> String {
+        calendar_collection_href(self.activity.id)
+    }
+
+    pub fn object(&self, name: &str) -> Option<&CalendarObject> {
+        self.objects.iter().find(|object| object.name == name)
+    }
+
+    /// Changes whenever an object is added, changed or removed.
+    pub fn ctag(&self) -> String {
+        let mut digest = Sha256::new();
+        for object in &self.objects {
+            digest.update(format!("{} {}\n", object.name, object.etag()));
+        }
+        if let Some(removed_at) = self.last_removed_at {
+            digest.update(format!("removed {}\n", removed_at.timestamp()));
+        }
+        hex::encode(&digest.finalize()[..8])
+    }
+
+    /// The ctag, prefixed with the second of the latest change so a later
+    /// sync-collection only has to look at what changed since.
+    pub fn sync_token(&self) -> String {
+        let changed_at = self
+            .objects
+            .iter()
+            .map(CalendarObject::modified_at)
+            .chain(self.last_removed_at)
+            .max()
+            .map_or(0, |changed_at| changed_at.timestamp());
+        format!("{}{}-{}", SYNC_TOKEN_PREFIX, changed_at, self.ctag())
+    }
+}
+
+/// The instant a sync token was issued at, from its second.
+pub fn parse_sync_token(token: &str) -> Option<DateTime<Utc>> {
+    let (seconds, _) = token.strip_prefix(SYNC_TOKEN_PREFIX)?.split_once('-')?;
+    Utc.timestamp_opt(seconds.parse().ok()?, 0).single()
+}
+
+pub fn calendar_collection_href(activity_group_id: i32) -> String {
+    format!("{}{}/", CALDAV_ROOT, activity_group_id)
+}
+
+/// Whether `name` can name a calendar object: path-safe ASCII that fits a
+/// `calendar_objects` row.
+pub fn is_valid_object_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 255
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-._~@+=".contains(c))
+}
+
+/// Whether `uid` can be stored as a calendar object's UID: printable ASCII
+/// that fits a `calendar_objects` row.
+pub fn is_valid_uid(uid: &str) -> bool {
+    !uid.is_empty() && uid.len() <= 255 && uid.chars().all(|c| c == ' ' || c.is_ascii_graphic())
+}
+
+/// A resource of the CalDAV tree.
+pub enum DavResource<'a> {
+    /// `/caldav/`: the only principal, and the home of every calendar.
+    Home,
+    Collection(&'a CalendarCollection),
+    Object(&'a CalendarCollection, &'a CalendarObject),
+}
+
+impl DavResource<'_> {
+    pub fn href(&self) -> String {
+        match self {
+            DavResource::Home => CALDAV_ROOT.into(),
+            DavResource::Collection(collection) => collection.href(),
+            DavResource::Object(collection, object) => {
+                format!("{}{}", collection.href(), object.name)
+            }
+        }
+    }
+
+    fn all_props(&self) -> Vec<PropName> {
+        let dav = |name| PropName::new(DAV_NS, name);
+        let caldav = |name| PropName::new(CALDAV_NS, name);
+        match self {
+            DavResource::Home => vec![
+                dav("resourcetype"),
+                dav("displayname"),
+                dav("current-user-principal"),
+                dav("principal-URL"),
+                caldav("calendar-home-set"),
+            ],
+            DavResource::Collection(_) => vec![
+                dav("resourcetype"),
+                dav("displayname"),
+                dav("current-user-principal"),
+                dav("current-user-privilege-set"),
+                dav("supported-report-set"),
+                dav("sync-token"),
+                caldav("supported-calendar-component-set"),
+                caldav("supported-calendar-data"),
+                PropName::new(CALENDARSERVER_NS, "getctag"),
+            ],
+            DavResource::Object(..) => vec![
+                dav("resourcetype"),
+                dav("getetag"),
+                dav("getcontenttype"),
+                dav("getcontentlength"),
+                dav("getlastmodified"),
+            ],
+        }
+    }
+
+    /// The value of a property as XML, or `None` when the resource has no
+    /// such property.
+    fn prop(&self, prop: &PropName) -> Option<String> {
+        let principal = format!("<d:href>{}</d:href>", CALDAV_ROOT);
+        if prop.is(DAV_NS, "current-user-principal") {
+            return Some(principal);
+        }
+        match self {
+            DavResource::Home => match (prop.namespace.as_str(), prop.name.as_str()) {
+                (DAV_NS, "resourcetype") => Some("<d:collection/><d:principal/>".into()),
+                (DAV_NS, "displayname") => Some("Todo List".into()),
+                (DAV_NS, "principal-URL") => Some(principal),
+                (CALDAV_NS, "calendar-home-set") => Some(principal),
+                _ => None,
+            },
+            DavResource::Collection(collection) => {
+                match (prop.namespace.as_str(), prop.name.as_str()) {
+                    (DAV_NS, "resourcetype") => Some("<d:collection/><c:calendar/>".into()),
+                    (DAV_NS, "displayname") => Some(escape(&collection.activity.title).into()),
+                    (DAV_NS, "owner") => Some(principal),
+                    (DAV_NS, "current-user-privilege-set") => Some(
+                        ["read", "write", "write-content", "bind", "unbind"]
+                            .iter()
+                            .map(|privilege| format!("<d:privilege><d:{}/></d:privilege>", privilege))
+                            .collect(),
+                    ),
+                    (DAV_NS, "supported-report-set") => Some(
+                        ["c:calendar-query", "c:calendar-multiget", "d:sync-collection"]
+                            .iter()
+                            .map(|report| {
+                                format!("<d:supported-report><d:report><{}/></d:report></d:supported-report>", report)
+                            })
+                            .collect(),
+                    ),
+                    (DAV_NS, "sync-token") => Some(escape(&collection.sync_token()).into()),
+                    (CALDAV_NS, "supported-calendar-component-set") => {
+                        Some("<c:comp name=\"VTODO\"/>".into())
+                    }
+                    (CALDAV_NS, "supported-calendar-data") => Some(
+                        "<c:calendar-data content-type=\"text/calendar\" version=\"2.0\"/>".into(),
+                    ),
+                    (CALENDARSERVER_NS, "getctag") => Some(collection.ctag()),
+                    _ => None,
+                }
+            }
+            DavResource::Object(_, object) => match (prop.namespace.as_str(), prop.name.as_str()) {
+                (DAV_NS, "resourcetype") => Some(String::new()),
+                (DAV_NS, "getetag") => Some(escape(&object.etag()).into()),
+                (DAV_NS, "getcontenttype") => {
+                    Some("text/calendar; charset=utf-8; component=VTODO".into())
+                }
+                (DAV_NS, "getcontentlength") => {
+                    Some(render_calendar_object(object).len().to_string())
+                }
+                (DAV_NS, "getlastmodified") => Some(
+                    object
+                        .modified_at()
+                        .format("%a, %d %b %Y %H:%M:%S GMT")
+                        .to_string(),
+                ),
+                (CALDAV_NS, "calendar-data") => {
+                    Some(escape(&render_calendar_object(object)).into())
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Loads an activity group as a calendar collection.
+#[tracing::instrument(skip(pool))]
+pub async fn get_calendar_collection(
+    activity_group_id: i32,
+    host: &str,
+    pool: &MySqlPool,
+) -> Result<CalendarCollection, sqlx::Error> {
+    let _timer = query_timer("get_calendar_collection");
+    let activity = get_activity_by_id(activity_group_id, pool).await?;
+    let objects = get_calendar_objects(activity_group_id, host, pool).await?;
+    let removed = sqlx::query!(
+        r#"select max(deleted_at) as "last_removed_at?" from calendar_tombstones where activity_group_id = ?"#,
+        activity_group_id
+    )
+    .in_span(|query| query.fetch_one(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(CalendarCollection {
+        activity,
+        objects,
+        last_removed_at: removed
+            .last_removed_at
+            .map(|removed_at| Utc.from_utc_datetime(&removed_at)),
+    })
+}
+
+/// Names of objects removed from a collection at or after `since` that
+/// have not been put back.
+#[tracing::instrument(skip(collection, pool))]
+pub async fn removed_calendar_objects(
+    collection: &CalendarCollection,
+    since: DateTime<Utc>,
+    pool: &MySqlPool,
+) -> Result<Vec<String>, sqlx::Error> {
+    let _timer = query_timer("removed_calendar_objects");
+    let rows = sqlx::query!(
+        "select distinct name from calendar_tombstones where activity_group_id = ? and deleted_at >= ?",
+        collection.activity.id,
+        since
+    )
+    .in_span(|query| query.fetch_all(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| row.name)
+        .filter(|name| collection.object(name).is_none())
+        .collect())
+}
+
+/// Stores a VTODO put at `name`: it updates the todo already there, or
+/// creates one that keeps the client's name and UID. The todo and its
+/// object row are written in one transaction, and the todo is published on
+/// `events` once committed.
+#[tracing::instrument(skip(existing, entry, pool, events))]
+pub async fn put_calendar_object(
+    activity_group_id: i32,
+    name: &str,
+    existing: Option<&CalendarObject>,
+    entry: CalendarTodo,
+    host: &str,
+    pool: &MySqlPool,
+    events: &EventBus,
+) -> Result<CalendarObject, sqlx::Error> {
+    let _timer = query_timer("put_calendar_object");
+    // `datetime` columns hold whole seconds; truncate so the returned record
+    // matches what a later read yields.
+    let now = Utc::now().trunc_subsecs(0);
+    let due_at = entry.due_at.map(|due_at| due_at.trunc_subsecs(0));
+    let mut tx = TimedPool(pool).begin().await?;
+    let (action, todo) = match existing {
+        Some(object) => {
+            // A VTODO without DUE clears the due date.
+            sqlx::query!(
+                "update todos set title = ?, is_active = ?, priority = ?, due_at = ?, updated_at = ? where id = ?",
+                entry.title,
+                entry.is_active,
+                entry.priority,
+                due_at,
+                now,
+                object.todo.id
+            )
+            .in_span(|query| query.execute(&mut tx))
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to execute query: {:?}", e);
+                e
+            })?;
+            let todo = TodoRecord {
+                title: entry.title,
+                is_active: entry.is_active,
+                priority: entry.priority,
+                due_at,
+                updated_at: Some(now),
+                ..object.todo.clone()
+            };
+            (ChangeAction::Updated, todo)
+        }
+        None => {
+            let query = sqlx::query!(
+                r#"
+                insert into todos (title, activity_group_id, is_active, priority, due_at, created_at, updated_at)
+                values (?, ?, ?, ?, ?, ?, ?)
+                "#,
+                entry.title,
+                activity_group_id,
+                entry.is_active,
+                entry.priority,
+                due_at,
+                now,
+                now
+            )
+            .in_span(|query| query.execute(&mut tx))
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to execute query: {:?}", e);
+                e
+            })?;
+            let todo = TodoRecord {
+                id: query.last_insert_id() as i32,
+                title: entry.title,
+                activity_group_id,
+                is_active: entry.is_active,
+                priority: entry.priority,
+                due_at,
+                created_at: now,
+                updated_at: Some(now),
+            };
+            (ChangeAction::Created, todo)
+        }
+    };
+
+    let uid = entry
+        .uid
+        .unwrap_or_else(|| format!("todo-{}@{}", todo.id, host));
+    sqlx::query!(
+        r#"
+        insert into calendar_objects (todo_id, activity_group_id, name, uid)
+        values (?, ?, ?, ?)
+        on duplicate key update name = values(name), uid = values(uid)
+        "#,
+        todo.id,
+        activity_group_id,
+        name,
+        uid
+    )
+    .in_span(|query| query.execute(&mut tx))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    tx.commit().await?;
+    events.todo_changed(action, &todo);
+
+    Ok(CalendarObject {
+        todo,
+        name: name.into(),
+        uid,
+    })
+}
+
+/// Records that a todo left an activity group's calendar, so sync-collection
+/// reports it as removed, and drops the name it was served under.
+#[tracing::instrument(skip(pool))]
+pub async fn forget_calendar_object(
+    todo_id: i32,
+    activity_group_id: i32,
+    pool: &MySqlPool,
+) -> Result<(), sqlx::Error> {
+    let _timer = query_timer("forget_calendar_object");
+    let object = sqlx::query!(
+        "select name from calendar_objects where todo_id = ? and activity_group_id = ?",
+        todo_id,
+        activity_group_id
+    )
+    .in_span(|query| query.fetch_optional(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    let name = object.map_or_else(|| format!("todo-{}.ics", todo_id), |object| object.name);
+
+    sqlx::query!(
+        "insert into calendar_tombstones (activity_group_id, name, deleted_at) values (?, ?, ?)",
+        activity_group_id,
+        name,
+        Utc::now().trunc_subsecs(0)
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    sqlx::query!("delete from calendar_objects where todo_id = ?", todo_id)
+        .in_span(|query| query.execute(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(())
+}
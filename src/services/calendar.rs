@@ -0,0 +1,658 @@
+use crate::common::{Timezone, TitleField};
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{
+    default_as_very_high, ChangeAction, EventBus, ImportLineError, TodoRecord,
+    MAX_REPORTED_ROW_ERRORS,
+};
+use crate::telemetry::QuerySpan;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SubsecRound, TimeZone, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::MySqlPool;
+use uuid::Uuid;
+pub use zero2prod_types::CalendarFeed;
+
+pub const CALENDAR_PRODID: &str = "-//zero2prod//Todo List//EN";
+
+/// iCalendar priorities todos are written with. On import 1-2, 3-4, 5, 6-7
+/// and 8-9 map back to these, and 0 means undefined.
+pub const ICALENDAR_PRIORITIES: [(u8, &str); 5] = [
+    (1, "very-high"),
+    (3, "high"),
+    (5, "normal"),
+    (7, "low"),
+    (9, "very-low"),
+];
+
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const LOCAL_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+const DATE_FORMAT: &str = "%Y%m%d";
+
+/// The longest a content line may be, in octets, before it is folded.
+const MAX_LINE_OCTETS: usize = 75;
+
+/// The subscribe token of an activity group's calendar feed, created the
+/// first time it is asked for.
+#[tracing::instrument(skip(pool))]
+pub async fn calendar_feed_token(
+    activity_group_id: i32,
+    pool: &MySqlPool,
+) -> Result<String, sqlx::Error> {
+    let _timer = query_timer("calendar_feed_token");
+    sqlx::query!(
+        "insert ignore into calendar_feeds (activity_group_id, token, created_at) values (?, ?, ?)",
+        activity_group_id,
+        Uuid::new_v4().to_simple().to_string(),
+        Utc::now().trunc_subsecs(0)
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    let feed = sqlx::query!(
+        "select token from calendar_feeds where activity_group_id = ?",
+        activity_group_id
+    )
+    .in_span(|query| query.fetch_one(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    Ok(feed.token)
+}
+
+/// Revokes the calendar feed of an activity group, so its subscribe URL
+/// stops working. Returns whether there was one.
+#[tracing::instrument(skip(pool))]
+pub async fn revoke_calendar_feed(
+    activity_group_id: i32,
+    pool: &MySqlPool,
+) -> Result<bool, sqlx::Error> {
+    let _timer = query_timer("revoke_calendar_feed");
+    let query = sqlx::query!(
+        "delete from calendar_feeds where activity_group_id = ?",
+        activity_group_id
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    Ok(query.rows_affected() > 0)
+}
+
+/// Whether `token` is the calendar feed token of `activity_group_id`.
+#[tracing::instrument(skip(token, pool))]
+pub async fn calendar_feed_matches(
+    activity_group_id: i32,
+    token: &str,
+    pool: &MySqlPool,
+) -> Result<bool, sqlx::Error> {
+    let _timer = query_timer("calendar_feed_matches");
+    let feed = sqlx::query!(
+        "select activity_group_id from calendar_feeds where token = ?",
+        token
+    )
+    .in_span(|query| query.fetch_optional(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    Ok(feed.is_some_and(|feed| feed.activity_group_id == activity_group_id))
+}
+
+/// The subscribe URLs of an activity group's calendar feed.
+pub fn calendar_feed(base_url: &str, activity_group_id: i32, token: &str) -> CalendarFeed {
+    let url = format!(
+        "{}/activity-groups/{}/calendar.ics?token={}",
+        base_url.trim_end_matches('/'),
+        activity_group_id,
+        token
+    );
+    let webcal_url = match url.split_once("://") {
+        Some((_, rest)) => format!("webcal://{}", rest),
+        None => url.clone(),
+    };
+    CalendarFeed { url, webcal_url }
+}
+
+/// The host todo UIDs are qualified with, so they stay unique across
+/// deployments.
+pub fn calendar_uid_host(base_url: &str) -> String {
+    reqwest::Url::parse(base_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "zero2prod".into())
+}
+
+/// A todo as a calendar object resource: the name it is served under and
+/// its UID. Todos created over CalDAV keep the client's; others get
+/// `todo-{id}.ics` and `todo-{id}@host`.
+#[derive(Clone, Debug)]
+pub struct CalendarObject {
+    pub todo: TodoRecord,
+    pub name: String,
+    pub uid: String,
+}
+
+impl CalendarObject {
+    pub fn new(todo: TodoRecord, host: &str) -> CalendarObject {
+        CalendarObject {
+            name: format!("todo-{}.ics", todo.id),
+            uid: format!("todo-{}@{}", todo.id, host),
+            todo,
+        }
+    }
+
+    /// When the todo last changed; it doubles as the object's DTSTAMP, so
+    /// the rendered object only changes with the todo.
+    pub fn modified_at(&self) -> DateTime<Utc> {
+        self.todo.updated_at.unwrap_or(self.todo.created_at)
+    }
+
+    /// A strong entity tag: a digest of the rendered object.
+    pub fn etag(&self) -> String {
+        let digest = Sha256::digest(render_calendar_object(self).as_bytes());
+        format!("\"{}\"", hex::encode(&digest[..16]))
+    }
+}
+
+/// The todos of an activity group as calendar objects, in id order.
+#[tracing::instrument(skip(pool))]
+pub async fn get_calendar_objects(
+    activity_group_id: i32,
+    host: &str,
+    pool: &MySqlPool,
+) -> Result<Vec<CalendarObject>, sqlx::Error> {
+    let _timer = query_timer("get_calendar_objects");
+    let rows = sqlx::query!(
+        r#"
+        select t.id, t.title, t.activity_group_id, t.is_active, t.priority, t.due_at,
+            t.created_at, t.updated_at, o.name as "name?", o.uid as "uid?"
+        from todos t
+        left join calendar_objects o
+            on o.todo_id = t.id and o.activity_group_id = t.activity_group_id
+        where t.activity_group_id = ?
+        order by t.id
+        "#,
+        activity_group_id
+    )
+    .in_span(|query| query.fetch_all(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let todo = TodoRecord {
+                id: row.id,
+                title: row.title,
+                activity_group_id: row.activity_group_id,
+                is_active: row.is_active != 0,
+                priority: row.priority,
+                created_at: Utc.from_utc_datetime(&row.created_at),
+                updated_at: row
+                    .updated_at
+                    .map(|updated_at| Utc.from_utc_datetime(&updated_at)),
+                due_at: row.due_at.map(|due_at| Utc.from_utc_datetime(&due_at)),
+            };
+            match (row.name, row.uid) {
+                (Some(name), Some(uid)) => CalendarObject { todo, name, uid },
+                _ => CalendarObject::new(todo, host),
+            }
+        })
+        .collect())
+}
+
+/// Renders todos as an RFC 5545 calendar of VTODO components named after
+/// their activity group. Finished todos are COMPLETED as of `updated_at`.
+pub fn render_calendar(name: &str, objects: &[CalendarObject], now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", CALENDAR_PRODID),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+    for object in objects {
+        lines.extend(vtodo_lines(&object.todo, &object.uid, now));
+    }
+    lines.push("END:VCALENDAR".to_string());
+    fold_lines(lines)
+}
+
+/// Renders one todo as the calendar object resource CalDAV serves it as.
+pub fn render_calendar_object(object: &CalendarObject) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", CALENDAR_PRODID),
+    ];
+    lines.extend(vtodo_lines(&object.todo, &object.uid, object.modified_at()));
+    lines.push("END:VCALENDAR".to_string());
+    fold_lines(lines)
+}
+
+fn fold_lines(lines: Vec<String>) -> String {
+    let mut calendar = String::new();
+    for line in lines {
+        fold_line(&line, &mut calendar);
+    }
+    calendar
+}
+
+fn vtodo_lines(todo: &TodoRecord, uid: &str, dtstamp: DateTime<Utc>) -> Vec<String> {
+    let timestamp = |instant: DateTime<Utc>| instant.format(DATE_TIME_FORMAT).to_string();
+    let mut lines = vec![
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", uid),
+        format!("DTSTAMP:{}", timestamp(dtstamp)),
+        format!("SUMMARY:{}", escape_text(&todo.title)),
+    ];
+    if let Some((priority, _)) = ICALENDAR_PRIORITIES
+        .iter()
+        .find(|(_, priority)| *priority == todo.priority)
+    {
+        lines.push(format!("PRIORITY:{}", priority));
+    }
+    if todo.is_active {
+        lines.push("STATUS:NEEDS-ACTION".to_string());
+    } else {
+        lines.push("STATUS:COMPLETED".to_string());
+        lines.push(format!(
+            "COMPLETED:{}",
+            timestamp(todo.updated_at.unwrap_or(todo.created_at))
+        ));
+    }
+    lines.push(format!("CREATED:{}", timestamp(todo.created_at)));
+    if let Some(updated_at) = todo.updated_at {
+        lines.push(format!("LAST-MODIFIED:{}", timestamp(updated_at)));
+    }
+    if let Some(due_at) = todo.due_at {
+        lines.push(format!("DUE:{}", timestamp(due_at)));
+    }
+    lines.push("END:VTODO".to_string());
+    lines
+}
+
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Appends `line` to `out` with CRLF endings, folded so that no line is
+/// longer than 75 octets and no UTF-8 character is split.
+fn fold_line(line: &str, out: &mut String) {
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            octets = 1;
+        }
+        out.push(c);
+        octets += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// A content line: `NAME;PARAM=value:value`.
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn parse(line: &str) -> Result<Property, String> {
+        let mut in_quotes = false;
+        let mut colon = None;
+        for (i, c) in line.char_indices() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                ':' if !in_quotes => {
+                    colon = Some(i);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let colon = colon.ok_or_else(|| format!("\"{}\" is not a NAME:value line", line))?;
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+
+        let mut parts = Vec::new();
+        let mut start = 0;
+        in_quotes = false;
+        for (i, c) in head.char_indices() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                ';' if !in_quotes => {
+                    parts.push(&head[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        parts.push(&head[start..]);
+
+        let mut parts = parts.into_iter();
+        let name = parts.next().unwrap_or_default().to_ascii_uppercase();
+        let params = parts
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| {
+                (
+                    key.to_ascii_uppercase(),
+                    value.trim_matches('"').to_string(),
+                )
+            })
+            .collect();
+        Ok(Property {
+            name,
+            params,
+            value: value.to_string(),
+        })
+    }
+
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// A DATE or DATE-TIME value. UTC times are taken as is, times with a
+    /// TZID in that zone and floating times in `tz`; a date is the start of
+    /// that day in `tz`, or its end when `end_of_day` is set.
+    fn instant(&self, tz: &Timezone, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+        let value = self.value.trim();
+        let invalid = || format!("{} \"{}\" is not a date or date-time", self.name, value);
+        if self.param("VALUE") == Some("DATE") || !value.contains('T') {
+            let date = NaiveDate::parse_from_str(value, DATE_FORMAT).map_err(|_| invalid())?;
+            return Ok(if end_of_day {
+                tz.end_of_day(date) - chrono::Duration::seconds(1)
+            } else {
+                tz.start_of_day(date)
+            });
+        }
+        if let Some(local) = value.strip_suffix('Z') {
+            let utc = NaiveDateTime::parse_from_str(local, LOCAL_DATE_TIME_FORMAT)
+                .map_err(|_| invalid())?;
+            return Ok(Utc.from_utc_datetime(&utc));
+        }
+        let local =
+            NaiveDateTime::parse_from_str(value, LOCAL_DATE_TIME_FORMAT).map_err(|_| invalid())?;
+        let zone = match self.param("TZID") {
+            Some(tzid) => Timezone::parse(tzid)
+                .map_err(|_| format!("{} has an unknown TZID \"{}\"", self.name, tzid))?,
+            None => *tz,
+        };
+        zone.utc_from_local(local).ok_or_else(|| {
+            format!(
+                "{} \"{}\" does not exist in that timezone",
+                self.name, value
+            )
+        })
+    }
+}
+
+/// A VTODO component read from an iCalendar file.
+#[derive(Debug, PartialEq)]
+pub struct CalendarTodo {
+    pub uid: Option<String>,
+    pub title: String,
+    pub priority: String,
+    pub is_active: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub due_at: Option<DateTime<Utc>>,
+}
+
+impl CalendarTodo {
+    fn parse(properties: &[Property], tz: &Timezone) -> Result<CalendarTodo, String> {
+        let mut todo = CalendarTodo {
+            uid: None,
+            title: String::new(),
+            priority: default_as_very_high(),
+            is_active: true,
+            created_at: None,
+            updated_at: None,
+            due_at: None,
+        };
+        for property in properties {
+            match property.name.as_str() {
+                "UID" => todo.uid = Some(property.value.clone()),
+                "SUMMARY" => {
+                    let summary = unescape_text(&property.value);
+                    todo.title = summary.split_whitespace().collect::<Vec<_>>().join(" ");
+                }
+                "PRIORITY" => {
+                    let priority = property
+                        .value
+                        .trim()
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|priority| *priority <= 9)
+                        .ok_or_else(|| {
+                            format!("PRIORITY {} is not a number from 0 to 9", property.value)
+                        })?;
+                    if priority > 0 {
+                        todo.priority = todo_priority(priority).into();
+                    }
+                }
+                "STATUS" => {
+                    todo.is_active = !property.value.trim().eq_ignore_ascii_case("COMPLETED")
+                }
+                "COMPLETED" => todo.is_active = false,
+                "CREATED" => todo.created_at = Some(property.instant(tz, false)?),
+                "LAST-MODIFIED" => todo.updated_at = Some(property.instant(tz, false)?),
+                "DUE" => todo.due_at = Some(property.instant(tz, true)?),
+                _ => {}
+            }
+        }
+        TitleField::parse(todo.title.clone()).map_err(|_| "the VTODO has no SUMMARY")?;
+        Ok(todo)
+    }
+}
+
+fn todo_priority(priority: u8) -> &'static str {
+    match priority {
+        1..=2 => "very-high",
+        3..=4 => "high",
+        5 => "normal",
+        6..=7 => "low",
+        _ => "very-low",
+    }
+}
+
+#[derive(Debug)]
+pub enum CalendarImportError {
+    /// Some VTODOs are invalid, so none were imported. Holds at most
+    /// `MAX_REPORTED_ROW_ERRORS` of them by the line they begin on, and how
+    /// many there were.
+    Lines(Vec<ImportLineError>, usize),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for CalendarImportError {
+    fn from(e: sqlx::Error) -> Self {
+        tracing::error!("Failed to execute query: {:?}", e);
+        CalendarImportError::Database(e)
+    }
+}
+
+/// Unfolds `text` into content lines, each with the number of the line it
+/// starts on.
+fn unfold(text: &str) -> Vec<(u64, String)> {
+    let mut lines: Vec<(u64, String)> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some((_, last))) => last.push_str(continuation),
+            _ => lines.push((i as u64 + 1, line.to_string())),
+        }
+    }
+    lines.retain(|(_, line)| !line.trim().is_empty());
+    lines
+}
+
+/// Reads every VTODO of an iCalendar file; other components and unknown
+/// properties are skipped. Fails with every invalid VTODO when any is.
+pub fn parse_calendar(text: &str, tz: &Timezone) -> Result<Vec<CalendarTodo>, CalendarImportError> {
+    let lines = unfold(text);
+    let is_calendar = lines
+        .first()
+        .is_some_and(|(_, line)| line.eq_ignore_ascii_case("BEGIN:VCALENDAR"));
+    if !is_calendar {
+        let error = ImportLineError {
+            line: lines.first().map_or(1, |(number, _)| *number),
+            message: "the body is not an iCalendar object starting with BEGIN:VCALENDAR".into(),
+        };
+        return Err(CalendarImportError::Lines(vec![error], 1));
+    }
+
+    let mut todos = Vec::new();
+    let mut errors = Vec::new();
+    let mut invalid = 0;
+    let mut report = |line: u64, message: String| {
+        invalid += 1;
+        if errors.len() < MAX_REPORTED_ROW_ERRORS {
+            errors.push(ImportLineError { line, message });
+        }
+    };
+
+    let mut lines = lines.into_iter();
+    while let Some((number, line)) = lines.next() {
+        if !line.eq_ignore_ascii_case("BEGIN:VTODO") {
+            continue;
+        }
+        // Properties of the VTODO itself, not of a VALARM inside it.
+        let mut properties = Vec::new();
+        let mut depth = 0;
+        let mut ended = false;
+        let mut error = None;
+        for (_, line) in lines.by_ref() {
+            let upper = line.to_ascii_uppercase();
+            if upper.starts_with("BEGIN:") {
+                depth += 1;
+            } else if upper == "END:VTODO" && depth == 0 {
+                ended = true;
+                break;
+            } else if upper.starts_with("END:") {
+                depth -= 1;
+                if depth < 0 {
+                    break;
+                }
+            } else if depth == 0 {
+                match Property::parse(&line) {
+                    Ok(property) => properties.push(property),
+                    Err(message) => {
+                        error.get_or_insert(message);
+                    }
+                }
+            }
+        }
+        if !ended {
+            report(number, "the VTODO has no END:VTODO".into());
+            break;
+        }
+        match error.map_or_else(|| CalendarTodo::parse(&properties, tz), Err) {
+            Ok(todo) => todos.push(todo),
+            Err(message) => report(number, message),
+        }
+    }
+
+    if invalid > 0 {
+        return Err(CalendarImportError::Lines(errors, invalid));
+    }
+    Ok(todos)
+}
+
+/// Adds a todo to `activity_group_id` for every VTODO in `text`, all of
+/// them or, when any VTODO is invalid, none. The todos are published on
+/// `events` once committed.
+#[tracing::instrument(skip(text, tz, pool, events))]
+pub async fn import_calendar(
+    text: &str,
+    activity_group_id: i32,
+    tz: &Timezone,
+    pool: &MySqlPool,
+    events: &EventBus,
+) -> Result<Vec<TodoRecord>, CalendarImportError> {
+    let _timer = query_timer("import_calendar");
+    let entries = parse_calendar(text, tz)?;
+
+    let now = Utc::now().trunc_subsecs(0);
+    let mut todos = Vec::with_capacity(entries.len());
+    let mut tx = TimedPool(pool).begin().await?;
+    for entry in entries {
+        let created_at = entry.created_at.unwrap_or(now).trunc_subsecs(0);
+        let updated_at = entry
+            .updated_at
+            .unwrap_or(now)
+            .trunc_subsecs(0)
+            .max(created_at);
+        let query = sqlx::query!(
+            r#"
+            insert into todos (title, activity_group_id, is_active, priority, due_at, created_at, updated_at)
+            values (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            entry.title,
+            activity_group_id,
+            entry.is_active,
+            entry.priority,
+            entry.due_at,
+            created_at,
+            updated_at
+        )
+        .in_span(|query| query.execute(&mut tx))
+        .await?;
+        todos.push(TodoRecord {
+            id: query.last_insert_id() as i32,
+            title: entry.title,
+            activity_group_id,
+            is_active: entry.is_active,
+            priority: entry.priority,
+            due_at: entry.due_at,
+            created_at,
+            updated_at: Some(updated_at),
+        });
+    }
+    tx.commit().await?;
+    for todo in &todos {
+        events.todo_changed(ChangeAction::Created, todo);
+    }
+    Ok(todos)
+}
@@ -0,0 +1,419 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, SubsecRound, TimeZone, Utc};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::{AsyncTransport, Message};
+use sqlx::MySqlPool;
+use uuid::Uuid;
+
+use crate::configuration::{DigestFrequency, DigestSettings};
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{
+    get_activity_by_id, get_todos, ActivityRecord, Mailer, TodoFilter, TodoRecord,
+};
+use crate::telemetry::QuerySpan;
+
+/// The length of one digest period.
+pub fn digest_period(frequency: DigestFrequency) -> chrono::Duration {
+    match frequency {
+        DigestFrequency::Daily => chrono::Duration::days(1),
+        DigestFrequency::Weekly => chrono::Duration::weeks(1),
+    }
+}
+
+/// When the digest period containing `now` began: the latest send hour, on
+/// the configured weekday for weekly digests, that is not after `now`.
+pub fn digest_period_start(settings: &DigestSettings, now: DateTime<Utc>) -> DateTime<Utc> {
+    let send_time = now
+        .date_naive()
+        .and_hms_opt(settings.send_hour.min(23), 0, 0)
+        .expect("hours up to 23 are valid");
+    let mut start = Utc.from_utc_datetime(&send_time);
+    if settings.frequency == DigestFrequency::Weekly {
+        let days_back = (now.weekday().num_days_from_monday() + 7
+            - settings.weekday.num_days_from_monday())
+            % 7;
+        start -= chrono::Duration::days(days_back.into());
+    }
+    if start > now {
+        start -= digest_period(settings.frequency);
+    }
+    start
+}
+
+pub fn digest_opt_out_url(base_url: &str, token: &str) -> String {
+    format!(
+        "{}/digests/opt-out?token={}",
+        base_url.trim_end_matches('/'),
+        token
+    )
+}
+
+/// One activity group's summary of open, overdue and recently completed
+/// todos.
+#[derive(Clone, Debug)]
+pub struct Digest {
+    pub activity_title: String,
+    pub frequency: DigestFrequency,
+    /// Open todos past their `due_at`, most overdue first.
+    pub overdue: Vec<TodoRecord>,
+    /// Other open todos, those due soonest first.
+    pub open: Vec<TodoRecord>,
+    /// Completed todos last updated since `completed_since`, latest first.
+    /// Todos don't record when they were completed, so editing a completed
+    /// todo lists it again.
+    pub completed: Vec<TodoRecord>,
+    pub completed_since: DateTime<Utc>,
+    pub opt_out_url: String,
+}
+
+impl Digest {
+    pub fn new(
+        activity: &ActivityRecord,
+        todos: Vec<TodoRecord>,
+        frequency: DigestFrequency,
+        now: DateTime<Utc>,
+        completed_since: DateTime<Utc>,
+        opt_out_url: String,
+    ) -> Digest {
+        let (active, done): (Vec<_>, Vec<_>) = todos.into_iter().partition(|todo| todo.is_active);
+        let (mut overdue, mut open): (Vec<_>, Vec<_>) = active
+            .into_iter()
+            .partition(|todo| todo.due_at.is_some_and(|due_at| due_at < now));
+        let mut completed = done
+            .into_iter()
+            .filter(|todo| todo.updated_at.unwrap_or(todo.created_at) >= completed_since)
+            .collect::<Vec<_>>();
+
+        overdue.sort_by_key(|todo| (todo.due_at, todo.id));
+        open.sort_by_key(|todo| (todo.due_at.is_none(), todo.due_at, todo.id));
+        completed.sort_by_key(|todo| std::cmp::Reverse((todo.updated_at, todo.id)));
+
+        Digest {
+            activity_title: activity.title.clone(),
+            frequency,
+            overdue,
+            open,
+            completed,
+            completed_since,
+            opt_out_url,
+        }
+    }
+
+    /// Nothing is open and nothing was completed, so there's nothing to send.
+    pub fn is_empty(&self) -> bool {
+        self.overdue.is_empty() && self.open.is_empty() && self.completed.is_empty()
+    }
+
+    pub fn subject(&self) -> String {
+        let frequency = match self.frequency {
+            DigestFrequency::Daily => "Daily",
+            DigestFrequency::Weekly => "Weekly",
+        };
+        format!("{} digest: {}", frequency, self.activity_title)
+    }
+
+    fn sections(&self) -> Vec<(String, Vec<String>)> {
+        let line = |todo: &TodoRecord| match todo.due_at {
+            Some(due_at) => format!(
+                "[{}] {} (due {})",
+                todo.priority,
+                todo.title,
+                due_at.to_rfc3339()
+            ),
+            None => format!("[{}] {}", todo.priority, todo.title),
+        };
+        [
+            ("Overdue".to_string(), &self.overdue),
+            ("Open".to_string(), &self.open),
+            (
+                format!(
+                    "Completed, last updated since {}",
+                    self.completed_since.to_rfc3339()
+                ),
+                &self.completed,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, todos)| !todos.is_empty())
+        .map(|(heading, todos)| {
+            (
+                format!("{} ({})", heading, todos.len()),
+                todos.iter().map(line).collect(),
+            )
+        })
+        .collect()
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = format!("{}\n", self.subject());
+        for (heading, lines) in self.sections() {
+            text.push_str(&format!("\n{}\n", heading));
+            for line in lines {
+                text.push_str(&format!("- {}\n", line));
+            }
+        }
+        text.push_str(&format!("\nStop these emails: {}\n", self.opt_out_url));
+        text
+    }
+
+    pub fn html(&self) -> String {
+        let mut html = format!("<h1>{}</h1>\n", escape_html(&self.subject()));
+        for (heading, lines) in self.sections() {
+            html.push_str(&format!("<h2>{}</h2>\n<ul>\n", escape_html(&heading)));
+            for line in lines {
+                html.push_str(&format!("<li>{}</li>\n", escape_html(&line)));
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str(&format!(
+            "<p><a href=\"{}\">Stop these emails</a></p>\n",
+            escape_html(&self.opt_out_url)
+        ));
+        html
+    }
+
+    pub fn message(&self, sender: &Mailbox, to: &str) -> Result<Message, String> {
+        let to = to
+            .parse::<Mailbox>()
+            .map_err(|_| format!("{} is not an email address", to))?;
+        Message::builder()
+            .from(sender.clone())
+            .to(to)
+            .subject(self.subject())
+            .multipart(MultiPart::alternative_plain_html(self.text(), self.html()))
+            .map_err(|e| e.to_string())
+    }
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Stops digests for the activity group `token` belongs to, returning its
+/// id.
+#[tracing::instrument(skip_all)]
+pub async fn opt_out_of_digest(token: &str, pool: &MySqlPool) -> Result<i32, sqlx::Error> {
+    let _timer = query_timer("opt_out_of_digest");
+    let query = sqlx::query!(
+        "select activity_group_id from activity_digests where token = ?",
+        token
+    )
+    .in_span(|query| query.fetch_one(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    sqlx::query!(
+        "update activity_digests set opted_out_at = ? where token = ? and opted_out_at is null",
+        Utc::now().trunc_subsecs(0),
+        token
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(query.activity_group_id)
+}
+
+/// Mails each activity group with an email its digest once per period.
+pub struct DigestJob {
+    pool: MySqlPool,
+    mailer: Mailer,
+    settings: DigestSettings,
+    base_url: String,
+}
+
+impl DigestJob {
+    /// `base_url` is the public address opt-out links point at.
+    pub fn new(
+        pool: MySqlPool,
+        mailer: Mailer,
+        settings: DigestSettings,
+        base_url: String,
+    ) -> Self {
+        Self {
+            pool,
+            mailer,
+            settings,
+            base_url,
+        }
+    }
+
+    pub async fn run(self) {
+        let poll_interval = Duration::from_secs(self.settings.poll_interval_seconds);
+        loop {
+            if let Err(e) = self.send_due(Utc::now()).await {
+                tracing::error!("Failed to send digests: {:?}", e);
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Sends the digests not yet sent this period, returning how many went
+    /// out. A group is claimed by stamping `last_sent_at` first, so several
+    /// instances never mail it twice; the stamp is put back if the digest
+    /// can't be sent.
+    #[tracing::instrument(skip(self))]
+    pub async fn send_due(&self, now: DateTime<Utc>) -> Result<usize, sqlx::Error> {
+        let _timer = query_timer("DigestJob::send_due");
+        let period_start = digest_period_start(&self.settings, now);
+        let due = sqlx::query!(
+            r#"
+            select a.id
+            from activities a
+            left join activity_digests d on d.activity_group_id = a.id
+            where a.email is not null and a.email <> ''
+            and d.opted_out_at is null
+            and (d.last_sent_at is null or d.last_sent_at < ?)
+            order by a.id
+            "#,
+            period_start
+        )
+        .in_span(|query| query.fetch_all(TimedPool(&self.pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+        let mut sent = 0;
+        for activity in due {
+            if self.send(activity.id, now, period_start).await? {
+                sent += 1;
+            }
+        }
+
+        Ok(sent)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn send(
+        &self,
+        activity_group_id: i32,
+        now: DateTime<Utc>,
+        period_start: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let utc_now = now.trunc_subsecs(0);
+        sqlx::query!(
+            "insert ignore into activity_digests (activity_group_id, token, created_at) values (?, ?, ?)",
+            activity_group_id,
+            Uuid::new_v4().to_simple().to_string(),
+            utc_now
+        )
+        .in_span(|query| query.execute(TimedPool(&self.pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+        let subscription = sqlx::query!(
+            "select token, last_sent_at from activity_digests where activity_group_id = ?",
+            activity_group_id
+        )
+        .in_span(|query| query.fetch_one(TimedPool(&self.pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+        let claimed = sqlx::query!(
+            r#"
+            update activity_digests set last_sent_at = ?
+            where activity_group_id = ? and opted_out_at is null
+            and (last_sent_at is null or last_sent_at < ?)
+            "#,
+            utc_now,
+            activity_group_id,
+            period_start
+        )
+        .in_span(|query| query.execute(TimedPool(&self.pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+        if claimed.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        let completed_since = subscription
+            .last_sent_at
+            .map(|last_sent_at| Utc.from_utc_datetime(&last_sent_at))
+            .unwrap_or(period_start - digest_period(self.settings.frequency));
+        match self
+            .deliver(activity_group_id, &subscription.token, now, completed_since)
+            .await
+        {
+            Ok(sent) => Ok(sent),
+            Err(e) => {
+                tracing::warn!(
+                    "Digest for activity group {} failed: {}",
+                    activity_group_id,
+                    e
+                );
+                // Hand the period back so the next poll tries again.
+                sqlx::query!(
+                    "update activity_digests set last_sent_at = ? where activity_group_id = ? and last_sent_at = ?",
+                    subscription.last_sent_at,
+                    activity_group_id,
+                    utc_now
+                )
+                .in_span(|query| query.execute(TimedPool(&self.pool)))
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to execute query: {:?}", e);
+                    e
+                })?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Builds and mails the digest, returning whether there was anything to
+    /// send.
+    async fn deliver(
+        &self,
+        activity_group_id: i32,
+        token: &str,
+        now: DateTime<Utc>,
+        completed_since: DateTime<Utc>,
+    ) -> Result<bool, String> {
+        let activity = get_activity_by_id(activity_group_id, &self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        let todos = get_todos(
+            &TodoFilter {
+                activity_group_id: Some(activity_group_id),
+                ..TodoFilter::default()
+            },
+            &self.pool,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+        let digest = Digest::new(
+            &activity,
+            todos,
+            self.settings.frequency,
+            now,
+            completed_since,
+            digest_opt_out_url(&self.base_url, token),
+        );
+        if digest.is_empty() {
+            return Ok(false);
+        }
+
+        let email = activity.email.unwrap_or_default();
+        let message = digest.message(self.mailer.sender(), &email)?;
+        self.mailer.send(message).await?;
+        Ok(true)
+    }
+}
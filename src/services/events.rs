@@ -0,0 +1,151 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use tokio::sync::broadcast;
+
+use crate::common::{RequestId, Timezone};
+use crate::services::{ActivityRecord, TodoRecord};
+pub use zero2prod_types::{ChangeAction, ChangeEntity, ChangeEvent};
+
+/// Events kept for `Last-Event-ID` resume when none is configured.
+pub const DEFAULT_EVENT_BUFFER: usize = 1024;
+
+/// A published change together with its stream id.
+#[derive(Clone, Debug)]
+pub struct StoredEvent {
+    pub id: u64,
+    pub event: ChangeEvent,
+    /// The request that made the change, for correlating follow-up work.
+    pub request_id: Option<RequestId>,
+}
+
+/// Fans todo and activity group changes out to `GET /events` subscribers.
+///
+/// The most recent events are kept in a bounded buffer so that a client
+/// reconnecting with `Last-Event-ID` can be sent what it missed.
+pub struct EventBus {
+    state: Mutex<BufferState>,
+    sender: broadcast::Sender<Arc<StoredEvent>>,
+    capacity: usize,
+}
+
+struct BufferState {
+    next_id: u64,
+    buffer: VecDeque<Arc<StoredEvent>>,
+}
+
+/// What a new subscriber gets: the buffered events after its last seen id,
+/// followed by everything published from then on.
+pub struct Subscription {
+    pub missed: Vec<Arc<StoredEvent>>,
+    /// Id of the newest event published before subscribing.
+    pub newest_id: u64,
+    pub receiver: broadcast::Receiver<Arc<StoredEvent>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_BUFFER)
+    }
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            state: Mutex::new(BufferState {
+                // Ids start at the current time in milliseconds so that they
+                // keep increasing across restarts and a stale Last-Event-ID
+                // never hides events published by the new process.
+                next_id: Utc::now().timestamp_millis().max(0) as u64,
+                buffer: VecDeque::with_capacity(capacity),
+            }),
+            sender,
+            capacity,
+        }
+    }
+
+    pub fn publish(&self, event: ChangeEvent) -> u64 {
+        let mut state = self.state.lock().expect("event buffer lock poisoned");
+        state.next_id += 1;
+        let stored = Arc::new(StoredEvent {
+            id: state.next_id,
+            event,
+            request_id: RequestId::current(),
+        });
+        if state.buffer.len() == self.capacity {
+            state.buffer.pop_front();
+        }
+        state.buffer.push_back(stored.clone());
+        // Sent under the lock so that `subscribe` never sees an event both
+        // in the buffer and on its receiver, or in neither.
+        let _ = self.sender.send(stored);
+        state.next_id
+    }
+
+    /// Subscribes to new events, replaying buffered ones with an id above
+    /// `last_event_id`. Without one, nothing is replayed.
+    pub fn subscribe(&self, last_event_id: Option<u64>) -> Subscription {
+        let state = self.state.lock().expect("event buffer lock poisoned");
+        let missed = match last_event_id {
+            Some(last) => state
+                .buffer
+                .iter()
+                .filter(|stored| stored.id > last)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        Subscription {
+            missed,
+            newest_id: state.next_id,
+            receiver: self.sender.subscribe(),
+        }
+    }
+
+    pub fn todo_changed(&self, action: ChangeAction, todo: &TodoRecord) {
+        self.publish(ChangeEvent {
+            entity: ChangeEntity::Todo,
+            action,
+            id: todo.id,
+            activity_group_id: todo.activity_group_id,
+            todo: Some(todo.clone().into_v1(&Timezone::default())),
+            activity_group: None,
+        });
+    }
+
+    pub fn todo_deleted(&self, todo_id: i32, activity_group_id: i32) {
+        self.publish(ChangeEvent {
+            entity: ChangeEntity::Todo,
+            action: ChangeAction::Deleted,
+            id: todo_id,
+            activity_group_id,
+            todo: None,
+            activity_group: None,
+        });
+    }
+
+    pub fn activity_changed(&self, action: ChangeAction, activity: &ActivityRecord) {
+        self.publish(ChangeEvent {
+            entity: ChangeEntity::ActivityGroup,
+            action,
+            id: activity.id,
+            activity_group_id: activity.id,
+            todo: None,
+            activity_group: Some(activity.clone().into_v1(&Timezone::default())),
+        });
+    }
+
+    pub fn activity_deleted(&self, activity_id: i32) {
+        self.publish(ChangeEvent {
+            entity: ChangeEntity::ActivityGroup,
+            action: ChangeAction::Deleted,
+            id: activity_id,
+            activity_group_id: activity_id,
+            todo: None,
+            activity_group: None,
+        });
+    }
+}
@@ -0,0 +1,172 @@
+use crate::common::TitleField;
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{ActivityRecord, ChangeAction, EventBus, TodoRecord};
+use crate::telemetry::QuerySpan;
+use chrono::{DateTime, SubsecRound, Utc};
+use sqlx::MySqlPool;
+use std::collections::{BTreeMap, HashSet};
+use std::hash::Hash;
+
+pub use zero2prod_types::{ExternalImportReport, UnmappedField};
+
+/// Activity groups read from another tool's export, ready to insert.
+#[derive(Debug, Default, PartialEq)]
+pub struct ExternalImport {
+    pub groups: Vec<ExternalGroup>,
+    /// Archived or deleted entries left out.
+    pub skipped: u64,
+    pub unmapped: UnmappedFields,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ExternalGroup {
+    pub title: String,
+    /// In the order the source shows them.
+    pub todos: Vec<ExternalTodo>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ExternalTodo {
+    pub title: String,
+    pub priority: String,
+    pub is_active: bool,
+    pub due_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Counts the entries carrying each field an import has no place for.
+#[derive(Debug, Default, PartialEq)]
+pub struct UnmappedFields(BTreeMap<&'static str, u64>);
+
+impl UnmappedFields {
+    /// Counts `field` once when `is_set`.
+    pub fn count(&mut self, field: &'static str, is_set: bool) {
+        self.add(field, is_set as u64);
+    }
+
+    /// Counts `field` on `entries` more entries.
+    pub fn add(&mut self, field: &'static str, entries: u64) {
+        if entries > 0 {
+            *self.0.entry(field).or_default() += entries;
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<UnmappedField> {
+        self.0
+            .into_iter()
+            .map(|(field, count)| UnmappedField {
+                field: field.into(),
+                count,
+            })
+            .collect()
+    }
+}
+
+/// A title checked like one sent to the API, or a message naming `what`
+/// lacks one.
+pub fn external_title(title: &str, what: impl FnOnce() -> String) -> Result<String, String> {
+    TitleField::parse(title.split_whitespace().collect::<Vec<_>>().join(" "))
+        .map(|title| title.inner_ref().into())
+        .map_err(|_| format!("{} has no title", what()))
+}
+
+/// Rejects entries sharing an id, which would otherwise be imported twice;
+/// `what` names the entry, e.g. `list l1`.
+pub fn unique_ids<'a, T: Eq + Hash + 'a>(
+    ids: impl IntoIterator<Item = &'a T>,
+    what: impl FnOnce(&T) -> String,
+) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    match ids.into_iter().find(|id| !seen.insert(*id)) {
+        Some(id) => Err(format!("{} appears more than once", what(id))),
+        None => Ok(()),
+    }
+}
+
+/// Inserts every group and todo of an import in one transaction. Missing
+/// timestamps are now, and a todo is never updated before it was created.
+/// The groups and todos are published on `events` once committed.
+#[tracing::instrument(skip_all)]
+pub async fn insert_external_import(
+    groups: Vec<ExternalGroup>,
+    pool: &MySqlPool,
+    events: &EventBus,
+) -> Result<Vec<(ActivityRecord, Vec<TodoRecord>)>, sqlx::Error> {
+    let _timer = query_timer("insert_external_import");
+    let now = Utc::now().trunc_subsecs(0);
+    let mut inserted = Vec::with_capacity(groups.len());
+    let mut tx = TimedPool(pool).begin().await?;
+    for group in groups {
+        let query = sqlx::query!(
+            r#"
+            insert into activities (title, created_at, updated_at)
+            values (?, ?, ?)
+            "#,
+            group.title,
+            now,
+            now
+        )
+        .in_span(|query| query.execute(&mut tx))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+        let activity = ActivityRecord {
+            id: query.last_insert_id() as i32,
+            title: group.title,
+            email: None,
+            created_at: now,
+            updated_at: Some(now),
+        };
+
+        let mut todos = Vec::with_capacity(group.todos.len());
+        for todo in group.todos {
+            let created_at = todo.created_at.map_or(now, |at| at.trunc_subsecs(0));
+            let updated_at = todo
+                .updated_at
+                .map_or(now, |at| at.trunc_subsecs(0))
+                .max(created_at);
+            let due_at = todo.due_at.map(|due_at| due_at.trunc_subsecs(0));
+            let query = sqlx::query!(
+                r#"
+                insert into todos (title, activity_group_id, is_active, priority, due_at, created_at, updated_at)
+                values (?, ?, ?, ?, ?, ?, ?)
+                "#,
+                todo.title,
+                activity.id,
+                todo.is_active,
+                todo.priority,
+                due_at,
+                created_at,
+                updated_at
+            )
+            .in_span(|query| query.execute(&mut tx))
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to execute query: {:?}", e);
+                e
+            })?;
+            todos.push(TodoRecord {
+                id: query.last_insert_id() as i32,
+                title: todo.title,
+                activity_group_id: activity.id,
+                is_active: todo.is_active,
+                priority: todo.priority,
+                due_at,
+                created_at,
+                updated_at: Some(updated_at),
+            });
+        }
+        inserted.push((activity, todos));
+    }
+    tx.commit().await?;
+    for (activity, todos) in &inserted {
+        events.activity_changed(ChangeAction::Created, activity);
+        for todo in todos {
+            events.todo_changed(ChangeAction::Created, todo);
+        }
+    }
+    Ok(inserted)
+}
@@ -0,0 +1,453 @@
+use crate::common::{Timezone, TitleField};
+use crate::services::{
+    default_as_true, default_as_very_high, delete_activity_by_id, delete_todo_by_id,
+    get_activities, get_activities_by_ids, get_activity_by_id, get_todo_by_id,
+    get_todos_by_activity_ids, insert_activity, insert_todo, update_activity_by_id,
+    update_todo_by_id,
+};
+use crate::services::{
+    ActivityRecord, EventBus, NewActivity, NewTodo, TodoRecord, UpdateActivity, UpdateTodo,
+};
+use async_graphql::dataloader::{DataLoader, HashMapCache, Loader};
+use async_graphql::{Context, EmptySubscription, ErrorExtensions, InputObject, Object, Schema};
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use sqlx::MySqlPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use zero2prod_types::{TodoFormData, TodoFormUpdateData};
+
+pub type GraphqlSchema = Schema<GraphqlQuery, GraphqlMutation, EmptySubscription>;
+
+/// How deeply a query may nest fields. GraphiQL's introspection query needs
+/// 15 levels.
+pub const GRAPHQL_DEPTH_LIMIT: usize = 16;
+/// How many fields a query may select, counting each field under
+/// `ActivityGroup.todos` `TODOS_COMPLEXITY` times.
+pub const GRAPHQL_COMPLEXITY_LIMIT: usize = 1000;
+const TODOS_COMPLEXITY: usize = 10;
+
+/// The schema served at `/graphql`. Resolvers also need the data that
+/// `graphql_request` adds to every request.
+pub fn graphql_schema(pool: MySqlPool, events: Arc<EventBus>) -> GraphqlSchema {
+    Schema::build(GraphqlQuery, GraphqlMutation, EmptySubscription)
+        .limit_depth(GRAPHQL_DEPTH_LIMIT)
+        .limit_complexity(GRAPHQL_COMPLEXITY_LIMIT)
+        .data(pool)
+        .data(events)
+        .finish()
+}
+
+/// Adds the caller's timezone to `request`, and loaders whose caches last
+/// only as long as it does.
+pub fn graphql_request(
+    request: async_graphql::Request,
+    pool: &MySqlPool,
+    tz: Timezone,
+) -> async_graphql::Request {
+    request
+        .data(tz)
+        .data(DataLoader::with_cache(
+            TodosByActivityLoader(pool.clone()),
+            tokio::spawn,
+            HashMapCache::default(),
+        ))
+        .data(DataLoader::with_cache(
+            ActivityLoader(pool.clone()),
+            tokio::spawn,
+            HashMapCache::default(),
+        ))
+}
+
+/// Loads the todos of every activity group resolved in a request with one
+/// query, rather than one per group.
+pub struct TodosByActivityLoader(MySqlPool);
+
+impl Loader<i32> for TodosByActivityLoader {
+    type Value = Vec<TodoRecord>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[i32]) -> Result<HashMap<i32, Self::Value>, Self::Error> {
+        let mut todos: HashMap<i32, Vec<TodoRecord>> =
+            keys.iter().map(|id| (*id, Vec::new())).collect();
+        for todo in get_todos_by_activity_ids(keys, &self.0).await? {
+            todos.entry(todo.activity_group_id).or_default().push(todo);
+        }
+        Ok(todos)
+    }
+}
+
+/// Loads the activity group of every todo resolved in a request with one
+/// query.
+pub struct ActivityLoader(MySqlPool);
+
+impl Loader<i32> for ActivityLoader {
+    type Value = ActivityRecord;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[i32]) -> Result<HashMap<i32, Self::Value>, Self::Error> {
+        Ok(get_activities_by_ids(keys, &self.0)
+            .await?
+            .into_iter()
+            .map(|activity| (activity.id, activity))
+            .collect())
+    }
+}
+
+/// Errors carry the `code` of the matching v2 error in their extensions.
+fn error(code: &'static str, message: impl Into<String>) -> async_graphql::Error {
+    async_graphql::Error::new(message).extend_with(|_, e| e.set("code", code))
+}
+
+fn internal_error(e: impl std::fmt::Debug) -> async_graphql::Error {
+    tracing::error!("Failed to execute query: {:?}", e);
+    error("internal_error", "Internal Server Error")
+}
+
+/// Maps a failed lookup onto `not_found` and anything else onto
+/// `internal_error`.
+fn lookup_error(e: sqlx::Error, not_found_message: String) -> async_graphql::Error {
+    match e {
+        sqlx::Error::RowNotFound => error("not_found", not_found_message),
+        e => internal_error(e),
+    }
+}
+
+/// A lookup that finds nothing resolves to null.
+fn optional<T>(result: Result<T, sqlx::Error>) -> async_graphql::Result<Option<T>> {
+    match result {
+        Ok(record) => Ok(Some(record)),
+        Err(sqlx::Error::RowNotFound) => Ok(None),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+fn activity_not_found(activity_id: i32) -> String {
+    format!("Activity with ID {} Not Found", activity_id)
+}
+
+fn todo_not_found(todo_id: i32) -> String {
+    format!("Todo with ID {} Not Found", todo_id)
+}
+
+pub struct GraphqlActivityGroup(ActivityRecord);
+
+#[Object(name = "ActivityGroup")]
+impl GraphqlActivityGroup {
+    async fn id(&self) -> i32 {
+        self.0.id
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    async fn email(&self) -> Option<&str> {
+        self.0.email.as_deref()
+    }
+
+    async fn created_at(&self, ctx: &Context<'_>) -> async_graphql::Result<DateTime<FixedOffset>> {
+        Ok(ctx.data::<Timezone>()?.localize(self.0.created_at))
+    }
+
+    async fn updated_at(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Option<DateTime<FixedOffset>>> {
+        let tz = ctx.data::<Timezone>()?;
+        Ok(self.0.updated_at.map(|updated_at| tz.localize(updated_at)))
+    }
+
+    /// The todos of this group, oldest first.
+    // Weighted as a list, so nesting `todos` through `activityGroup` runs
+    // into the complexity limit after a couple of levels.
+    #[graphql(complexity = "TODOS_COMPLEXITY * child_complexity")]
+    async fn todos(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<GraphqlTodoFilter>,
+    ) -> async_graphql::Result<Vec<GraphqlTodo>> {
+        let filter = filter.unwrap_or_default();
+        let tz = ctx.data::<Timezone>()?;
+        let created_from = filter.created_from.map(|date| tz.start_of_day(date));
+        let created_before = filter.created_to.map(|date| tz.end_of_day(date));
+        Ok(self
+            .load_todos(ctx)
+            .await?
+            .into_iter()
+            .filter(|todo| {
+                filter
+                    .is_active
+                    .is_none_or(|is_active| todo.is_active == is_active)
+                    && filter
+                        .priority
+                        .as_ref()
+                        .is_none_or(|priority| todo.priority == *priority)
+                    && created_from.is_none_or(|from| todo.created_at >= from)
+                    && created_before.is_none_or(|before| todo.created_at < before)
+            })
+            .map(GraphqlTodo)
+            .collect())
+    }
+
+    async fn todo_count(&self, ctx: &Context<'_>) -> async_graphql::Result<usize> {
+        Ok(self.load_todos(ctx).await?.len())
+    }
+
+    async fn active_todo_count(&self, ctx: &Context<'_>) -> async_graphql::Result<usize> {
+        let todos = self.load_todos(ctx).await?;
+        Ok(todos.iter().filter(|todo| todo.is_active).count())
+    }
+}
+
+impl GraphqlActivityGroup {
+    async fn load_todos(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<TodoRecord>> {
+        let loader = ctx.data::<DataLoader<TodosByActivityLoader, HashMapCache>>()?;
+        Ok(loader
+            .load_one(self.0.id)
+            .await
+            .map_err(internal_error)?
+            .unwrap_or_default())
+    }
+}
+
+pub struct GraphqlTodo(TodoRecord);
+
+#[Object(name = "Todo")]
+impl GraphqlTodo {
+    async fn id(&self) -> i32 {
+        self.0.id
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    async fn activity_group_id(&self) -> i32 {
+        self.0.activity_group_id
+    }
+
+    async fn is_active(&self) -> bool {
+        self.0.is_active
+    }
+
+    async fn priority(&self) -> &str {
+        &self.0.priority
+    }
+
+    async fn due_at(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Option<DateTime<FixedOffset>>> {
+        let tz = ctx.data::<Timezone>()?;
+        Ok(self.0.due_at.map(|due_at| tz.localize(due_at)))
+    }
+
+    async fn created_at(&self, ctx: &Context<'_>) -> async_graphql::Result<DateTime<FixedOffset>> {
+        Ok(ctx.data::<Timezone>()?.localize(self.0.created_at))
+    }
+
+    async fn updated_at(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Option<DateTime<FixedOffset>>> {
+        let tz = ctx.data::<Timezone>()?;
+        Ok(self.0.updated_at.map(|updated_at| tz.localize(updated_at)))
+    }
+
+    async fn activity_group(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Option<GraphqlActivityGroup>> {
+        let loader = ctx.data::<DataLoader<ActivityLoader, HashMapCache>>()?;
+        Ok(loader
+            .load_one(self.0.activity_group_id)
+            .await
+            .map_err(internal_error)?
+            .map(GraphqlActivityGroup))
+    }
+}
+
+/// Narrows the todos of a group; every unset field matches all of them.
+#[derive(InputObject, Default)]
+#[graphql(name = "TodoFilter")]
+pub struct GraphqlTodoFilter {
+    pub is_active: Option<bool>,
+    pub priority: Option<String>,
+    /// Only todos created on or after this date, in the caller's timezone.
+    pub created_from: Option<NaiveDate>,
+    /// Only todos created on or before this date, in the caller's timezone.
+    pub created_to: Option<NaiveDate>,
+}
+
+#[derive(InputObject)]
+#[graphql(name = "ActivityGroupInput")]
+pub struct GraphqlActivityGroupInput {
+    pub title: String,
+    pub email: Option<String>,
+}
+
+#[derive(InputObject)]
+#[graphql(name = "NewTodoInput")]
+pub struct GraphqlNewTodoInput {
+    pub title: String,
+    pub activity_group_id: i32,
+    #[graphql(default_with = "default_as_very_high()")]
+    pub priority: String,
+    #[graphql(default_with = "default_as_true()")]
+    pub is_active: bool,
+    pub due_at: Option<DateTime<FixedOffset>>,
+}
+
+/// Changes to a todo; unset fields are left as they are.
+#[derive(InputObject)]
+#[graphql(name = "UpdateTodoInput")]
+pub struct GraphqlUpdateTodoInput {
+    pub title: Option<String>,
+    pub activity_group_id: Option<i32>,
+    pub priority: Option<String>,
+    pub is_active: Option<bool>,
+    pub due_at: Option<DateTime<FixedOffset>>,
+}
+
+pub struct GraphqlQuery;
+
+#[Object(name = "Query")]
+impl GraphqlQuery {
+    async fn activity_groups(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Vec<GraphqlActivityGroup>> {
+        let activities = get_activities(ctx.data::<MySqlPool>()?)
+            .await
+            .map_err(internal_error)?;
+        Ok(activities.into_iter().map(GraphqlActivityGroup).collect())
+    }
+
+    /// Null when there is no such group.
+    async fn activity_group(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+    ) -> async_graphql::Result<Option<GraphqlActivityGroup>> {
+        let activity = get_activity_by_id(id, ctx.data::<MySqlPool>()?).await;
+        Ok(optional(activity)?.map(GraphqlActivityGroup))
+    }
+
+    /// Null when there is no such todo.
+    async fn todo(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Option<GraphqlTodo>> {
+        let todo = get_todo_by_id(id, ctx.data::<MySqlPool>()?).await;
+        Ok(optional(todo)?.map(GraphqlTodo))
+    }
+}
+
+/// The v1 create, update and delete endpoints, with the same validation and
+/// the same events.
+pub struct GraphqlMutation;
+
+#[Object(name = "Mutation")]
+impl GraphqlMutation {
+    async fn create_activity_group(
+        &self,
+        ctx: &Context<'_>,
+        input: GraphqlActivityGroupInput,
+    ) -> async_graphql::Result<GraphqlActivityGroup> {
+        let activity = NewActivity {
+            title: activity_title(input.title)?,
+            email: input.email,
+        };
+        let pool = ctx.data::<MySqlPool>()?;
+        let events = ctx.data::<Arc<EventBus>>()?;
+        let activity = insert_activity(pool, &activity, events)
+            .await
+            .map_err(internal_error)?;
+        Ok(GraphqlActivityGroup(activity))
+    }
+
+    async fn update_activity_group(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        input: GraphqlActivityGroupInput,
+    ) -> async_graphql::Result<GraphqlActivityGroup> {
+        let activity = UpdateActivity {
+            title: activity_title(input.title)?,
+            email: input.email,
+        };
+        let pool = ctx.data::<MySqlPool>()?;
+        let events = ctx.data::<Arc<EventBus>>()?;
+        let activity = update_activity_by_id(id, pool, &activity, events)
+            .await
+            .map_err(|e| lookup_error(e, activity_not_found(id)))?;
+        Ok(GraphqlActivityGroup(activity))
+    }
+
+    /// Always true.
+    async fn delete_activity_group(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+    ) -> async_graphql::Result<bool> {
+        let pool = ctx.data::<MySqlPool>()?;
+        let events = ctx.data::<Arc<EventBus>>()?;
+        delete_activity_by_id(id, pool, events)
+            .await
+            .map_err(|e| lookup_error(e, activity_not_found(id)))?;
+        Ok(true)
+    }
+
+    async fn create_todo(
+        &self,
+        ctx: &Context<'_>,
+        input: GraphqlNewTodoInput,
+    ) -> async_graphql::Result<GraphqlTodo> {
+        let todo = NewTodo::parse(TodoFormData {
+            title: input.title,
+            activity_group_id: Some(input.activity_group_id),
+            priority: input.priority,
+            is_active: input.is_active,
+            due_at: input.due_at,
+        })
+        .map_err(|message| error("validation_failed", message))?;
+        let pool = ctx.data::<MySqlPool>()?;
+        let events = ctx.data::<Arc<EventBus>>()?;
+        let todo = insert_todo(pool, &todo, events)
+            .await
+            .map_err(internal_error)?;
+        Ok(GraphqlTodo(todo))
+    }
+
+    async fn update_todo(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        input: GraphqlUpdateTodoInput,
+    ) -> async_graphql::Result<GraphqlTodo> {
+        let todo = UpdateTodo::from(TodoFormUpdateData {
+            title: input.title,
+            activity_group_id: input.activity_group_id,
+            priority: input.priority,
+            is_active: input.is_active,
+            due_at: input.due_at,
+        });
+        let pool = ctx.data::<MySqlPool>()?;
+        let events = ctx.data::<Arc<EventBus>>()?;
+        let todo = update_todo_by_id(id, pool, &todo, events)
+            .await
+            .map_err(|e| lookup_error(e, todo_not_found(id)))?;
+        Ok(GraphqlTodo(todo))
+    }
+
+    /// Always true.
+    async fn delete_todo(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<bool> {
+        let pool = ctx.data::<MySqlPool>()?;
+        let events = ctx.data::<Arc<EventBus>>()?;
+        delete_todo_by_id(id, pool, events)
+            .await
+            .map_err(|e| lookup_error(e, todo_not_found(id)))?;
+        Ok(true)
+    }
+}
+
+fn activity_title(title: String) -> async_graphql::Result<TitleField> {
+    TitleField::parse(title).map_err(|_| error("validation_failed", "title cannot be null"))
+}
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use sqlx::migrate::Migrate;
+use sqlx::{Connection, MySqlPool};
+
+use crate::services::MIGRATOR;
+
+pub use zero2prod_types::{ComponentHealth, HealthReport, HealthStatus};
+
+/// How long each readiness check may take before it counts as down.
+pub const READINESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Checks that MySQL answers a ping and that every migration this build
+/// knows about has been applied.
+#[tracing::instrument(skip_all)]
+pub async fn check_readiness(pool: &MySqlPool, timeout: Duration) -> HealthReport {
+    let database = check(timeout, async {
+        let mut connection = pool.acquire().await.map_err(|e| e.to_string())?;
+        connection.ping().await.map_err(|e| e.to_string())
+    })
+    .await;
+    let migrations = check(timeout, async {
+        let mut connection = pool.acquire().await.map_err(|e| e.to_string())?;
+        let applied = connection
+            .list_applied_migrations()
+            .await
+            .map_err(|e| e.to_string())?;
+        let pending = MIGRATOR
+            .iter()
+            .filter(|migration| !applied.iter().any(|a| a.version == migration.version))
+            .count();
+        match pending {
+            0 => Ok(()),
+            1 => Err("1 migration is not applied".to_string()),
+            n => Err(format!("{} migrations are not applied", n)),
+        }
+    })
+    .await;
+
+    HealthReport::new(BTreeMap::from([
+        ("database".to_string(), database),
+        ("migrations".to_string(), migrations),
+    ]))
+}
+
+async fn check<F>(timeout: Duration, check: F) -> ComponentHealth
+where
+    F: Future<Output = Result<(), String>>,
+{
+    let started = Instant::now();
+    let result = match tokio::time::timeout(timeout, check).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("timed out after {}ms", timeout.as_millis())),
+    };
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    match result {
+        Ok(()) => ComponentHealth {
+            status: HealthStatus::Up,
+            latency_ms,
+            message: None,
+        },
+        Err(message) => {
+            tracing::warn!("Readiness check failed: {}", message);
+            ComponentHealth {
+                status: HealthStatus::Down,
+                latency_ms,
+                message: Some(message),
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use lettre::address::Envelope;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use secrecy::ExposeSecret;
+
+use crate::configuration::{EmailSettings, SmtpSettings};
+
+/// Sends mail over SMTP or, in test mode, writes each email to a directory.
+#[derive(Clone)]
+pub struct Mailer {
+    transport: Transport,
+    sender: Mailbox,
+}
+
+#[derive(Clone)]
+enum Transport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    Outbox(AsyncFileTransport<Tokio1Executor>),
+}
+
+impl Mailer {
+    pub fn from_settings(settings: &EmailSettings) -> Result<Self, String> {
+        let sender = settings
+            .sender
+            .parse::<Mailbox>()
+            .map_err(|e| format!("email.sender is not an email address: {}", e))?;
+        match (&settings.outbox_dir, &settings.smtp) {
+            (Some(dir), _) => Ok(Self::outbox(dir, sender)),
+            (None, Some(smtp)) => Ok(Self {
+                transport: Transport::Smtp(smtp_transport(smtp)?),
+                sender,
+            }),
+            (None, None) => Err("email needs either smtp or outbox_dir".into()),
+        }
+    }
+
+    /// Writes every email to `dir` as `<uuid>.eml`, creating it if needed.
+    pub fn outbox(dir: impl AsRef<std::path::Path>, sender: Mailbox) -> Self {
+        if let Err(e) = std::fs::create_dir_all(dir.as_ref()) {
+            tracing::error!("Failed to create the email outbox: {:?}", e);
+        }
+        Self {
+            transport: Transport::Outbox(AsyncFileTransport::new(dir)),
+            sender,
+        }
+    }
+
+    pub fn sender(&self) -> &Mailbox {
+        &self.sender
+    }
+}
+
+fn smtp_transport(settings: &SmtpSettings) -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
+    let mut transport = if settings.starttls {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)
+            .map_err(|e| e.to_string())?
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+    }
+    .port(settings.port);
+    if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+        transport = transport.credentials(Credentials::new(
+            username.clone(),
+            password.expose_secret().clone(),
+        ));
+    }
+    Ok(transport.build())
+}
+
+#[async_trait]
+impl AsyncTransport for Mailer {
+    type Ok = ();
+    type Error = String;
+
+    async fn send_raw(&self, envelope: &Envelope, email: &[u8]) -> Result<(), String> {
+        match &self.transport {
+            Transport::Smtp(smtp) => smtp
+                .send_raw(envelope, email)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            Transport::Outbox(outbox) => outbox
+                .send_raw(envelope, email)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// An email to a single recipient, as plain text with an optional HTML
+/// alternative.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+/// Delivers emails. Handlers hold one as `web::Data<dyn EmailSender>`, so
+/// tests can swap in a stub.
+#[async_trait]
+pub trait EmailSender: Send + Sync {
+    async fn send_email(&self, email: Email) -> Result<(), String>;
+}
+
+#[async_trait]
+impl EmailSender for Mailer {
+    #[tracing::instrument(skip_all)]
+    async fn send_email(&self, email: Email) -> Result<(), String> {
+        let to = email
+            .to
+            .parse::<Mailbox>()
+            .map_err(|_| format!("{} is not an email address", email.to))?;
+        let builder = Message::builder()
+            .from(self.sender.clone())
+            .to(to)
+            .subject(email.subject);
+        let message = match email.html {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(email.text, html)),
+            None => builder.body(email.text),
+        }
+        .map_err(|e| e.to_string())?;
+
+        self.send(message).await
+    }
+}
+
+/// Writes emails to the log instead of sending them; used while `email` is
+/// unset.
+#[derive(Default)]
+pub struct LogEmailSender;
+
+#[async_trait]
+impl EmailSender for LogEmailSender {
+    #[tracing::instrument(skip_all)]
+    async fn send_email(&self, email: Email) -> Result<(), String> {
+        tracing::warn!(
+            to = %email.to,
+            subject = %email.subject,
+            "Email is not configured, logging instead of sending:\n{}",
+            email.text
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+use crate::common::TitleField;
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{
+    default_as_very_high, ActivityRecord, ChangeAction, EventBus, ImportLineError, TodoRecord,
+    MAX_REPORTED_ROW_ERRORS, PRIORITIES,
+};
+use crate::telemetry::QuerySpan;
+use chrono::{SubsecRound, Utc};
+use sqlx::MySqlPool;
+
+/// Renders an activity group as a Markdown heading followed by a checklist
+/// of its todos, each ending in a `!priority` marker.
+pub fn render_markdown(activity: &ActivityRecord, todos: &[TodoRecord]) -> String {
+    let mut markdown = format!("# {}\n", single_line(&activity.title));
+    if !todos.is_empty() {
+        markdown.push('\n');
+    }
+    for todo in todos {
+        markdown.push_str(&format!(
+            "- [{}] {} !{}\n",
+            if todo.is_active { ' ' } else { 'x' },
+            single_line(&todo.title),
+            todo.priority
+        ));
+    }
+    markdown
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A heading and the checklist items below it.
+#[derive(Debug, PartialEq)]
+pub struct MarkdownSection {
+    pub title: String,
+    pub todos: Vec<MarkdownTodo>,
+}
+
+/// A `- [ ]` or `- [x]` checklist item.
+#[derive(Debug, PartialEq)]
+pub struct MarkdownTodo {
+    pub title: String,
+    pub priority: String,
+    pub is_active: bool,
+}
+
+/// Parses every heading that has checklist items below it into a section.
+/// Headings without any, prose, plain list items and fenced code blocks are
+/// skipped, so meeting notes can be imported as they are.
+pub fn parse_markdown(text: &str) -> Result<Vec<MarkdownSection>, Vec<ImportLineError>> {
+    let mut sections: Vec<(u64, MarkdownSection)> = Vec::new();
+    let mut errors = Vec::new();
+    let mut fence: Option<&str> = None;
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i as u64 + 1;
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker))
+        {
+            fence = Some(marker);
+            continue;
+        }
+
+        if let Some(title) = heading(line) {
+            sections.push((
+                line_number,
+                MarkdownSection {
+                    title,
+                    todos: Vec::new(),
+                },
+            ));
+        } else if let Some((is_active, text)) = checklist_item(trimmed) {
+            let result = match sections.last_mut() {
+                None => Err("the checklist item is not below a heading".to_string()),
+                Some((_, section)) => parse_item(is_active, text).map(|todo| {
+                    section.todos.push(todo);
+                }),
+            };
+            if let Err(message) = result {
+                errors.push(ImportLineError {
+                    line: line_number,
+                    message,
+                });
+            }
+        }
+    }
+
+    sections.retain(|(_, section)| !section.todos.is_empty());
+    for (line, section) in &sections {
+        if section.title.is_empty() {
+            errors.push(ImportLineError {
+                line: *line,
+                message: "the heading has no text".into(),
+            });
+        }
+    }
+    if !errors.is_empty() {
+        errors.sort_by_key(|error| error.line);
+        return Err(errors);
+    }
+    Ok(sections.into_iter().map(|(_, section)| section).collect())
+}
+
+/// The text of an ATX heading such as `## Action items ##`.
+fn heading(line: &str) -> Option<String> {
+    let text = line.strip_prefix('#')?.trim_start_matches('#');
+    if line.len() - text.len() > 6 || !(text.is_empty() || text.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let text = text.trim();
+    // A closing run of `#` only counts when set apart by a space.
+    let without_closing = text.trim_end_matches('#');
+    let text = if without_closing.is_empty() || without_closing.ends_with([' ', '\t']) {
+        without_closing.trim_end()
+    } else {
+        text
+    };
+    Some(single_line(text))
+}
+
+/// Whether a list item is a checklist item, ticked or not, and its text.
+fn checklist_item(line: &str) -> Option<(bool, &str)> {
+    let rest = match line.strip_prefix(['-', '*', '+']) {
+        Some(rest) => rest,
+        None => {
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 || digits > 9 {
+                return None;
+            }
+            line[digits..].strip_prefix(['.', ')'])?
+        }
+    };
+    let rest = rest.strip_prefix([' ', '\t'])?.trim_start();
+    let (is_active, text) = if let Some(text) = rest.strip_prefix("[ ]") {
+        (true, text)
+    } else if let Some(text) = rest
+        .strip_prefix("[x]")
+        .or_else(|| rest.strip_prefix("[X]"))
+    {
+        (false, text)
+    } else {
+        return None;
+    };
+    if !(text.is_empty() || text.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some((is_active, text))
+}
+
+fn parse_item(is_active: bool, text: &str) -> Result<MarkdownTodo, String> {
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    let priority = words
+        .last()
+        .and_then(|word| word.strip_prefix('!'))
+        .and_then(|priority| PRIORITIES.into_iter().find(|p| *p == priority));
+    if priority.is_some() {
+        words.pop();
+    }
+    let title = TitleField::parse(words.join(" ")).map_err(|_| "the checklist item has no text")?;
+    Ok(MarkdownTodo {
+        title: title.inner_ref().into(),
+        priority: priority.map_or_else(default_as_very_high, String::from),
+        is_active,
+    })
+}
+
+#[derive(Debug)]
+pub enum MarkdownImportError {
+    /// Some lines are invalid, so nothing was imported. Holds at most
+    /// `MAX_REPORTED_ROW_ERRORS` of them, and how many there were.
+    Lines(Vec<ImportLineError>, usize),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for MarkdownImportError {
+    fn from(e: sqlx::Error) -> Self {
+        tracing::error!("Failed to execute query: {:?}", e);
+        MarkdownImportError::Database(e)
+    }
+}
+
+/// Adds an activity group for every heading of `text` with checklist items,
+/// and a todo for each of those items; all of them or, when any line is
+/// invalid, none. The groups and todos are published on `events` once
+/// committed.
+#[tracing::instrument(skip(text, pool, events))]
+pub async fn import_markdown(
+    text: &str,
+    pool: &MySqlPool,
+    events: &EventBus,
+) -> Result<Vec<(ActivityRecord, Vec<TodoRecord>)>, MarkdownImportError> {
+    let _timer = query_timer("import_markdown");
+    let sections = parse_markdown(text).map_err(|mut errors| {
+        let invalid = errors.len();
+        errors.truncate(MAX_REPORTED_ROW_ERRORS);
+        MarkdownImportError::Lines(errors, invalid)
+    })?;
+
+    let now = Utc::now().trunc_subsecs(0);
+    let mut groups = Vec::with_capacity(sections.len());
+    let mut tx = TimedPool(pool).begin().await?;
+    for section in sections {
+        let query = sqlx::query!(
+            r#"
+            insert into activities (title, created_at, updated_at)
+            values (?, ?, ?)
+            "#,
+            section.title,
+            now,
+            now
+        )
+        .in_span(|query| query.execute(&mut tx))
+        .await?;
+        let activity = ActivityRecord {
+            id: query.last_insert_id() as i32,
+            title: section.title,
+            email: None,
+            created_at: now,
+            updated_at: Some(now),
+        };
+
+        let mut todos = Vec::with_capacity(section.todos.len());
+        for todo in section.todos {
+            let query = sqlx::query!(
+                r#"
+                insert into todos (title, activity_group_id, is_active, priority, created_at, updated_at)
+                values (?, ?, ?, ?, ?, ?)
+                "#,
+                todo.title,
+                activity.id,
+                todo.is_active,
+                todo.priority,
+                now,
+                now
+            )
+            .in_span(|query| query.execute(&mut tx))
+            .await?;
+            todos.push(TodoRecord {
+                id: query.last_insert_id() as i32,
+                title: todo.title,
+                activity_group_id: activity.id,
+                is_active: todo.is_active,
+                priority: todo.priority,
+                due_at: None,
+                created_at: now,
+                updated_at: Some(now),
+            });
+        }
+        groups.push((activity, todos));
+    }
+    tx.commit().await?;
+    for (activity, todos) in &groups {
+        events.activity_changed(ChangeAction::Created, activity);
+        for todo in todos {
+            events.todo_changed(ChangeAction::Created, todo);
+        }
+    }
+    Ok(groups)
+}
@@ -0,0 +1,110 @@
+use std::time::{Duration, Instant};
+
+use sqlx::migrate::{AppliedMigration, Migrate, Migrator};
+use sqlx::{Connection, MySqlPool};
+
+/// The migrations in `./migrations`, embedded at compile time.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// The `GET_LOCK` name that serialises migrations across replicas.
+pub const MIGRATION_LOCK: &str = "zero2prod.migrations";
+
+/// How long to wait for another replica to finish migrating.
+pub const MIGRATION_LOCK_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Retries until MySQL accepts a connection or `timeout` passes.
+pub async fn wait_for_database(pool: &MySqlPool, timeout: Duration) -> Result<(), String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match pool.acquire().await {
+            Ok(_) => return Ok(()),
+            Err(e) if Instant::now() >= deadline => {
+                return Err(format!("MySQL is unavailable: {}", e));
+            }
+            Err(e) => {
+                tracing::warn!("MySQL is still unavailable: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+/// Applies the pending migrations while holding `MIGRATION_LOCK`, so that
+/// replicas booting together take turns. Returns how many were applied.
+///
+/// Fails without touching the schema when the database has migrations this
+/// build doesn't know, i.e. a newer release already ran against it.
+#[tracing::instrument(skip(pool))]
+pub async fn run_migrations(pool: &MySqlPool, lock_timeout: Duration) -> Result<usize, String> {
+    // Taken out of the pool so that the lock, which belongs to the session,
+    // goes away with the connection even if a migration fails halfway.
+    let mut connection = pool
+        .acquire()
+        .await
+        .map_err(|e| format!("Failed to connect to MySQL: {}", e))?
+        .detach();
+
+    let locked: Option<i64> = sqlx::query_scalar("select get_lock(?, ?)")
+        .bind(MIGRATION_LOCK)
+        .bind(lock_timeout.as_secs())
+        .fetch_one(&mut connection)
+        .await
+        .map_err(|e| format!("Failed to take the migration lock: {}", e))?;
+    if locked != Some(1) {
+        return Err(format!(
+            "Another instance held the migration lock for more than {}s",
+            lock_timeout.as_secs()
+        ));
+    }
+
+    connection
+        .ensure_migrations_table()
+        .await
+        .map_err(|e| format!("Failed to create the migrations table: {}", e))?;
+    let applied = connection
+        .list_applied_migrations()
+        .await
+        .map_err(|e| format!("Failed to read applied migrations: {}", e))?;
+    ensure_schema_is_known(&applied)?;
+    let pending = MIGRATOR
+        .iter()
+        .filter(|migration| !applied.iter().any(|a| a.version == migration.version))
+        .count();
+
+    MIGRATOR
+        .run(&mut connection)
+        .await
+        .map_err(|e| format!("Failed to migrate the database: {}", e))?;
+    let _ = connection.close().await;
+
+    Ok(pending)
+}
+
+/// Fails when the database has migrations this build doesn't know, for
+/// deployments that migrate out of band.
+#[tracing::instrument(skip(pool))]
+pub async fn check_schema_version(pool: &MySqlPool) -> Result<(), String> {
+    let mut connection = pool
+        .acquire()
+        .await
+        .map_err(|e| format!("Failed to connect to MySQL: {}", e))?;
+    let applied = connection
+        .list_applied_migrations()
+        .await
+        .map_err(|e| format!("Failed to read applied migrations: {}", e))?;
+    ensure_schema_is_known(&applied)
+}
+
+fn ensure_schema_is_known(applied: &[AppliedMigration]) -> Result<(), String> {
+    match applied.iter().find(|a| {
+        !MIGRATOR
+            .iter()
+            .any(|migration| migration.version == a.version)
+    }) {
+        Some(unknown) => Err(format!(
+            "The database schema is newer than this build: migration {} is applied but unknown",
+            unknown.version
+        )),
+        None => Ok(()),
+    }
+}
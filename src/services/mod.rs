@@ -1,5 +1,51 @@
 mod activity;
+mod caldav;
+mod calendar;
+mod digest;
+mod events;
+mod external_import;
+mod graphql;
+mod health;
+mod mailer;
+mod markdown;
+mod migration;
+mod notifier;
+mod presence;
+mod reminder;
+mod reminder_scheduler;
+mod search;
+mod subscription;
 mod todo;
+mod todo_csv;
+mod todo_txt;
+mod todoist;
+mod transfer;
+mod trello;
+mod webhook;
+mod webhook_delivery;
 
 pub use activity::*;
+pub use caldav::*;
+pub use calendar::*;
+pub use digest::*;
+pub use events::*;
+pub use external_import::*;
+pub use graphql::*;
+pub use health::*;
+pub use mailer::*;
+pub use markdown::*;
+pub use migration::*;
+pub use notifier::*;
+pub use presence::*;
+pub use reminder::*;
+pub use reminder_scheduler::*;
+pub use search::*;
+pub use subscription::*;
 pub use todo::*;
+pub use todo_csv::*;
+pub use todo_txt::*;
+pub use todoist::*;
+pub use transfer::*;
+pub use trello::*;
+pub use webhook::*;
+pub use webhook_delivery::*;
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use lettre::message::Mailbox;
+use lettre::{AsyncTransport, Message};
+use secrecy::ExposeSecret;
+
+use crate::configuration::ReminderSettings;
+use crate::services::{sign, Mailer, ReminderChannel, EVENT_HEADER, SIGNATURE_HEADER};
+
+/// The event name webhook reminders are sent with.
+pub const REMINDER_EVENT: &str = "reminder.due";
+
+/// What a reminder tells its recipient about a todo.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Notification {
+    pub reminder_id: i32,
+    pub todo_id: i32,
+    pub title: String,
+    pub activity_group_id: i32,
+    pub activity_group_title: String,
+    pub due_at: Option<DateTime<Utc>>,
+    /// Email address or URL; the activity group's email unless the reminder
+    /// named one.
+    #[serde(skip)]
+    pub recipient: Option<String>,
+}
+
+impl Notification {
+    pub fn subject(&self) -> String {
+        format!("Reminder: {}", self.title)
+    }
+
+    pub fn body(&self) -> String {
+        match self.due_at {
+            Some(due_at) => format!(
+                "\"{}\" in {} is due at {}.",
+                self.title,
+                self.activity_group_title,
+                due_at.to_rfc3339()
+            ),
+            None => format!(
+                "\"{}\" in {} is still open.",
+                self.title, self.activity_group_title
+            ),
+        }
+    }
+}
+
+/// Sends notifications over one channel, failing with a message kept in the
+/// reminder's `last_error`.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, notification: &Notification) -> Result<(), String>;
+}
+
+/// Writes reminders to the server log.
+#[derive(Default)]
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    #[tracing::instrument(skip_all, fields(reminder_id = notification.reminder_id))]
+    async fn notify(&self, notification: &Notification) -> Result<(), String> {
+        tracing::info!(
+            reminder_id = notification.reminder_id,
+            todo_id = notification.todo_id,
+            recipient = notification.recipient.as_deref().unwrap_or_default(),
+            "{}",
+            notification.body()
+        );
+        Ok(())
+    }
+}
+
+/// Mails reminders through any lettre transport.
+pub struct EmailNotifier<T> {
+    transport: T,
+    sender: Mailbox,
+}
+
+impl<T> EmailNotifier<T> {
+    pub fn new(transport: T, sender: Mailbox) -> Self {
+        Self { transport, sender }
+    }
+}
+
+impl From<Mailer> for EmailNotifier<Mailer> {
+    fn from(mailer: Mailer) -> Self {
+        let sender = mailer.sender().clone();
+        Self::new(mailer, sender)
+    }
+}
+
+#[async_trait]
+impl<T> Notifier for EmailNotifier<T>
+where
+    T: AsyncTransport + Send + Sync,
+    T::Error: std::fmt::Display,
+{
+    #[tracing::instrument(skip_all, fields(reminder_id = notification.reminder_id))]
+    async fn notify(&self, notification: &Notification) -> Result<(), String> {
+        let recipient = notification
+            .recipient
+            .as_deref()
+            .ok_or("no recipient: the activity group has no email")?;
+        let to = recipient
+            .parse::<Mailbox>()
+            .map_err(|_| format!("{} is not an email address", recipient))?;
+        let message = Message::builder()
+            .from(self.sender.clone())
+            .to(to)
+            .subject(notification.subject())
+            .body(notification.body())
+            .map_err(|e| e.to_string())?;
+
+        self.transport
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// POSTs reminders as JSON to the recipient URL, signed like outbound
+/// webhooks when a secret is configured.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    secret: Option<String>,
+}
+
+impl WebhookNotifier {
+    pub fn new(timeout: Duration, secret: Option<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("Failed to build the reminder HTTP client");
+        Self { client, secret }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    #[tracing::instrument(skip_all, fields(reminder_id = notification.reminder_id))]
+    async fn notify(&self, notification: &Notification) -> Result<(), String> {
+        let url = notification
+            .recipient
+            .as_deref()
+            .ok_or("no recipient URL")?;
+        let payload = serde_json::to_string(notification).expect("notifications serialize to JSON");
+
+        let mut request = self
+            .client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header(EVENT_HEADER, REMINDER_EVENT);
+        if let Some(secret) = &self.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, payload.as_bytes()));
+        }
+
+        match request.body(payload).send().await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => Err(format!(
+                "HTTP {}: {}",
+                response.status().as_u16(),
+                response.text().await.unwrap_or_default()
+            )),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// The notifier for each reminder channel.
+pub struct Notifiers {
+    channels: HashMap<ReminderChannel, Box<dyn Notifier>>,
+}
+
+impl Notifiers {
+    /// Logging and webhook notifiers only; add email with `with`.
+    pub fn new(settings: &ReminderSettings) -> Self {
+        let webhook = WebhookNotifier::new(
+            Duration::from_secs(settings.timeout_seconds),
+            settings
+                .webhook_secret
+                .as_ref()
+                .map(|secret| secret.expose_secret().clone()),
+        );
+        Self {
+            channels: HashMap::new(),
+        }
+        .with(ReminderChannel::Log, LogNotifier)
+        .with(ReminderChannel::Webhook, webhook)
+    }
+
+    /// Every channel, with email sent through `mailer` when there is one.
+    pub fn from_settings(settings: &ReminderSettings, mailer: Option<Mailer>) -> Self {
+        let notifiers = Self::new(settings);
+        match mailer {
+            Some(mailer) => notifiers.with(ReminderChannel::Email, EmailNotifier::from(mailer)),
+            None => notifiers,
+        }
+    }
+
+    pub fn with(mut self, channel: ReminderChannel, notifier: impl Notifier + 'static) -> Self {
+        self.channels.insert(channel, Box::new(notifier));
+        self
+    }
+
+    pub fn get(&self, channel: ReminderChannel) -> Option<&dyn Notifier> {
+        self.channels
+            .get(&channel)
+            .map(|notifier| notifier.as_ref())
+    }
+}
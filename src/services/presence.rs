@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::broadcast;
+
+pub use zero2prod_types::Member;
+
+/// Join and leave notifications buffered per group before slow connections
+/// start missing them.
+const PRESENCE_CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PresenceChange {
+    Joined(Member),
+    Left(Member),
+}
+
+/// Who is connected to each activity group's WebSocket channel.
+#[derive(Default)]
+pub struct Presence {
+    groups: Mutex<HashMap<i32, Group>>,
+}
+
+struct Group {
+    members: Vec<Member>,
+    sender: broadcast::Sender<PresenceChange>,
+}
+
+/// Membership of one connection, released when dropped.
+pub struct PresenceGuard {
+    presence: Arc<Presence>,
+    activity_group_id: i32,
+    pub member: Member,
+}
+
+/// What a connection gets on joining: everyone connected, itself included,
+/// and the changes made by others from then on.
+pub struct Joined {
+    pub guard: PresenceGuard,
+    pub members: Vec<Member>,
+    pub receiver: broadcast::Receiver<PresenceChange>,
+}
+
+impl Presence {
+    pub fn join(self: &Arc<Self>, activity_group_id: i32, member: Member) -> Joined {
+        let mut groups = self.groups.lock().expect("presence lock poisoned");
+        let group = groups.entry(activity_group_id).or_insert_with(|| Group {
+            members: Vec::new(),
+            sender: broadcast::channel(PRESENCE_CHANNEL_CAPACITY).0,
+        });
+        group.members.push(member.clone());
+        let _ = group.sender.send(PresenceChange::Joined(member.clone()));
+
+        Joined {
+            members: group.members.clone(),
+            // Subscribed after announcing, so a member never hears itself join.
+            receiver: group.sender.subscribe(),
+            guard: PresenceGuard {
+                presence: self.clone(),
+                activity_group_id,
+                member,
+            },
+        }
+    }
+
+    pub fn members(&self, activity_group_id: i32) -> Vec<Member> {
+        let groups = self.groups.lock().expect("presence lock poisoned");
+        groups
+            .get(&activity_group_id)
+            .map(|group| group.members.clone())
+            .unwrap_or_default()
+    }
+
+    fn leave(&self, activity_group_id: i32, member: &Member) {
+        let mut groups = self.groups.lock().expect("presence lock poisoned");
+        if let Some(group) = groups.get_mut(&activity_group_id) {
+            group
+                .members
+                .retain(|m| m.connection_id != member.connection_id);
+            if group.members.is_empty() {
+                groups.remove(&activity_group_id);
+            } else {
+                let _ = group.sender.send(PresenceChange::Left(member.clone()));
+            }
+        }
+    }
+}
+
+impl Drop for PresenceGuard {
+    fn drop(&mut self) {
+        self.presence.leave(self.activity_group_id, &self.member);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::common::Timezone;
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{ActivityRecord, TodoRecord};
+use crate::telemetry::QuerySpan;
+use chrono::{DateTime, NaiveDateTime, SubsecRound, TimeZone, Utc};
+use lettre::message::Mailbox;
+use sqlx::MySqlPool;
+
+pub use zero2prod_types::{Reminder, ReminderChannel, ReminderFormData};
+
+/// A reminder as stored, with timestamps in UTC.
+#[derive(Clone, Debug)]
+pub struct ReminderRecord {
+    pub id: i32,
+    pub todo_id: i32,
+    pub remind_at: Option<DateTime<Utc>>,
+    pub offset_minutes: Option<i32>,
+    pub channel: ReminderChannel,
+    pub recipient: Option<String>,
+    pub fired_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ReminderRecord {
+    pub fn into_v1(self, tz: &Timezone) -> Reminder {
+        Reminder {
+            id: self.id,
+            todo_id: self.todo_id,
+            remind_at: self.remind_at.map(|remind_at| tz.localize(remind_at)),
+            offset_minutes: self.offset_minutes,
+            channel: self.channel,
+            recipient: self.recipient,
+            fired_at: self.fired_at.map(|fired_at| tz.localize(fired_at)),
+            last_error: self.last_error,
+            created_at: tz.localize(self.created_at),
+        }
+    }
+}
+
+pub struct NewReminder {
+    pub remind_at: Option<DateTime<Utc>>,
+    pub offset_minutes: Option<i32>,
+    pub channel: ReminderChannel,
+    pub recipient: Option<String>,
+}
+
+impl NewReminder {
+    /// Validates a create form for a reminder on `todo`, failing with the
+    /// message reported to the client.
+    pub fn parse(
+        form: ReminderFormData,
+        todo: &TodoRecord,
+        activity: &ActivityRecord,
+    ) -> Result<NewReminder, String> {
+        match (form.remind_at, form.offset_minutes) {
+            (None, None) => return Err("remind_at or offset_minutes cannot be null".into()),
+            (Some(_), Some(_)) => {
+                return Err("remind_at and offset_minutes cannot both be set".into())
+            }
+            (None, Some(offset)) if offset < 0 => {
+                return Err("offset_minutes cannot be negative".into())
+            }
+            (None, Some(_)) if todo.due_at.is_none() => {
+                return Err("offset_minutes needs a todo with a due_at".into())
+            }
+            _ => {}
+        }
+
+        let channel = form.channel.unwrap_or(ReminderChannel::Email);
+        let recipient = form
+            .recipient
+            .map(|recipient| recipient.trim().to_string())
+            .filter(|recipient| !recipient.is_empty());
+        match (channel, &recipient) {
+            (ReminderChannel::Email, Some(address)) if address.parse::<Mailbox>().is_err() => {
+                return Err(format!("{} is not an email address", address))
+            }
+            (ReminderChannel::Email, None) if activity.email.is_none() => {
+                return Err("recipient cannot be null when the activity group has no email".into())
+            }
+            (ReminderChannel::Webhook, None) => return Err("recipient cannot be null".into()),
+            (ReminderChannel::Webhook, Some(url)) => match reqwest::Url::parse(url) {
+                Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+                _ => return Err(format!("{} is not an http or https URL", url)),
+            },
+            _ => {}
+        }
+
+        Ok(NewReminder {
+            remind_at: form
+                .remind_at
+                .map(|remind_at| remind_at.with_timezone(&Utc).trunc_subsecs(0)),
+            offset_minutes: form.offset_minutes,
+            channel,
+            recipient,
+        })
+    }
+}
+
+pub(crate) fn channel_name(channel: ReminderChannel) -> &'static str {
+    match channel {
+        ReminderChannel::Log => "log",
+        ReminderChannel::Email => "email",
+        ReminderChannel::Webhook => "webhook",
+    }
+}
+
+pub(crate) fn parse_channel(channel: &str) -> ReminderChannel {
+    match channel {
+        "log" => ReminderChannel::Log,
+        "webhook" => ReminderChannel::Webhook,
+        _ => ReminderChannel::Email,
+    }
+}
+
+fn utc(datetime: Option<NaiveDateTime>) -> Option<DateTime<Utc>> {
+    datetime.map(|datetime| Utc.from_utc_datetime(&datetime))
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_reminders_by_todo(
+    todo_id: i32,
+    pool: &MySqlPool,
+) -> Result<Vec<ReminderRecord>, sqlx::Error> {
+    let _timer = query_timer("get_reminders_by_todo");
+    let query = sqlx::query!(
+        "select * from reminders where todo_id = ? order by id",
+        todo_id
+    )
+    .in_span(|query| query.fetch_all(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(query
+        .into_iter()
+        .map(|q| ReminderRecord {
+            id: q.id,
+            todo_id: q.todo_id,
+            remind_at: utc(q.remind_at),
+            offset_minutes: q.offset_minutes,
+            channel: parse_channel(&q.channel),
+            recipient: q.recipient,
+            fired_at: utc(q.fired_at),
+            last_error: q.last_error,
+            created_at: Utc.from_utc_datetime(&q.created_at),
+        })
+        .collect())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_reminder_by_id(
+    reminder_id: i32,
+    pool: &MySqlPool,
+) -> Result<ReminderRecord, sqlx::Error> {
+    let _timer = query_timer("get_reminder_by_id");
+    let query = sqlx::query!("select * from reminders where id = ?", reminder_id)
+        .in_span(|query| query.fetch_one(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+    Ok(ReminderRecord {
+        id: query.id,
+        todo_id: query.todo_id,
+        remind_at: utc(query.remind_at),
+        offset_minutes: query.offset_minutes,
+        channel: parse_channel(&query.channel),
+        recipient: query.recipient,
+        fired_at: utc(query.fired_at),
+        last_error: query.last_error,
+        created_at: Utc.from_utc_datetime(&query.created_at),
+    })
+}
+
+#[tracing::instrument(skip(pool, form))]
+pub async fn insert_reminder(
+    todo_id: i32,
+    pool: &MySqlPool,
+    form: &NewReminder,
+) -> Result<ReminderRecord, sqlx::Error> {
+    let _timer = query_timer("insert_reminder");
+    let utc_now = Utc::now().trunc_subsecs(0);
+
+    let query = sqlx::query!(
+        r#"
+        insert into reminders (todo_id, remind_at, offset_minutes, channel, recipient, created_at)
+        values (?, ?, ?, ?, ?, ?)
+        "#,
+        todo_id,
+        form.remind_at,
+        form.offset_minutes,
+        channel_name(form.channel),
+        form.recipient,
+        utc_now
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(ReminderRecord {
+        id: query.last_insert_id() as i32,
+        todo_id,
+        remind_at: form.remind_at,
+        offset_minutes: form.offset_minutes,
+        channel: form.channel,
+        recipient: form.recipient.clone(),
+        fired_at: None,
+        last_error: None,
+        created_at: utc_now,
+    })
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn delete_reminder_by_id(reminder_id: i32, pool: &MySqlPool) -> Result<(), sqlx::Error> {
+    let _timer = query_timer("delete_reminder_by_id");
+    let _record = get_reminder_by_id(reminder_id, pool).await?;
+    sqlx::query!("delete from reminders where id = ?", reminder_id)
+        .in_span(|query| query.execute(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+    Ok(())
+}
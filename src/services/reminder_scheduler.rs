@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use chrono::{SubsecRound, TimeZone, Utc};
+use sqlx::MySqlPool;
+
+use crate::configuration::ReminderSettings;
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{parse_channel, Notification, Notifiers};
+use crate::telemetry::QuerySpan;
+
+/// Reminders claimed from the table per round.
+const REMINDER_BATCH: i64 = 50;
+
+/// Longest error kept in `last_error`.
+const MAX_ERROR_LEN: usize = 1024;
+
+/// Fires due reminders. Each one is claimed by setting `fired_at` before it
+/// is sent, so it fires once even with several instances polling, and a
+/// failed send is recorded rather than retried.
+pub struct ReminderScheduler {
+    pool: MySqlPool,
+    notifiers: Notifiers,
+    settings: ReminderSettings,
+}
+
+impl ReminderScheduler {
+    pub fn new(pool: MySqlPool, notifiers: Notifiers, settings: ReminderSettings) -> Self {
+        Self {
+            pool,
+            notifiers,
+            settings,
+        }
+    }
+
+    pub async fn run(self) {
+        let poll_interval = Duration::from_secs(self.settings.poll_interval_seconds);
+        loop {
+            if let Err(e) = self.fire_due().await {
+                tracing::error!("Failed to fire reminders: {:?}", e);
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Sends every due reminder, returning how many fired.
+    #[tracing::instrument(skip_all)]
+    pub async fn fire_due(&self) -> Result<usize, sqlx::Error> {
+        let _timer = query_timer("ReminderScheduler::fire_due");
+        let now = Utc::now();
+        let due = sqlx::query!(
+            r#"
+            select r.id, r.todo_id, r.channel, r.recipient, t.title, t.activity_group_id, t.due_at,
+            a.title as activity_group_title, a.email
+            from reminders r
+            join todos t on t.id = r.todo_id
+            join activities a on a.id = t.activity_group_id
+            where r.fired_at is null
+            and coalesce(r.remind_at, date_sub(t.due_at, interval r.offset_minutes minute)) <= ?
+            order by r.id
+            limit ?
+            "#,
+            now,
+            REMINDER_BATCH
+        )
+        .in_span(|query| query.fetch_all(TimedPool(&self.pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+        let mut fired = 0;
+        for reminder in due {
+            let claimed = sqlx::query!(
+                "update reminders set fired_at = ? where id = ? and fired_at is null",
+                Utc::now().trunc_subsecs(0),
+                reminder.id
+            )
+            .in_span(|query| query.execute(TimedPool(&self.pool)))
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to execute query: {:?}", e);
+                e
+            })?;
+            if claimed.rows_affected() != 1 {
+                continue;
+            }
+            fired += 1;
+
+            let channel = parse_channel(&reminder.channel);
+            let notification = Notification {
+                reminder_id: reminder.id,
+                todo_id: reminder.todo_id,
+                title: reminder.title,
+                activity_group_id: reminder.activity_group_id,
+                activity_group_title: reminder.activity_group_title,
+                due_at: reminder.due_at.map(|due_at| Utc.from_utc_datetime(&due_at)),
+                recipient: reminder.recipient.or(reminder.email),
+            };
+            let result = match self.notifiers.get(channel) {
+                Some(notifier) => notifier.notify(&notification).await,
+                None => Err(format!("no notifier is configured for {:?}", channel)),
+            };
+            if let Err(error) = result {
+                tracing::warn!("Reminder {} failed: {}", reminder.id, error);
+                let error: String = error.chars().take(MAX_ERROR_LEN).collect();
+                sqlx::query!(
+                    "update reminders set last_error = ? where id = ?",
+                    error,
+                    reminder.id
+                )
+                .in_span(|query| query.execute(TimedPool(&self.pool)))
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to execute query: {:?}", e);
+                    e
+                })?;
+            }
+        }
+
+        Ok(fired)
+    }
+}
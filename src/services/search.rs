@@ -0,0 +1,341 @@
+use crate::metrics::{query_timer, TimedPool};
+use crate::telemetry::QuerySpan;
+use sqlx::mysql::MySqlDatabaseError;
+use sqlx::MySqlPool;
+
+pub use zero2prod_types::{SearchHit, SearchKind};
+
+/// Characters kept around the first match when a title is too long to be
+/// returned whole as a snippet.
+const SNIPPET_CONTEXT: usize = 60;
+
+/// How many rows the LIKE fallback fetches per requested hit before ranking.
+const FALLBACK_CANDIDATES_PER_HIT: i64 = 5;
+
+pub struct SearchQuery {
+    pub terms: Vec<String>,
+    pub activity_group_id: Option<i32>,
+    pub is_active: Option<bool>,
+    pub limit: i64,
+}
+
+impl SearchQuery {
+    /// Splits `q` into lowercase alphanumeric terms, dropping anything that
+    /// would be interpreted as a FULLTEXT boolean-mode operator.
+    pub fn parse_terms(q: &str) -> Vec<String> {
+        q.split(|c: char| !c.is_alphanumeric())
+            .filter(|term| !term.is_empty())
+            .map(|term| term.to_lowercase())
+            .collect()
+    }
+
+    fn boolean_mode_query(&self) -> String {
+        self.terms
+            .iter()
+            .map(|term| format!("{}*", term))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// LIKE pattern for the longest term, used to narrow fallback candidates.
+    fn like_pattern(&self) -> String {
+        let longest = self
+            .terms
+            .iter()
+            .max_by_key(|term| term.len())
+            .map(String::as_str)
+            .unwrap_or_default();
+        let escaped = longest
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        format!("%{}%", escaped)
+    }
+}
+
+/// Searches todo and activity group titles, best matches first.
+///
+/// Uses the FULLTEXT indexes when the database has them and falls back to
+/// LIKE matching ranked in Rust when it does not (e.g. servers or storage
+/// engines without FULLTEXT support).
+#[tracing::instrument(skip_all)]
+pub async fn search(query: &SearchQuery, pool: &MySqlPool) -> Result<Vec<SearchHit>, sqlx::Error> {
+    let _timer = query_timer("search");
+    let mut hits = match search_fulltext(query, pool).await {
+        Err(e) if is_fulltext_unavailable(&e) => {
+            tracing::warn!("FULLTEXT search unavailable, using LIKE fallback: {:?}", e);
+            search_like(query, pool).await?
+        }
+        result => result?,
+    };
+
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(query.limit as usize);
+    Ok(hits)
+}
+
+#[tracing::instrument(skip_all)]
+async fn search_fulltext(
+    query: &SearchQuery,
+    pool: &MySqlPool,
+) -> Result<Vec<SearchHit>, sqlx::Error> {
+    let against = query.boolean_mode_query();
+
+    let todos = sqlx::query!(
+        r#"
+        select id, activity_group_id, title, is_active,
+        match (title) against (? in boolean mode) as `score!`
+        from todos
+        where match (title) against (? in boolean mode)
+        and (? is null or activity_group_id = ?)
+        and (? is null or is_active = ?)
+        order by `score!` desc
+        limit ?
+        "#,
+        against,
+        against,
+        query.activity_group_id,
+        query.activity_group_id,
+        query.is_active,
+        query.is_active,
+        query.limit
+    )
+    .in_span(|query| query.fetch_all(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    let mut hits = todos
+        .into_iter()
+        .map(|q| SearchHit {
+            kind: SearchKind::Todo,
+            id: q.id,
+            activity_group_id: q.activity_group_id,
+            snippet: snippet(&q.title, &query.terms),
+            title: q.title,
+            is_active: Some(q.is_active != 0),
+            score: q.score,
+        })
+        .collect::<Vec<_>>();
+
+    // Status only applies to todos, so a status filter leaves groups out.
+    if query.is_active.is_none() {
+        let activities = sqlx::query!(
+            r#"
+            select id, title,
+            match (title) against (? in boolean mode) as `score!`
+            from activities
+            where match (title) against (? in boolean mode)
+            and (? is null or id = ?)
+            order by `score!` desc
+            limit ?
+            "#,
+            against,
+            against,
+            query.activity_group_id,
+            query.activity_group_id,
+            query.limit
+        )
+        .in_span(|query| query.fetch_all(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+        hits.extend(activities.into_iter().map(|q| SearchHit {
+            kind: SearchKind::ActivityGroup,
+            id: q.id,
+            activity_group_id: q.id,
+            snippet: snippet(&q.title, &query.terms),
+            title: q.title,
+            is_active: None,
+            score: q.score,
+        }));
+    }
+
+    Ok(hits)
+}
+
+#[tracing::instrument(skip_all)]
+async fn search_like(query: &SearchQuery, pool: &MySqlPool) -> Result<Vec<SearchHit>, sqlx::Error> {
+    let pattern = query.like_pattern();
+    let candidates = query.limit * FALLBACK_CANDIDATES_PER_HIT;
+
+    let todos = sqlx::query!(
+        r#"
+        select id, activity_group_id, title, is_active
+        from todos
+        where title like ?
+        and (? is null or activity_group_id = ?)
+        and (? is null or is_active = ?)
+        limit ?
+        "#,
+        pattern,
+        query.activity_group_id,
+        query.activity_group_id,
+        query.is_active,
+        query.is_active,
+        candidates
+    )
+    .in_span(|query| query.fetch_all(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    let mut hits = todos
+        .into_iter()
+        .map(|q| SearchHit {
+            kind: SearchKind::Todo,
+            id: q.id,
+            activity_group_id: q.activity_group_id,
+            snippet: snippet(&q.title, &query.terms),
+            score: like_score(&q.title, &query.terms),
+            title: q.title,
+            is_active: Some(q.is_active != 0),
+        })
+        .collect::<Vec<_>>();
+
+    if query.is_active.is_none() {
+        let activities = sqlx::query!(
+            r#"
+            select id, title
+            from activities
+            where title like ?
+            and (? is null or id = ?)
+            limit ?
+            "#,
+            pattern,
+            query.activity_group_id,
+            query.activity_group_id,
+            candidates
+        )
+        .in_span(|query| query.fetch_all(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+        hits.extend(activities.into_iter().map(|q| SearchHit {
+            kind: SearchKind::ActivityGroup,
+            id: q.id,
+            activity_group_id: q.id,
+            snippet: snippet(&q.title, &query.terms),
+            score: like_score(&q.title, &query.terms),
+            title: q.title,
+            is_active: None,
+        }));
+    }
+
+    Ok(hits)
+}
+
+/// ER_FT_MATCHING_KEY_NOT_FOUND (1191) and ER_TABLE_CANT_HANDLE_FT (1214).
+fn is_fulltext_unavailable(e: &sqlx::Error) -> bool {
+    match e {
+        sqlx::Error::Database(db) => db
+            .try_downcast_ref::<MySqlDatabaseError>()
+            .map(|e| matches!(e.number(), 1191 | 1214))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Ranks a LIKE candidate by the share of terms it contains, preferring
+/// titles where the first match comes early. Candidates matching no term
+/// score zero.
+pub fn like_score(title: &str, terms: &[String]) -> f64 {
+    let haystack = title.to_lowercase();
+    let positions = terms
+        .iter()
+        .filter_map(|term| haystack.find(term.as_str()))
+        .collect::<Vec<_>>();
+    if positions.is_empty() {
+        return 0.0;
+    }
+
+    let coverage = positions.len() as f64 / terms.len() as f64;
+    let first = *positions.iter().min().unwrap() as f64;
+    coverage + 1.0 / (1.0 + first)
+}
+
+/// HTML-escapes `text` and wraps every case-insensitive occurrence of a
+/// term in `<mark>`, trimming long text to a window around the first match.
+pub fn snippet(text: &str, terms: &[String]) -> String {
+    let ranges = match_ranges(text, terms);
+    let (start, end) = snippet_window(text, ranges.first().map(|(start, _)| *start));
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    let mut cursor = start;
+    for (match_start, match_end) in ranges {
+        if match_start < cursor || match_end > end {
+            continue;
+        }
+        snippet.push_str(&escape_html(&text[cursor..match_start]));
+        snippet.push_str("<mark>");
+        snippet.push_str(&escape_html(&text[match_start..match_end]));
+        snippet.push_str("</mark>");
+        cursor = match_end;
+    }
+    snippet.push_str(&escape_html(&text[cursor..end]));
+    if end < text.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Non-overlapping byte ranges of term occurrences, in order.
+fn match_ranges(text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let haystack = text.to_ascii_lowercase();
+    let mut ranges = Vec::new();
+    for term in terms {
+        let needle = term.to_ascii_lowercase();
+        let mut from = 0;
+        while let Some(found) = haystack[from..].find(&needle) {
+            let start = from + found;
+            ranges.push((start, start + needle.len()));
+            from = start + needle.len();
+        }
+    }
+    ranges.sort();
+
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+fn snippet_window(text: &str, first_match: Option<usize>) -> (usize, usize) {
+    if text.len() <= SNIPPET_CONTEXT * 2 {
+        return (0, text.len());
+    }
+    let anchor = first_match.unwrap_or(0);
+    let mut start = anchor.saturating_sub(SNIPPET_CONTEXT);
+    let mut end = (anchor + SNIPPET_CONTEXT).min(text.len());
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+    (start, end)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,162 @@
+use crate::common::{SubscriberEmail, SubscriberName};
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{escape_html, Email};
+use crate::telemetry::QuerySpan;
+use chrono::{SubsecRound, Utc};
+use sqlx::MySqlPool;
+use uuid::Uuid;
+
+pub use zero2prod_types::SubscriptionFormData;
+
+pub struct NewSubscriber {
+    pub email: SubscriberEmail,
+    pub name: SubscriberName,
+}
+
+impl NewSubscriber {
+    pub fn parse(form: SubscriptionFormData) -> Result<NewSubscriber, String> {
+        let email = SubscriberEmail::parse(form.email.unwrap_or_default())?;
+        let name = SubscriberName::parse(form.name.unwrap_or_default())?;
+        Ok(NewSubscriber { email, name })
+    }
+}
+
+/// A subscription as stored; `status` is `pending_confirmation` until the
+/// link in the confirmation email is followed.
+#[derive(Clone, Debug)]
+pub struct SubscriptionRecord {
+    pub id: i32,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+}
+
+impl SubscriptionRecord {
+    pub fn is_confirmed(&self) -> bool {
+        self.status == "confirmed"
+    }
+}
+
+/// Stores `subscriber` as pending unless the email is already subscribed,
+/// returning the stored subscription either way.
+#[tracing::instrument(skip_all)]
+pub async fn insert_subscriber(
+    subscriber: &NewSubscriber,
+    pool: &MySqlPool,
+) -> Result<SubscriptionRecord, sqlx::Error> {
+    let _timer = query_timer("insert_subscriber");
+    sqlx::query!(
+        "insert ignore into subscriptions (email, name, subscribed_at) values (?, ?, ?)",
+        subscriber.email.inner_ref(),
+        subscriber.name.inner_ref(),
+        Utc::now().trunc_subsecs(0)
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    let query = sqlx::query!(
+        "select id, email, name, status from subscriptions where email = ?",
+        subscriber.email.inner_ref()
+    )
+    .in_span(|query| query.fetch_one(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(SubscriptionRecord {
+        id: query.id,
+        email: query.email,
+        name: query.name,
+        status: query.status,
+    })
+}
+
+/// Issues a new confirmation token for the subscription.
+#[tracing::instrument(skip(pool))]
+pub async fn store_subscription_token(
+    subscription_id: i32,
+    pool: &MySqlPool,
+) -> Result<String, sqlx::Error> {
+    let _timer = query_timer("store_subscription_token");
+    let token = Uuid::new_v4().to_simple().to_string();
+    sqlx::query!(
+        "insert into subscription_tokens (token, subscription_id, created_at) values (?, ?, ?)",
+        token,
+        subscription_id,
+        Utc::now().trunc_subsecs(0)
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(token)
+}
+
+/// Confirms the subscription `token` was issued for, returning its email.
+/// Following a link twice is not an error.
+#[tracing::instrument(skip_all)]
+pub async fn confirm_subscription(token: &str, pool: &MySqlPool) -> Result<String, sqlx::Error> {
+    let _timer = query_timer("confirm_subscription");
+    let query = sqlx::query!(
+        r#"
+        select s.id, s.email
+        from subscription_tokens t
+        join subscriptions s on s.id = t.subscription_id
+        where t.token = ?
+        "#,
+        token
+    )
+    .in_span(|query| query.fetch_one(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    sqlx::query!(
+        "update subscriptions set status = 'confirmed', confirmed_at = ? where id = ? and status = 'pending_confirmation'",
+        Utc::now().trunc_subsecs(0),
+        query.id
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(query.email)
+}
+
+pub fn subscription_confirm_url(base_url: &str, token: &str) -> String {
+    format!(
+        "{}/subscriptions/confirm?token={}",
+        base_url.trim_end_matches('/'),
+        token
+    )
+}
+
+/// The email asking `subscription` to follow `confirm_url`.
+pub fn confirmation_email(subscription: &SubscriptionRecord, confirm_url: &str) -> Email {
+    Email {
+        to: subscription.email.clone(),
+        subject: "Confirm your subscription".into(),
+        text: format!(
+            "Hi {},\n\nFollow this link to confirm your subscription:\n{}\n",
+            subscription.name, confirm_url
+        ),
+        html: Some(format!(
+            "<p>Hi {},</p>\n<p><a href=\"{}\">Confirm your subscription</a></p>\n",
+            escape_html(&subscription.name),
+            escape_html(confirm_url)
+        )),
+    }
+}
@@ -1,30 +1,57 @@
-use crate::common::TitleField;
-use chrono::{NaiveDateTime, Utc};
+use crate::common::{Timezone, TitleField};
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{forget_calendar_object, placeholders, ChangeAction, EventBus};
+use crate::telemetry::QuerySpan;
+use chrono::{DateTime, NaiveDateTime, SubsecRound, TimeZone, Utc};
 use sqlx::MySqlPool;
 
-pub fn default_as_true() -> bool {
-    true
-}
+use zero2prod_types::v2;
+pub use zero2prod_types::{default_as_true, default_as_very_high, Todo};
+use zero2prod_types::{TodoFormData, TodoFormUpdateData};
 
-pub fn default_as_very_high() -> String {
-    "very-high".into()
+/// A todo as stored, with timestamps in UTC.
+#[derive(Clone, Debug)]
+pub struct TodoRecord {
+    pub id: i32,
+    pub title: String,
+    pub activity_group_id: i32,
+    pub is_active: bool,
+    pub priority: String,
+    pub due_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(serde::Serialize)]
-pub struct Todo {
-    id: i32,
-    title: String,
-    priority: Option<String>,
-    activity_group_id: i32,
-    #[serde(default = "default_as_true")]
-    is_active: Option<bool>,
-    #[serde(rename = "createdAt")]
-    created_at: String,
-    #[serde(rename = "updatedAt")]
-    updated_at: Option<String>,
+impl TodoRecord {
+    pub fn into_v1(self, tz: &Timezone) -> Todo {
+        Todo {
+            id: self.id,
+            title: self.title,
+            activity_group_id: self.activity_group_id,
+            is_active: Some(self.is_active),
+            priority: Some(self.priority),
+            created_at: tz.localize(self.created_at),
+            updated_at: self.updated_at.map(|updated_at| tz.localize(updated_at)),
+            due_at: self.due_at.map(|due_at| tz.localize(due_at)),
+        }
+    }
+
+    pub fn into_v2(self, tz: &Timezone) -> v2::Todo {
+        v2::Todo {
+            id: self.id,
+            title: self.title,
+            activity_group_id: self.activity_group_id,
+            is_active: self.is_active,
+            priority: self.priority,
+            due_at: self.due_at.map(|due_at| tz.localize(due_at)),
+            created_at: tz.localize(self.created_at),
+            updated_at: self.updated_at.map(|updated_at| tz.localize(updated_at)),
+        }
+    }
 }
 
 #[allow(dead_code)]
+#[derive(sqlx::FromRow)]
 pub struct TodoTable {
     id: i32,
     title: String,
@@ -34,6 +61,7 @@ pub struct TodoTable {
     created_at: NaiveDateTime,
     updated_at: Option<NaiveDateTime>,
     r#deleted_at: Option<NaiveDateTime>,
+    due_at: Option<NaiveDateTime>,
 }
 
 pub struct NewTodo {
@@ -41,6 +69,36 @@ pub struct NewTodo {
     pub activity_group_id: i32,
     pub is_active: Option<bool>,
     pub priority: Option<String>,
+    pub due_at: Option<DateTime<Utc>>,
+}
+
+impl NewTodo {
+    /// Validates a v1 create form, failing with the message reported to the
+    /// client.
+    pub fn parse(form: TodoFormData) -> Result<NewTodo, String> {
+        let title = TitleField::parse(form.title).map_err(|_| "title cannot be null")?;
+        let activity_group_id = form
+            .activity_group_id
+            .ok_or("activity_group_id cannot be null")?;
+
+        Ok(NewTodo {
+            title,
+            activity_group_id,
+            priority: Some(form.priority),
+            is_active: Some(form.is_active),
+            due_at: form.due_at.map(|due_at| due_at.with_timezone(&Utc)),
+        })
+    }
+}
+
+/// Narrows `get_todos`; every unset field matches all todos.
+#[derive(Default, Debug)]
+pub struct TodoFilter {
+    pub activity_group_id: Option<i32>,
+    /// Inclusive lower bound on `created_at`.
+    pub created_from: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on `created_at`.
+    pub created_before: Option<DateTime<Utc>>,
 }
 
 #[derive(serde::Serialize, Debug)]
@@ -49,83 +107,148 @@ pub struct UpdateTodo {
     pub activity_group_id: Option<i32>,
     pub is_active: Option<bool>,
     pub priority: Option<String>,
+    pub due_at: Option<DateTime<Utc>>,
 }
 
+impl From<TodoFormUpdateData> for UpdateTodo {
+    fn from(form: TodoFormUpdateData) -> Self {
+        UpdateTodo {
+            title: form.title,
+            activity_group_id: form.activity_group_id,
+            is_active: form.is_active,
+            priority: form.priority,
+            due_at: form.due_at.map(|due_at| due_at.with_timezone(&Utc)),
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
 pub async fn get_todos(
-    activity_group_id: Option<i32>,
+    filter: &TodoFilter,
     pool: &MySqlPool,
-) -> Result<Vec<Todo>, sqlx::Error> {
-    let query = match activity_group_id {
-        Some(activity_group_id) => sqlx::query_as!(
-            TodoTable,
-            "select * from todos where activity_group_id = ?",
-            activity_group_id
-        )
-        .fetch_all(pool)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to execute query: {:?}", e);
-            e
-        })?,
-        None => sqlx::query_as!(TodoTable, "select * from todos")
-            .fetch_all(pool)
-            .await
-            .map_err(|e| {
-                tracing::error!("Failed to execute query: {:?}", e);
-                e
-            })?,
-    };
+) -> Result<Vec<TodoRecord>, sqlx::Error> {
+    let _timer = query_timer("get_todos");
+    let query = sqlx::query_as!(
+        TodoTable,
+        r#"
+        select * from todos
+        where (? is null or activity_group_id = ?)
+        and (? is null or created_at >= ?)
+        and (? is null or created_at < ?)
+        "#,
+        filter.activity_group_id,
+        filter.activity_group_id,
+        filter.created_from,
+        filter.created_from,
+        filter.created_before,
+        filter.created_before
+    )
+    .in_span(|query| query.fetch_all(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
 
-    let mut todos = Vec::<Todo>::new();
+    let mut todos = Vec::<TodoRecord>::new();
 
     for q in query {
-        todos.push(Todo {
+        todos.push(TodoRecord {
             id: q.id,
             title: q.title,
             activity_group_id: q.activity_group_id,
-            is_active: Some(q.is_active != 0),
-            priority: Some(q.priority),
-            created_at: q.created_at.to_string(),
-            updated_at: match q.updated_at {
-                Some(updated_at) => Some(updated_at.to_string()),
-                None => None,
-            },
+            is_active: q.is_active != 0,
+            priority: q.priority,
+            created_at: Utc.from_utc_datetime(&q.created_at),
+            updated_at: q
+                .updated_at
+                .map(|updated_at| Utc.from_utc_datetime(&updated_at)),
+            due_at: q.due_at.map(|due_at| Utc.from_utc_datetime(&due_at)),
         });
     }
 
     Ok(todos)
 }
 
-pub async fn get_todo_by_id(todo_id: i32, pool: &MySqlPool) -> Result<Todo, sqlx::Error> {
+/// The todos of every activity group in `activity_group_ids`, oldest first,
+/// in a single query.
+#[tracing::instrument(skip(pool))]
+pub async fn get_todos_by_activity_ids(
+    activity_group_ids: &[i32],
+    pool: &MySqlPool,
+) -> Result<Vec<TodoRecord>, sqlx::Error> {
+    if activity_group_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let _timer = query_timer("get_todos_by_activity_ids");
+    let sql = format!(
+        "select * from todos where activity_group_id in ({}) order by id",
+        placeholders(activity_group_ids.len())
+    );
+    let query = activity_group_ids
+        .iter()
+        .fold(sqlx::query_as::<_, TodoTable>(&sql), |query, id| {
+            query.bind(id)
+        })
+        .in_span(|query| query.fetch_all(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+    Ok(query
+        .into_iter()
+        .map(|q| TodoRecord {
+            id: q.id,
+            title: q.title,
+            activity_group_id: q.activity_group_id,
+            is_active: q.is_active != 0,
+            priority: q.priority,
+            created_at: Utc.from_utc_datetime(&q.created_at),
+            updated_at: q
+                .updated_at
+                .map(|updated_at| Utc.from_utc_datetime(&updated_at)),
+            due_at: q.due_at.map(|due_at| Utc.from_utc_datetime(&due_at)),
+        })
+        .collect())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_todo_by_id(todo_id: i32, pool: &MySqlPool) -> Result<TodoRecord, sqlx::Error> {
+    let _timer = query_timer("get_todo_by_id");
     let query = sqlx::query!("select * from todos where id = ?", todo_id)
-        .fetch_one(pool)
+        .in_span(|query| query.fetch_one(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
             e
         })?;
 
-    Ok(Todo {
+    Ok(TodoRecord {
         id: query.id,
         title: query.title,
         activity_group_id: query.activity_group_id,
-        is_active: Some(query.is_active != 0),
-        priority: Some(query.priority),
-        created_at: query.created_at.to_string(),
-        updated_at: match query.updated_at {
-            Some(updated_at) => Some(updated_at.to_string()),
-            None => None,
-        },
+        is_active: query.is_active != 0,
+        priority: query.priority,
+        created_at: Utc.from_utc_datetime(&query.created_at),
+        updated_at: query
+            .updated_at
+            .map(|updated_at| Utc.from_utc_datetime(&updated_at)),
+        due_at: query.due_at.map(|due_at| Utc.from_utc_datetime(&due_at)),
     })
 }
 
+#[tracing::instrument(skip(pool, form, events))]
 pub async fn update_todo_by_id(
     todo_id: i32,
     pool: &MySqlPool,
     form: &UpdateTodo,
-) -> Result<Todo, sqlx::Error> {
+    events: &EventBus,
+) -> Result<TodoRecord, sqlx::Error> {
+    let _timer = query_timer("update_todo_by_id");
     let current_todo = sqlx::query!("select * from todos  where id = ?", todo_id)
-        .fetch_one(pool)
+        .in_span(|query| query.fetch_one(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -148,7 +271,7 @@ pub async fn update_todo_by_id(
             todo_id
         )
     }
-        .execute(pool)
+        .in_span(|query| query.execute(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -171,7 +294,7 @@ pub async fn update_todo_by_id(
             todo_id
         )
     }
-        .execute(pool)
+        .in_span(|query| query.execute(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -194,7 +317,7 @@ pub async fn update_todo_by_id(
             todo_id
         )
     }
-        .execute(pool)
+        .in_span(|query| query.execute(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -217,83 +340,138 @@ pub async fn update_todo_by_id(
             todo_id
         )
     }
-        .execute(pool)
+        .in_span(|query| query.execute(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
             e
         })?;
 
+    let _query = match &form.due_at {
+        Some(due_at) => sqlx::query!(
+            "update todos set due_at = ?, updated_at = ? where id = ?",
+            due_at,
+            Utc::now(),
+            todo_id
+        ),
+        None => sqlx::query!(
+            "update todos set updated_at = ? where id = ?",
+            Utc::now(),
+            todo_id
+        ),
+    }
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
     let record = sqlx::query!("select * from todos where id = ?", todo_id)
-        .fetch_one(pool)
+        .in_span(|query| query.fetch_one(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
             e
         })?;
 
-    Ok(Todo {
+    if record.activity_group_id != current_todo.activity_group_id {
+        forget_calendar_object(todo_id, current_todo.activity_group_id, pool).await?;
+    }
+
+    let todo = TodoRecord {
         id: record.id,
         title: record.title,
         activity_group_id: record.activity_group_id,
-        is_active: Some(record.is_active != 0),
-        priority: Some(record.priority),
-        created_at: record.created_at.to_string(),
-        updated_at: match record.updated_at {
-            Some(updated_at) => Some(updated_at.to_string()),
-            None => None,
-        },
-    })
+        is_active: record.is_active != 0,
+        priority: record.priority,
+        created_at: Utc.from_utc_datetime(&record.created_at),
+        updated_at: record
+            .updated_at
+            .map(|updated_at| Utc.from_utc_datetime(&updated_at)),
+        due_at: record.due_at.map(|due_at| Utc.from_utc_datetime(&due_at)),
+    };
+    events.todo_changed(ChangeAction::Updated, &todo);
+
+    Ok(todo)
 }
 
-pub async fn delete_todo_by_id(todo_id: i32, pool: &MySqlPool) -> Result<(), sqlx::Error> {
-    let _record = sqlx::query!("select * from todos where id = ?", todo_id)
-        .fetch_one(pool)
+#[tracing::instrument(skip(pool, events))]
+pub async fn delete_todo_by_id(
+    todo_id: i32,
+    pool: &MySqlPool,
+    events: &EventBus,
+) -> Result<(), sqlx::Error> {
+    let _timer = query_timer("delete_todo_by_id");
+    let record = sqlx::query!("select * from todos where id = ?", todo_id)
+        .in_span(|query| query.fetch_one(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    sqlx::query!("delete from reminders where todo_id = ?", todo_id)
+        .in_span(|query| query.execute(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
             e
         })?;
     sqlx::query!("delete from todos where id = ?", todo_id)
-        .execute(pool)
+        .in_span(|query| query.execute(TimedPool(pool)))
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
             e
         })?;
+    forget_calendar_object(todo_id, record.activity_group_id, pool).await?;
+    events.todo_deleted(todo_id, record.activity_group_id);
 
     Ok(())
 }
 
-pub async fn insert_todo(pool: &MySqlPool, form: &NewTodo) -> Result<Todo, sqlx::Error> {
-    let utc_now = Utc::now();
+#[tracing::instrument(skip_all)]
+pub async fn insert_todo(
+    pool: &MySqlPool,
+    form: &NewTodo,
+    events: &EventBus,
+) -> Result<TodoRecord, sqlx::Error> {
+    let _timer = query_timer("insert_todo");
+    // `datetime` columns hold whole seconds; truncate so the returned record
+    // matches what a later read yields.
+    let utc_now = Utc::now().trunc_subsecs(0);
 
     let query = sqlx::query!(
         r#"
-        insert into todos (title, activity_group_id, is_active, priority, created_at, updated_at)
-        values (?, ?, ?, ?, ?, ?)
+        insert into todos (title, activity_group_id, is_active, priority, due_at, created_at, updated_at)
+        values (?, ?, ?, ?, ?, ?, ?)
         "#,
-        form.title.inner_ref().clone(),
+        form.title.inner_ref(),
         form.activity_group_id,
         form.is_active,
         form.priority,
+        form.due_at.map(|due_at| due_at.trunc_subsecs(0)),
         utc_now.clone(),
         utc_now.clone()
     )
-    .execute(pool)
+    .in_span(|query| query.execute(TimedPool(pool)))
     .await
     .map_err(|e| {
         tracing::error!("Failed to execute query: {:?}", e);
         e
     })?;
 
-    Ok(Todo {
+    let todo = TodoRecord {
         id: query.last_insert_id() as i32,
         title: form.title.inner_ref().into(),
-        activity_group_id: form.activity_group_id.clone(),
-        is_active: form.is_active,
-        priority: form.priority.clone(),
-        created_at: utc_now.to_string(),
-        updated_at: Some(utc_now.to_string()),
-    })
+        activity_group_id: form.activity_group_id,
+        is_active: form.is_active.unwrap_or_else(default_as_true),
+        priority: form.priority.clone().unwrap_or_else(default_as_very_high),
+        due_at: form.due_at.map(|due_at| due_at.trunc_subsecs(0)),
+        created_at: utc_now,
+        updated_at: Some(utc_now),
+    };
+    events.todo_changed(ChangeAction::Created, &todo);
+
+    Ok(todo)
 }
@@ -0,0 +1,300 @@
+use crate::common::{Timezone, TitleField};
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{
+    default_as_true, default_as_very_high, ChangeAction, EventBus, TodoRecord, PRIORITIES,
+};
+use crate::telemetry::QuerySpan;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SubsecRound, Utc};
+use csv_async::{AsyncReaderBuilder, AsyncWriter, StringRecord, Trim};
+use sqlx::MySqlPool;
+use tokio::io::AsyncRead;
+
+pub use zero2prod_types::{CsvImportSummary, ImportLineError};
+
+/// The columns of `GET /todo-items.csv`, which imports read back as is.
+pub const TODO_CSV_HEADERS: [&str; 8] = [
+    "id",
+    "title",
+    "activity_group_id",
+    "priority",
+    "is_active",
+    "due_at",
+    "created_at",
+    "updated_at",
+];
+
+/// Row errors past this many are counted but not listed.
+pub const MAX_REPORTED_ROW_ERRORS: usize = 100;
+
+/// Renders todos as CSV with `TODO_CSV_HEADERS`, timestamps in `tz`.
+pub async fn write_todos_csv(todos: Vec<TodoRecord>, tz: &Timezone) -> Result<Vec<u8>, String> {
+    let mut writer = AsyncWriter::from_writer(Vec::new());
+    writer
+        .write_record(TODO_CSV_HEADERS)
+        .await
+        .map_err(|e| e.to_string())?;
+    for todo in todos {
+        let timestamp = |instant: DateTime<Utc>| tz.localize(instant).to_rfc3339();
+        writer
+            .write_record([
+                todo.id.to_string(),
+                todo.title,
+                todo.activity_group_id.to_string(),
+                todo.priority,
+                todo.is_active.to_string(),
+                todo.due_at.map(timestamp).unwrap_or_default(),
+                timestamp(todo.created_at),
+                todo.updated_at.map(timestamp).unwrap_or_default(),
+            ])
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    writer.into_inner().await.map_err(|e| e.to_string())
+}
+
+/// Header names to read each field from, overriding the usual spellings.
+#[derive(Debug, Default)]
+pub struct CsvColumnNames {
+    pub title: Option<String>,
+    pub priority: Option<String>,
+    pub is_active: Option<String>,
+    pub due_at: Option<String>,
+}
+
+/// Where each field is in a row; only `title` is required.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CsvColumns {
+    pub title: usize,
+    pub priority: Option<usize>,
+    pub is_active: Option<usize>,
+    pub due_at: Option<usize>,
+}
+
+impl CsvColumns {
+    /// Finds each field's column by header, ignoring case and surrounding
+    /// whitespace. Columns not named in `names` go by their usual spellings.
+    pub fn resolve(headers: &StringRecord, names: &CsvColumnNames) -> Result<CsvColumns, String> {
+        let find = |name: &Option<String>, aliases: &[&str]| -> Result<Option<usize>, String> {
+            let position = |alias: &str| {
+                headers
+                    .iter()
+                    .position(|header| header.trim().eq_ignore_ascii_case(alias.trim()))
+            };
+            match name {
+                Some(name) => position(name)
+                    .map(Some)
+                    .ok_or_else(|| format!("The CSV has no {} column", name)),
+                None => Ok(aliases.iter().find_map(|alias| position(alias))),
+            }
+        };
+
+        Ok(CsvColumns {
+            title: find(&names.title, &["title", "name", "task"])?
+                .ok_or("The CSV has no title column")?,
+            priority: find(&names.priority, &["priority"])?,
+            is_active: find(&names.is_active, &["is_active", "active"])?,
+            due_at: find(&names.due_at, &["due_at", "due", "due date", "due_date"])?,
+        })
+    }
+}
+
+/// A valid CSV row, ready to insert.
+#[derive(Debug, PartialEq)]
+pub struct CsvTodo {
+    pub title: String,
+    pub priority: String,
+    pub is_active: bool,
+    pub due_at: Option<DateTime<Utc>>,
+}
+
+impl CsvTodo {
+    /// Validates one row, failing with a message naming the bad field. Empty
+    /// fields take the same defaults as `POST /todo-items`; dates without an
+    /// offset are in `tz`, and a bare date means the end of that day.
+    pub fn parse(
+        record: &StringRecord,
+        columns: &CsvColumns,
+        tz: &Timezone,
+    ) -> Result<CsvTodo, String> {
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|i| record.get(i))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let title = TitleField::parse(field(Some(columns.title)).unwrap_or_default().into())
+            .map_err(|_| "title cannot be empty")?;
+        let priority = match field(columns.priority) {
+            Some(priority) => {
+                let priority = priority.to_ascii_lowercase();
+                if !PRIORITIES.contains(&priority.as_str()) {
+                    return Err(format!("priority must be one of {}", PRIORITIES.join(", ")));
+                }
+                priority
+            }
+            None => default_as_very_high(),
+        };
+        let is_active = match field(columns.is_active).map(str::to_ascii_lowercase) {
+            Some(value) => match value.as_str() {
+                "true" | "1" | "yes" | "y" => true,
+                "false" | "0" | "no" | "n" => false,
+                _ => return Err("is_active must be true or false".into()),
+            },
+            None => default_as_true(),
+        };
+        let due_at = field(columns.due_at)
+            .map(|value| parse_due_at(value, tz))
+            .transpose()?;
+
+        Ok(CsvTodo {
+            title: title.inner_ref().into(),
+            priority,
+            is_active,
+            due_at,
+        })
+    }
+}
+
+fn parse_due_at(value: &str, tz: &Timezone) -> Result<DateTime<Utc>, String> {
+    if let Ok(due_at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(due_at.with_timezone(&Utc).trunc_subsecs(0));
+    }
+    for format in [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(local) = NaiveDateTime::parse_from_str(value, format) {
+            return tz
+                .utc_from_local(local)
+                .ok_or_else(|| format!("due date {} is skipped by a DST change", value));
+        }
+    }
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => Ok(tz.end_of_day(date) - chrono::Duration::seconds(1)),
+        Err(_) => Err(format!(
+            "due date {} is not a date, e.g. 2026-10-19 or 2026-10-19T17:00:00Z",
+            value
+        )),
+    }
+}
+
+#[derive(Debug)]
+pub enum CsvImportError {
+    /// The header row is missing or lacks a title column.
+    Header(String),
+    /// Some rows are invalid, so none were imported. Holds at most
+    /// `MAX_REPORTED_ROW_ERRORS` of them, and how many there were.
+    Rows(Vec<ImportLineError>, usize),
+    /// The upload broke off.
+    Read(String),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for CsvImportError {
+    fn from(e: sqlx::Error) -> Self {
+        tracing::error!("Failed to execute query: {:?}", e);
+        CsvImportError::Database(e)
+    }
+}
+
+/// Adds a todo to `activity_group_id` for every row of `csv`, reading it as
+/// it arrives. All rows are inserted or, when any is invalid, none; the
+/// todos are published on `events` once committed.
+#[tracing::instrument(skip(csv, names, tz, pool, events))]
+pub async fn import_todos_csv<R>(
+    csv: R,
+    activity_group_id: i32,
+    names: &CsvColumnNames,
+    tz: &Timezone,
+    pool: &MySqlPool,
+    events: &EventBus,
+) -> Result<CsvImportSummary, CsvImportError>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    let _timer = query_timer("import_todos_csv");
+    let mut reader = AsyncReaderBuilder::new()
+        .flexible(true)
+        .trim(Trim::Headers)
+        .create_reader(csv);
+    let headers = reader
+        .headers()
+        .await
+        .map_err(|e| CsvImportError::Header(format!("Failed to read the CSV header: {}", e)))?;
+    let columns = CsvColumns::resolve(headers, names).map_err(CsvImportError::Header)?;
+
+    let mut tx = TimedPool(pool).begin().await?;
+    let mut summary = CsvImportSummary::default();
+    let mut created = Vec::new();
+    let mut errors = Vec::new();
+    let mut invalid = 0;
+    let mut record = StringRecord::new();
+    let now = Utc::now().trunc_subsecs(0);
+    loop {
+        let line = reader.position().line();
+        let todo = match reader.read_record(&mut record).await {
+            Ok(false) => break,
+            Ok(true) if record.iter().all(|field| field.trim().is_empty()) => continue,
+            Ok(true) => CsvTodo::parse(&record, &columns, tz),
+            Err(e) => match e.kind() {
+                csv_async::ErrorKind::Utf8 { .. } => Err("the row is not valid UTF-8".into()),
+                _ => return Err(CsvImportError::Read(e.to_string())),
+            },
+        };
+        let todo = match todo {
+            Ok(todo) => todo,
+            Err(message) => {
+                invalid += 1;
+                if errors.len() < MAX_REPORTED_ROW_ERRORS {
+                    errors.push(ImportLineError { line, message });
+                }
+                continue;
+            }
+        };
+        // Once a row is invalid nothing will be committed; keep reading only
+        // to report the rest.
+        if invalid > 0 {
+            continue;
+        }
+
+        let query = sqlx::query!(
+            r#"
+            insert into todos (title, activity_group_id, is_active, priority, due_at, created_at, updated_at)
+            values (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            todo.title,
+            activity_group_id,
+            todo.is_active,
+            todo.priority,
+            todo.due_at,
+            now,
+            now
+        )
+        .in_span(|query| query.execute(&mut tx))
+        .await?;
+        summary.created += 1;
+        created.push(TodoRecord {
+            id: query.last_insert_id() as i32,
+            title: todo.title,
+            activity_group_id,
+            is_active: todo.is_active,
+            priority: todo.priority,
+            due_at: todo.due_at,
+            created_at: now,
+            updated_at: Some(now),
+        });
+    }
+
+    if invalid > 0 {
+        tx.rollback().await?;
+        return Err(CsvImportError::Rows(errors, invalid));
+    }
+    tx.commit().await?;
+    for todo in &created {
+        events.todo_changed(ChangeAction::Created, todo);
+    }
+    Ok(summary)
+}
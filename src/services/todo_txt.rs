@@ -0,0 +1,251 @@
+use crate::common::{Timezone, TitleField};
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{
+    default_as_very_high, ChangeAction, EventBus, ImportLineError, TodoRecord,
+    MAX_REPORTED_ROW_ERRORS,
+};
+use crate::telemetry::QuerySpan;
+use chrono::{DateTime, NaiveDate, SubsecRound, Utc};
+use sqlx::MySqlPool;
+
+/// todo.txt priorities and the priority each stands for.
+pub const TODO_TXT_PRIORITIES: [(char, &str); 5] = [
+    ('A', "very-high"),
+    ('B', "high"),
+    ('C', "normal"),
+    ('D', "low"),
+    ('E', "very-low"),
+];
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The `+project` an activity group is written as: its title with
+/// whitespace turned into dashes.
+pub fn todo_txt_project(activity_group_title: &str) -> String {
+    activity_group_title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Renders a todo as one todo.txt line with dates in `tz`. Finished todos
+/// keep their priority as a `pri:` tag, and `updated_at` stands in for the
+/// completion date.
+pub fn format_todo_txt(todo: &TodoRecord, project: &str, tz: &Timezone) -> String {
+    let date = |instant: DateTime<Utc>| tz.localize(instant).format(DATE_FORMAT).to_string();
+    let priority = TODO_TXT_PRIORITIES
+        .iter()
+        .find(|(_, priority)| *priority == todo.priority)
+        .map(|(letter, _)| *letter);
+
+    let mut parts = Vec::new();
+    if todo.is_active {
+        if let Some(letter) = priority {
+            parts.push(format!("({})", letter));
+        }
+    } else {
+        parts.push("x".to_string());
+        parts.push(date(todo.updated_at.unwrap_or(todo.created_at)));
+    }
+    parts.push(date(todo.created_at));
+    parts.push(todo.title.split_whitespace().collect::<Vec<_>>().join(" "));
+    if !project.is_empty() {
+        parts.push(format!("+{}", project));
+    }
+    if let Some(due_at) = todo.due_at {
+        parts.push(format!("due:{}", date(due_at)));
+    }
+    if let (false, Some(letter)) = (todo.is_active, priority) {
+        parts.push(format!("pri:{}", letter));
+    }
+    parts.join(" ")
+}
+
+/// A parsed todo.txt line.
+#[derive(Debug, PartialEq)]
+pub struct TodoTxtTask {
+    /// The description, `@contexts` and unknown `key:value` tags included.
+    pub title: String,
+    pub priority: String,
+    pub is_active: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub due_at: Option<DateTime<Utc>>,
+}
+
+impl TodoTxtTask {
+    /// Parses one line, dropping the `+project` of the activity group it is
+    /// imported into. Dates are days in `tz`; a due date means the end of
+    /// that day.
+    pub fn parse(line: &str, project: &str, tz: &Timezone) -> Result<TodoTxtTask, String> {
+        let date = |token: &str| NaiveDate::parse_from_str(token, DATE_FORMAT).ok();
+        let mut tokens = line.split_whitespace().peekable();
+        let mut priority = None;
+        let mut is_active = true;
+        let mut completed_on = None;
+
+        if tokens.peek() == Some(&"x") {
+            tokens.next();
+            is_active = false;
+            completed_on = tokens.peek().and_then(|token| date(token));
+            if completed_on.is_some() {
+                tokens.next();
+            }
+        } else if let Some(letter) = tokens.peek().and_then(|token| priority_letter(token)) {
+            tokens.next();
+            priority = Some(todo_txt_priority(letter)?);
+        }
+        let created_on = tokens.peek().and_then(|token| date(token));
+        if created_on.is_some() {
+            tokens.next();
+        }
+
+        let mut description = Vec::new();
+        let mut due_on = None;
+        for token in tokens {
+            if let Some(value) = token.strip_prefix("due:") {
+                due_on = Some(
+                    date(value)
+                        .ok_or_else(|| format!("due:{} is not a date like 2026-10-19", value))?,
+                );
+            } else if let Some(letter) = token
+                .strip_prefix("pri:")
+                .filter(|value| value.len() == 1)
+                .and_then(|value| value.chars().next())
+            {
+                priority = Some(todo_txt_priority(letter)?);
+            } else if token
+                .strip_prefix('+')
+                .is_some_and(|tag| !project.is_empty() && tag.eq_ignore_ascii_case(project))
+            {
+                continue;
+            } else {
+                description.push(token);
+            }
+        }
+
+        let title =
+            TitleField::parse(description.join(" ")).map_err(|_| "the task has no description")?;
+        Ok(TodoTxtTask {
+            title: title.inner_ref().into(),
+            priority: priority.unwrap_or_else(default_as_very_high),
+            is_active,
+            created_at: created_on.map(|date| tz.start_of_day(date)),
+            completed_at: completed_on.map(|date| tz.start_of_day(date)),
+            due_at: due_on.map(|date| tz.end_of_day(date) - chrono::Duration::seconds(1)),
+        })
+    }
+}
+
+/// The letter of a `(A)` style priority token.
+fn priority_letter(token: &str) -> Option<char> {
+    let letter = token.strip_prefix('(')?.strip_suffix(')')?;
+    let mut chars = letter.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) if letter.is_ascii_uppercase() => Some(letter),
+        _ => None,
+    }
+}
+
+/// Letters past `(E)` are lower still, so they all stand for very-low.
+fn todo_txt_priority(letter: char) -> Result<String, String> {
+    if !letter.is_ascii_uppercase() {
+        return Err(format!("priority ({}) is not one of (A) to (Z)", letter));
+    }
+    let priority = TODO_TXT_PRIORITIES
+        .iter()
+        .find(|(l, _)| *l == letter)
+        .map_or("very-low", |(_, priority)| priority);
+    Ok(priority.into())
+}
+
+#[derive(Debug)]
+pub enum TodoTxtImportError {
+    /// Some lines are invalid, so none were imported. Holds at most
+    /// `MAX_REPORTED_ROW_ERRORS` of them, and how many there were.
+    Lines(Vec<ImportLineError>, usize),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for TodoTxtImportError {
+    fn from(e: sqlx::Error) -> Self {
+        tracing::error!("Failed to execute query: {:?}", e);
+        TodoTxtImportError::Database(e)
+    }
+}
+
+/// Adds a todo to `activity_group_id` for every non-blank line of `text`,
+/// all of them or, when any line is invalid, none. The todos are published
+/// on `events` once committed.
+#[tracing::instrument(skip(text, tz, pool, events))]
+pub async fn import_todo_txt(
+    text: &str,
+    activity_group_id: i32,
+    project: &str,
+    tz: &Timezone,
+    pool: &MySqlPool,
+    events: &EventBus,
+) -> Result<Vec<TodoRecord>, TodoTxtImportError> {
+    let _timer = query_timer("import_todo_txt");
+    let mut tasks = Vec::new();
+    let mut errors = Vec::new();
+    let mut invalid = 0;
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match TodoTxtTask::parse(line, project, tz) {
+            Ok(task) => tasks.push(task),
+            Err(message) => {
+                invalid += 1;
+                if errors.len() < MAX_REPORTED_ROW_ERRORS {
+                    errors.push(ImportLineError {
+                        line: i as u64 + 1,
+                        message,
+                    });
+                }
+            }
+        }
+    }
+    if invalid > 0 {
+        return Err(TodoTxtImportError::Lines(errors, invalid));
+    }
+
+    let now = Utc::now().trunc_subsecs(0);
+    let mut todos = Vec::with_capacity(tasks.len());
+    let mut tx = TimedPool(pool).begin().await?;
+    for task in tasks {
+        let created_at = task.created_at.unwrap_or(now);
+        let updated_at = task.completed_at.unwrap_or(now).max(created_at);
+        let query = sqlx::query!(
+            r#"
+            insert into todos (title, activity_group_id, is_active, priority, due_at, created_at, updated_at)
+            values (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            task.title,
+            activity_group_id,
+            task.is_active,
+            task.priority,
+            task.due_at,
+            created_at,
+            updated_at
+        )
+        .in_span(|query| query.execute(&mut tx))
+        .await?;
+        todos.push(TodoRecord {
+            id: query.last_insert_id() as i32,
+            title: task.title,
+            activity_group_id,
+            is_active: task.is_active,
+            priority: task.priority,
+            due_at: task.due_at,
+            created_at,
+            updated_at: Some(updated_at),
+        });
+    }
+    tx.commit().await?;
+    for todo in &todos {
+        events.todo_changed(ChangeAction::Created, todo);
+    }
+    Ok(todos)
+}
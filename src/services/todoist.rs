@@ -0,0 +1,274 @@
+use crate::common::Timezone;
+use crate::services::{external_title, unique_ids, ExternalGroup, ExternalImport, ExternalTodo};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde_json::Value;
+
+/// A Todoist backup in the JSON shape of its Sync API, as written by export
+/// tools. REST API field names are accepted as well. Fields this importer
+/// neither maps nor reports are ignored.
+#[derive(serde::Deserialize, Debug)]
+pub struct TodoistBackup {
+    #[serde(default)]
+    pub projects: Vec<TodoistProject>,
+    #[serde(default)]
+    pub sections: Vec<TodoistSection>,
+    #[serde(default, alias = "tasks")]
+    pub items: Vec<TodoistItem>,
+    #[serde(default, alias = "comments")]
+    pub notes: Vec<Value>,
+}
+
+/// Todoist ids: strings nowadays, numbers in older backups.
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum TodoistId {
+    Text(String),
+    Number(i64),
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct TodoistProject {
+    pub id: TodoistId,
+    pub name: String,
+    #[serde(default, alias = "order")]
+    pub child_order: i64,
+    #[serde(default)]
+    pub is_archived: bool,
+    #[serde(default)]
+    pub is_deleted: bool,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct TodoistSection {
+    pub id: TodoistId,
+    pub project_id: TodoistId,
+    pub name: String,
+    #[serde(default, alias = "order")]
+    pub section_order: i64,
+    #[serde(default)]
+    pub is_deleted: bool,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct TodoistItem {
+    pub id: TodoistId,
+    pub project_id: TodoistId,
+    pub section_id: Option<TodoistId>,
+    pub parent_id: Option<TodoistId>,
+    pub content: String,
+    #[serde(default)]
+    pub description: String,
+    /// 4 is the most urgent, shown as p1 in the app; 1 means none.
+    #[serde(default = "default_todoist_priority")]
+    pub priority: u8,
+    pub due: Option<TodoistDue>,
+    #[serde(default, alias = "is_completed")]
+    pub checked: bool,
+    #[serde(default)]
+    pub is_deleted: bool,
+    #[serde(default, alias = "order")]
+    pub child_order: i64,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(alias = "created_at")]
+    pub added_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(alias = "assignee_id")]
+    pub responsible_uid: Option<TodoistId>,
+    pub duration: Option<Value>,
+}
+
+fn default_todoist_priority() -> u8 {
+    1
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct TodoistDue {
+    /// `2026-10-20`, a floating `2026-10-20T09:00:00`, or a UTC
+    /// `2026-10-20T09:00:00Z`.
+    pub date: String,
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub is_recurring: bool,
+}
+
+/// Todoist priorities from 1 (none) to 4 (p1) and the priority each maps to.
+pub const TODOIST_PRIORITIES: [(u8, &str); 4] =
+    [(1, "low"), (2, "normal"), (3, "high"), (4, "very-high")];
+
+impl TodoistDue {
+    /// A due date is the end of that day in `tz`; a floating time is in the
+    /// task's own timezone when it has one, else in `tz`.
+    fn instant(&self, tz: &Timezone) -> Result<DateTime<Utc>, String> {
+        if let Ok(date) = NaiveDate::parse_from_str(&self.date, "%Y-%m-%d") {
+            return Ok(tz.end_of_day(date) - chrono::Duration::seconds(1));
+        }
+        if let Ok(instant) = DateTime::parse_from_rfc3339(&self.date) {
+            return Ok(instant.with_timezone(&Utc));
+        }
+        let invalid = || format!("due date \"{}\" is not a date or date-time", self.date);
+        let local = NaiveDateTime::parse_from_str(&self.date, "%Y-%m-%dT%H:%M:%S")
+            .map_err(|_| invalid())?;
+        let zone = match &self.timezone {
+            Some(name) => Timezone::parse(name)
+                .map_err(|_| format!("due date has an unknown timezone \"{}\"", name))?,
+            None => *tz,
+        };
+        zone.utc_from_local(local).ok_or_else(invalid)
+    }
+}
+
+/// Maps every project onto an activity group. Tasks become todos ordered by
+/// section, those outside any section first, and then by their place in it;
+/// subtasks follow their parent, titled after it. Archived projects and
+/// deleted entries are skipped.
+pub fn todoist_import(backup: &TodoistBackup, tz: &Timezone) -> Result<ExternalImport, String> {
+    let mut import = ExternalImport::default();
+    import.unmapped.add("notes", backup.notes.len() as u64);
+
+    unique_ids(backup.projects.iter().map(|project| &project.id), |id| {
+        format!("project {}", id_text(id))
+    })?;
+    unique_ids(backup.sections.iter().map(|section| &section.id), |id| {
+        format!("section {}", id_text(id))
+    })?;
+    unique_ids(backup.items.iter().map(|item| &item.id), |id| {
+        format!("task {}", id_text(id))
+    })?;
+
+    let mut projects: Vec<&TodoistProject> = backup.projects.iter().collect();
+    projects.sort_by_key(|project| project.child_order);
+    let mut imported = 0;
+    for project in projects {
+        if project.is_archived || project.is_deleted {
+            continue;
+        }
+        let title = external_title(&project.name, || {
+            format!("project {}", id_text(&project.id))
+        })?;
+
+        let mut sections: Vec<Option<&TodoistSection>> = vec![None];
+        let mut own: Vec<&TodoistSection> = backup
+            .sections
+            .iter()
+            .filter(|section| !section.is_deleted && section.project_id == project.id)
+            .collect();
+        own.sort_by_key(|section| section.section_order);
+        sections.extend(own.into_iter().map(Some));
+
+        let mut todos = Vec::new();
+        for section in sections {
+            let section_id = section.map(|section| &section.id);
+            let top_level = children(backup, &project.id, None).filter(|item| {
+                // Tasks of deleted sections fall back to no section.
+                let in_section = item.section_id.as_ref().filter(|id| {
+                    backup
+                        .sections
+                        .iter()
+                        .any(|section| !section.is_deleted && section.id == **id)
+                });
+                in_section == section_id
+            });
+            let top_level: Vec<&TodoistItem> = sorted(top_level);
+            // Sections only order the todos; their names have no place here.
+            if let Some(section) = section {
+                import.unmapped.count(
+                    "sections.name",
+                    !top_level.is_empty() && !section.name.is_empty(),
+                );
+            }
+            for item in top_level {
+                add_with_subtasks(backup, item, None, tz, &mut import, &mut todos)?;
+            }
+        }
+        imported += todos.len();
+        import.groups.push(ExternalGroup { title, todos });
+    }
+    import.skipped = backup.items.len().saturating_sub(imported) as u64;
+    Ok(import)
+}
+
+fn id_text(id: &TodoistId) -> String {
+    match id {
+        TodoistId::Text(id) => id.clone(),
+        TodoistId::Number(id) => id.to_string(),
+    }
+}
+
+/// The live tasks of a project directly below `parent`, or at the top.
+fn children<'a>(
+    backup: &'a TodoistBackup,
+    project_id: &'a TodoistId,
+    parent: Option<&'a TodoistId>,
+) -> impl Iterator<Item = &'a TodoistItem> {
+    backup.items.iter().filter(move |item| {
+        !item.is_deleted && item.project_id == *project_id && item.parent_id.as_ref() == parent
+    })
+}
+
+fn sorted<'a>(items: impl Iterator<Item = &'a TodoistItem>) -> Vec<&'a TodoistItem> {
+    let mut items: Vec<&TodoistItem> = items.collect();
+    items.sort_by_key(|item| item.child_order);
+    items
+}
+
+fn add_with_subtasks(
+    backup: &TodoistBackup,
+    item: &TodoistItem,
+    parent_title: Option<&str>,
+    tz: &Timezone,
+    import: &mut ExternalImport,
+    todos: &mut Vec<ExternalTodo>,
+) -> Result<(), String> {
+    let content = external_title(&item.content, || format!("task {}", id_text(&item.id)))?;
+    let title = match parent_title {
+        Some(parent) => format!("{}: {}", parent, content),
+        None => content,
+    };
+    let priority = TODOIST_PRIORITIES
+        .iter()
+        .find(|(p, _)| *p == item.priority)
+        .map(|(_, priority)| priority.to_string())
+        .ok_or_else(|| {
+            format!(
+                "task {} has priority {}, not one from 1 to 4",
+                id_text(&item.id),
+                item.priority
+            )
+        })?;
+    let due_at = match &item.due {
+        Some(due) => Some(
+            due.instant(tz)
+                .map_err(|message| format!("task {}: {}", id_text(&item.id), message))?,
+        ),
+        None => None,
+    };
+
+    let unmapped = &mut import.unmapped;
+    unmapped.count("items.description", !item.description.trim().is_empty());
+    unmapped.count("items.labels", !item.labels.is_empty());
+    unmapped.count(
+        "items.due.is_recurring",
+        item.due.as_ref().is_some_and(|due| due.is_recurring),
+    );
+    unmapped.count("items.responsible_uid", item.responsible_uid.is_some());
+    unmapped.count(
+        "items.duration",
+        item.duration
+            .as_ref()
+            .is_some_and(|duration| !duration.is_null()),
+    );
+    todos.push(ExternalTodo {
+        title: title.clone(),
+        priority,
+        is_active: !item.checked,
+        due_at,
+        created_at: item.added_at,
+        updated_at: item.completed_at,
+    });
+
+    for subtask in sorted(children(backup, &item.project_id, Some(&item.id))) {
+        add_with_subtasks(backup, subtask, Some(&title), tz, import, todos)?;
+    }
+    Ok(())
+}
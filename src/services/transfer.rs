@@ -0,0 +1,468 @@
+use crate::common::{Timezone, TitleField};
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{
+    default_as_true, default_as_very_high, get_activities, get_todos, insert_activity, insert_todo,
+    ActivityRecord, ChangeAction, EventBus, NewActivity, NewTodo, TodoFilter, TodoRecord,
+};
+use crate::telemetry::QuerySpan;
+use chrono::{DateTime, SubsecRound, Utc};
+use sqlx::MySqlPool;
+use std::collections::{HashMap, HashSet};
+
+pub use zero2prod_types::{
+    ConflictResolution, Export, ExportedActivityGroup, IdMapping, ImportConflict, ImportEntity,
+    ImportMode, ImportReport,
+};
+
+/// The only `Export::version` this build reads and writes.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// The values of `todos.priority`.
+pub const PRIORITIES: [&str; 5] = ["very-low", "low", "normal", "high", "very-high"];
+
+/// What `purge_deleted` removed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PurgeSummary {
+    pub activity_groups: u64,
+    pub todos: u64,
+}
+
+/// Snapshots every activity group with its todos.
+#[tracing::instrument(skip_all)]
+pub async fn export_all(pool: &MySqlPool) -> Result<Export, sqlx::Error> {
+    let tz = Timezone::default();
+    let mut todos = get_todos(&TodoFilter::default(), pool).await?;
+    let activity_groups = get_activities(pool)
+        .await?
+        .into_iter()
+        .map(|activity_group| {
+            let (own, rest): (Vec<TodoRecord>, _) = todos
+                .drain(..)
+                .partition(|todo| todo.activity_group_id == activity_group.id);
+            todos = rest;
+            ExportedActivityGroup {
+                activity_group: activity_group.into_v1(&tz),
+                todos: own.into_iter().map(|todo| todo.into_v1(&tz)).collect(),
+            }
+        })
+        .collect();
+
+    Ok(Export {
+        version: EXPORT_VERSION,
+        exported_at: Utc::now().trunc_subsecs(0).into(),
+        activity_groups,
+    })
+}
+
+/// Checks that an export can be imported, failing with a message naming the
+/// first offending entry.
+pub fn validate_export(export: &Export) -> Result<(), String> {
+    if export.version != EXPORT_VERSION {
+        return Err(format!(
+            "Unsupported export version {}; expected {}",
+            export.version, EXPORT_VERSION
+        ));
+    }
+    let mut activity_group_ids = HashSet::new();
+    let mut todo_ids = HashSet::new();
+    for group in &export.activity_groups {
+        let id = group.activity_group.id;
+        if !activity_group_ids.insert(id) {
+            return Err(format!("Activity group {} appears more than once", id));
+        }
+        TitleField::parse(group.activity_group.title.clone())
+            .map_err(|_| format!("Activity group {} has an empty title", id))?;
+        for todo in &group.todos {
+            if !todo_ids.insert(todo.id) {
+                return Err(format!("Todo {} appears more than once", todo.id));
+            }
+            TitleField::parse(todo.title.clone())
+                .map_err(|_| format!("Todo {} has an empty title", todo.id))?;
+            if let Some(priority) = &todo.priority {
+                if !PRIORITIES.contains(&priority.as_str()) {
+                    return Err(format!(
+                        "Todo {} has an unknown priority {}",
+                        todo.id, priority
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Loads a validated export in one transaction, keeping timestamps and
+/// assigning new ids. Once committed, the groups replaced and the groups and
+/// todos created are published on `events`.
+#[tracing::instrument(skip(export, pool, events))]
+pub async fn import_export(
+    export: &Export,
+    mode: ImportMode,
+    pool: &MySqlPool,
+    events: &EventBus,
+) -> Result<ImportReport, sqlx::Error> {
+    let _timer = query_timer("import_export");
+    let mut report = ImportReport {
+        mode,
+        ..ImportReport::default()
+    };
+    let mut replaced = Vec::new();
+    let mut activities = Vec::new();
+    let mut todos = Vec::new();
+    let mut tx = TimedPool(pool).begin().await?;
+
+    if mode == ImportMode::Replace {
+        replaced = sqlx::query_scalar!("select id from activities where deleted_at is null")
+            .in_span(|query| query.fetch_all(&mut tx))
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to execute query: {:?}", e);
+                e
+            })?;
+        for query in [
+            sqlx::query!("delete from reminders"),
+            sqlx::query!("delete from activity_digests"),
+            sqlx::query!("delete from calendar_feeds"),
+            sqlx::query!("delete from calendar_objects"),
+            sqlx::query!("delete from calendar_tombstones"),
+            sqlx::query!("delete from todos"),
+            sqlx::query!("delete from activities"),
+        ] {
+            query
+                .in_span(|query| query.execute(&mut tx))
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to execute query: {:?}", e);
+                    e
+                })?;
+        }
+    }
+
+    let mut existing_groups: HashMap<String, i32> = HashMap::new();
+    if mode != ImportMode::Replace {
+        for row in sqlx::query!("select id, title from activities order by id desc")
+            .in_span(|query| query.fetch_all(&mut tx))
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to execute query: {:?}", e);
+                e
+            })?
+        {
+            existing_groups.insert(row.title, row.id);
+        }
+    }
+
+    for group in &export.activity_groups {
+        let activity = &group.activity_group;
+        let mut existing_todos: HashMap<String, i32> = HashMap::new();
+        let activity_group_id = match existing_groups.get(&activity.title) {
+            Some(&existing_id) => {
+                report.conflicts.push(ImportConflict {
+                    entity: ImportEntity::ActivityGroup,
+                    id: activity.id,
+                    existing_id,
+                    resolution: ConflictResolution::Merged,
+                });
+                for row in sqlx::query!(
+                    "select id, title from todos where activity_group_id = ? order by id desc",
+                    existing_id
+                )
+                .in_span(|query| query.fetch_all(&mut tx))
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to execute query: {:?}", e);
+                    e
+                })? {
+                    existing_todos.insert(row.title, row.id);
+                }
+                existing_id
+            }
+            None => {
+                let query = sqlx::query!(
+                    r#"
+                    insert into activities (title, email, created_at, updated_at)
+                    values (?, ?, ?, ?)
+                    "#,
+                    activity.title,
+                    activity.email,
+                    activity.created_at.with_timezone(&Utc),
+                    activity
+                        .updated_at
+                        .map(|updated_at| updated_at.with_timezone(&Utc))
+                )
+                .in_span(|query| query.execute(&mut tx))
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to execute query: {:?}", e);
+                    e
+                })?;
+                let id = query.last_insert_id() as i32;
+                // Later groups with the same title merge into this one.
+                existing_groups.insert(activity.title.clone(), id);
+                activities.push(ActivityRecord {
+                    id,
+                    title: activity.title.clone(),
+                    email: activity.email.clone(),
+                    created_at: activity.created_at.with_timezone(&Utc),
+                    updated_at: activity
+                        .updated_at
+                        .map(|updated_at| updated_at.with_timezone(&Utc)),
+                });
+                id
+            }
+        };
+        report.activity_groups.push(IdMapping {
+            from: activity.id,
+            to: activity_group_id,
+        });
+
+        for todo in &group.todos {
+            if let Some(&existing_id) = existing_todos.get(&todo.title) {
+                report.conflicts.push(ImportConflict {
+                    entity: ImportEntity::Todo,
+                    id: todo.id,
+                    existing_id,
+                    resolution: ConflictResolution::Skipped,
+                });
+                report.todos.push(IdMapping {
+                    from: todo.id,
+                    to: existing_id,
+                });
+                continue;
+            }
+            let record = TodoRecord {
+                id: 0,
+                title: todo.title.clone(),
+                activity_group_id,
+                is_active: todo.is_active.unwrap_or_else(default_as_true),
+                priority: todo.priority.clone().unwrap_or_else(default_as_very_high),
+                due_at: todo.due_at.map(|due_at| due_at.with_timezone(&Utc)),
+                created_at: todo.created_at.with_timezone(&Utc),
+                updated_at: todo
+                    .updated_at
+                    .map(|updated_at| updated_at.with_timezone(&Utc)),
+            };
+            let query = sqlx::query!(
+                r#"
+                insert into todos (title, activity_group_id, is_active, priority, due_at, created_at, updated_at)
+                values (?, ?, ?, ?, ?, ?, ?)
+                "#,
+                record.title,
+                record.activity_group_id,
+                record.is_active,
+                record.priority,
+                record.due_at,
+                record.created_at,
+                record.updated_at
+            )
+            .in_span(|query| query.execute(&mut tx))
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to execute query: {:?}", e);
+                e
+            })?;
+            let id = query.last_insert_id() as i32;
+            report.todos.push(IdMapping {
+                from: todo.id,
+                to: id,
+            });
+            todos.push(TodoRecord { id, ..record });
+        }
+    }
+
+    if mode == ImportMode::DryRun {
+        tx.rollback().await?;
+        return Ok(report);
+    }
+    tx.commit().await?;
+    for activity_id in replaced {
+        events.activity_deleted(activity_id);
+    }
+    for activity in &activities {
+        events.activity_changed(ChangeAction::Created, activity);
+    }
+    for todo in &todos {
+        events.todo_changed(ChangeAction::Created, todo);
+    }
+    Ok(report)
+}
+
+/// Hard-deletes activity groups and todos soft-deleted before `cutoff`,
+/// along with the todos, reminders, digest subscriptions and calendar state
+/// hanging off them.
+#[tracing::instrument(skip(pool))]
+pub async fn purge_deleted(
+    cutoff: DateTime<Utc>,
+    pool: &MySqlPool,
+) -> Result<PurgeSummary, sqlx::Error> {
+    let _timer = query_timer("purge_deleted");
+    let mut tx = TimedPool(pool).begin().await?;
+
+    sqlx::query!(
+        r#"
+        delete from reminders where todo_id in (
+            select id from todos
+            where deleted_at < ?
+            or activity_group_id in (select id from activities where deleted_at < ?)
+        )
+        "#,
+        cutoff,
+        cutoff
+    )
+    .in_span(|query| query.execute(&mut tx))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    sqlx::query!(
+        r#"
+        delete from calendar_objects
+        where todo_id in (
+            select id from todos
+            where deleted_at < ?
+            or activity_group_id in (select id from activities where deleted_at < ?)
+        )
+        "#,
+        cutoff,
+        cutoff
+    )
+    .in_span(|query| query.execute(&mut tx))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    let todos = sqlx::query!(
+        r#"
+        delete from todos
+        where deleted_at < ?
+        or activity_group_id in (select id from activities where deleted_at < ?)
+        "#,
+        cutoff,
+        cutoff
+    )
+    .in_span(|query| query.execute(&mut tx))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?
+    .rows_affected();
+
+    sqlx::query!(
+        r#"
+        delete from activity_digests
+        where activity_group_id in (select id from activities where deleted_at < ?)
+        "#,
+        cutoff
+    )
+    .in_span(|query| query.execute(&mut tx))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    sqlx::query!(
+        r#"
+        delete from calendar_feeds
+        where activity_group_id in (select id from activities where deleted_at < ?)
+        "#,
+        cutoff
+    )
+    .in_span(|query| query.execute(&mut tx))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    sqlx::query!(
+        r#"
+        delete from calendar_tombstones
+        where activity_group_id in (select id from activities where deleted_at < ?)
+        "#,
+        cutoff
+    )
+    .in_span(|query| query.execute(&mut tx))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    let activity_groups = sqlx::query!("delete from activities where deleted_at < ?", cutoff)
+        .in_span(|query| query.execute(&mut tx))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?
+        .rows_affected();
+
+    tx.commit().await?;
+    Ok(PurgeSummary {
+        activity_groups,
+        todos,
+    })
+}
+
+/// Fills an empty database with a couple of sample activity groups. Returns
+/// `false` without writing when activity groups already exist.
+#[tracing::instrument(skip(pool, events))]
+pub async fn seed_sample_data(
+    pool: &MySqlPool,
+    force: bool,
+    events: &EventBus,
+) -> Result<bool, sqlx::Error> {
+    if !force && !get_activities(pool).await?.is_empty() {
+        return Ok(false);
+    }
+
+    let samples: [(&str, &[(&str, &str)]); 2] = [
+        (
+            "Getting started",
+            &[
+                ("Create an activity group", "very-high"),
+                ("Add a todo with a due date", "high"),
+                ("Set up a reminder", "normal"),
+            ],
+        ),
+        (
+            "Groceries",
+            &[("Milk", "normal"), ("Coffee beans", "very-low")],
+        ),
+    ];
+
+    for (title, todos) in samples {
+        let activity = insert_activity(
+            pool,
+            &NewActivity {
+                title: TitleField::parse(title.into()).expect("sample titles are not empty"),
+                email: None,
+            },
+            events,
+        )
+        .await?;
+        for (title, priority) in todos {
+            insert_todo(
+                pool,
+                &NewTodo {
+                    title: TitleField::parse(title.to_string())
+                        .expect("sample titles are not empty"),
+                    activity_group_id: activity.id,
+                    is_active: Some(true),
+                    priority: Some(priority.to_string()),
+                    due_at: None,
+                },
+                events,
+            )
+            .await?;
+        }
+    }
+
+    Ok(true)
+}
@@ -0,0 +1,177 @@
+use crate::services::{
+    default_as_very_high, external_title, unique_ids, ExternalGroup, ExternalImport, ExternalTodo,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::Value;
+
+/// A board as exported by Trello's "Print, export and share" menu. Fields
+/// this importer neither maps nor reports are ignored.
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrelloBoard {
+    pub name: String,
+    #[serde(default)]
+    pub desc: String,
+    #[serde(default)]
+    pub lists: Vec<TrelloList>,
+    #[serde(default)]
+    pub cards: Vec<TrelloCard>,
+    #[serde(default)]
+    pub checklists: Vec<TrelloChecklist>,
+    #[serde(default)]
+    pub actions: Vec<TrelloAction>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrelloList {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub closed: bool,
+    #[serde(default)]
+    pub pos: f64,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrelloCard {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub desc: String,
+    pub id_list: String,
+    #[serde(default)]
+    pub closed: bool,
+    #[serde(default)]
+    pub pos: f64,
+    pub due: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub due_complete: bool,
+    pub start: Option<DateTime<Utc>>,
+    pub date_last_activity: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub labels: Vec<Value>,
+    #[serde(default)]
+    pub id_members: Vec<String>,
+    #[serde(default)]
+    pub attachments: Vec<Value>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrelloChecklist {
+    pub id_card: String,
+    #[serde(default)]
+    pub pos: f64,
+    #[serde(default)]
+    pub check_items: Vec<TrelloCheckItem>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrelloCheckItem {
+    pub name: String,
+    /// `complete` or `incomplete`.
+    pub state: String,
+    #[serde(default)]
+    pub pos: f64,
+    pub due: Option<DateTime<Utc>>,
+    pub id_member: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct TrelloAction {
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+/// When a Trello object was created: its id is a MongoDB ObjectId, which
+/// starts with the creation second.
+fn created_at(id: &str) -> Option<DateTime<Utc>> {
+    let seconds = i64::from_str_radix(id.get(..8)?, 16).ok()?;
+    Utc.timestamp_opt(seconds, 0).single()
+}
+
+/// Maps a board onto one activity group. Cards become todos ordered by list
+/// and then by their place in it, each followed by its checklist items as
+/// todos of their own, titled after the card. A card is done when its due
+/// date is marked complete. Archived lists and cards are skipped.
+pub fn trello_import(board: &TrelloBoard) -> Result<ExternalImport, String> {
+    let mut import = ExternalImport::default();
+    let unmapped = &mut import.unmapped;
+    let title = external_title(&board.name, || "the board".into())?;
+    unmapped.count("desc", !board.desc.trim().is_empty());
+    let comments = board
+        .actions
+        .iter()
+        .filter(|action| action.kind == "commentCard")
+        .count();
+    unmapped.add("actions.commentCard", comments as u64);
+
+    unique_ids(board.lists.iter().map(|list| &list.id), |id| {
+        format!("list {}", id)
+    })?;
+    let mut lists: Vec<&TrelloList> = board.lists.iter().filter(|list| !list.closed).collect();
+    lists.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+    let mut cards: Vec<&TrelloCard> = Vec::new();
+    for list in &lists {
+        let mut own: Vec<&TrelloCard> = board
+            .cards
+            .iter()
+            .filter(|card| !card.closed && card.id_list == list.id)
+            .collect();
+        own.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+        // Lists only order the todos; their names have no place here.
+        unmapped.count("lists.name", !own.is_empty());
+        cards.extend(own);
+    }
+    import.skipped = board.cards.len().saturating_sub(cards.len()) as u64;
+
+    let mut todos = Vec::new();
+    for card in cards {
+        let card_title = external_title(&card.name, || format!("card {}", card.id))?;
+        unmapped.count("cards.desc", !card.desc.trim().is_empty());
+        unmapped.count("cards.labels", !card.labels.is_empty());
+        unmapped.count("cards.idMembers", !card.id_members.is_empty());
+        unmapped.count("cards.start", card.start.is_some());
+        unmapped.count("cards.attachments", !card.attachments.is_empty());
+        let created_at = created_at(&card.id);
+        todos.push(ExternalTodo {
+            title: card_title.clone(),
+            priority: default_as_very_high(),
+            is_active: !card.due_complete,
+            due_at: card.due,
+            created_at,
+            updated_at: card.date_last_activity,
+        });
+
+        let mut checklists: Vec<&TrelloChecklist> = board
+            .checklists
+            .iter()
+            .filter(|checklist| checklist.id_card == card.id)
+            .collect();
+        checklists.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+        for checklist in checklists {
+            let mut items: Vec<&TrelloCheckItem> = checklist.check_items.iter().collect();
+            items.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+            for item in items {
+                let item_title = external_title(&item.name, || {
+                    format!("a checklist item of card {}", card.id)
+                })?;
+                unmapped.count("checklists.checkItems.idMember", item.id_member.is_some());
+                todos.push(ExternalTodo {
+                    title: format!("{}: {}", card_title, item_title),
+                    priority: default_as_very_high(),
+                    is_active: item.state != "complete",
+                    due_at: item.due,
+                    created_at,
+                    updated_at: None,
+                });
+            }
+        }
+    }
+
+    import.groups.push(ExternalGroup { title, todos });
+    Ok(import)
+}
@@ -0,0 +1,417 @@
+use crate::common::Timezone;
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::StoredEvent;
+use crate::telemetry::QuerySpan;
+use chrono::{DateTime, SubsecRound, TimeZone, Utc};
+use sqlx::MySqlPool;
+
+pub use zero2prod_types::{
+    DeliveryStatus, Webhook, WebhookDelivery, WebhookFormData, WebhookPayload,
+};
+
+/// Event names a webhook can subscribe to, besides `*`.
+pub const WEBHOOK_EVENT_TYPES: [&str; 6] = [
+    "todo.created",
+    "todo.updated",
+    "todo.deleted",
+    "activity-group.created",
+    "activity-group.updated",
+    "activity-group.deleted",
+];
+
+/// A webhook as stored, with timestamps in UTC.
+#[derive(Clone, Debug)]
+pub struct WebhookRecord {
+    pub id: i32,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl WebhookRecord {
+    pub fn into_v1(self, tz: &Timezone) -> Webhook {
+        Webhook {
+            id: self.id,
+            url: self.url,
+            event_types: self.event_types,
+            created_at: tz.localize(self.created_at),
+            updated_at: self.updated_at.map(|updated_at| tz.localize(updated_at)),
+        }
+    }
+
+    pub fn wants(&self, event_type: &str) -> bool {
+        self.event_types
+            .iter()
+            .any(|wanted| wanted == "*" || wanted == event_type)
+    }
+}
+
+/// A delivery as stored, with timestamps in UTC.
+#[derive(Clone, Debug)]
+pub struct WebhookDeliveryRecord {
+    pub id: i64,
+    pub webhook_id: i32,
+    pub event_type: String,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl WebhookDeliveryRecord {
+    pub fn into_v1(self, tz: &Timezone) -> WebhookDelivery {
+        WebhookDelivery {
+            id: self.id,
+            webhook_id: self.webhook_id,
+            event_type: self.event_type,
+            status: self.status,
+            attempts: self.attempts,
+            response_status: self.response_status,
+            last_error: self.last_error,
+            next_attempt_at: tz.localize(self.next_attempt_at),
+            created_at: tz.localize(self.created_at),
+            delivered_at: self
+                .delivered_at
+                .map(|delivered_at| tz.localize(delivered_at)),
+        }
+    }
+}
+
+pub struct NewWebhook {
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub secret: String,
+}
+
+/// Changes to a webhook; unset fields keep their current value.
+#[derive(Default)]
+pub struct UpdateWebhook {
+    pub url: Option<String>,
+    pub event_types: Option<Vec<String>>,
+    pub secret: Option<String>,
+}
+
+impl NewWebhook {
+    /// Validates a create form, failing with the message reported to the
+    /// client.
+    pub fn parse(form: WebhookFormData) -> Result<NewWebhook, String> {
+        let url = form.url.ok_or("url cannot be null")?;
+        let event_types = form.event_types.ok_or("event_types cannot be null")?;
+        let secret = form.secret.ok_or("secret cannot be null")?;
+
+        Ok(NewWebhook {
+            url: parse_url(url)?,
+            event_types: parse_event_types(event_types)?,
+            secret: parse_secret(secret)?,
+        })
+    }
+}
+
+impl UpdateWebhook {
+    /// Validates the fields present in an update form.
+    pub fn parse(form: WebhookFormData) -> Result<UpdateWebhook, String> {
+        Ok(UpdateWebhook {
+            url: form.url.map(parse_url).transpose()?,
+            event_types: form.event_types.map(parse_event_types).transpose()?,
+            secret: form.secret.map(parse_secret).transpose()?,
+        })
+    }
+}
+
+fn parse_url(url: String) -> Result<String, String> {
+    let url = url.trim().to_string();
+    if url.is_empty() {
+        return Err("url cannot be null".into());
+    }
+    match reqwest::Url::parse(&url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(url),
+        _ => Err(format!("{} is not an http or https URL", url)),
+    }
+}
+
+fn parse_event_types(event_types: Vec<String>) -> Result<Vec<String>, String> {
+    if event_types.is_empty() {
+        return Err("event_types cannot be empty".into());
+    }
+    for event_type in &event_types {
+        if event_type != "*" && !WEBHOOK_EVENT_TYPES.contains(&event_type.as_str()) {
+            return Err(format!("{} is not a known event type", event_type));
+        }
+    }
+    Ok(event_types)
+}
+
+fn parse_secret(secret: String) -> Result<String, String> {
+    if secret.trim().is_empty() {
+        Err("secret cannot be null".into())
+    } else {
+        Ok(secret)
+    }
+}
+
+fn split_event_types(event_types: &str) -> Vec<String> {
+    event_types
+        .split(',')
+        .filter(|event_type| !event_type.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_webhooks(pool: &MySqlPool) -> Result<Vec<WebhookRecord>, sqlx::Error> {
+    let _timer = query_timer("get_webhooks");
+    let query = sqlx::query!("select * from webhooks order by id")
+        .in_span(|query| query.fetch_all(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+    let mut webhooks = Vec::<WebhookRecord>::new();
+
+    for q in query {
+        webhooks.push(WebhookRecord {
+            id: q.id,
+            url: q.url,
+            event_types: split_event_types(&q.event_types),
+            secret: q.secret,
+            created_at: Utc.from_utc_datetime(&q.created_at),
+            updated_at: q
+                .updated_at
+                .map(|updated_at| Utc.from_utc_datetime(&updated_at)),
+        });
+    }
+
+    Ok(webhooks)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_webhook_by_id(
+    webhook_id: i32,
+    pool: &MySqlPool,
+) -> Result<WebhookRecord, sqlx::Error> {
+    let _timer = query_timer("get_webhook_by_id");
+    let query = sqlx::query!("select * from webhooks where id = ?", webhook_id)
+        .in_span(|query| query.fetch_one(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+    Ok(WebhookRecord {
+        id: query.id,
+        url: query.url,
+        event_types: split_event_types(&query.event_types),
+        secret: query.secret,
+        created_at: Utc.from_utc_datetime(&query.created_at),
+        updated_at: query
+            .updated_at
+            .map(|updated_at| Utc.from_utc_datetime(&updated_at)),
+    })
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn insert_webhook(
+    pool: &MySqlPool,
+    form: &NewWebhook,
+) -> Result<WebhookRecord, sqlx::Error> {
+    let _timer = query_timer("insert_webhook");
+    // `datetime` columns hold whole seconds; truncate so the returned record
+    // matches what a later read yields.
+    let utc_now = Utc::now().trunc_subsecs(0);
+
+    let query = sqlx::query!(
+        r#"
+        insert into webhooks (url, event_types, secret, created_at, updated_at)
+        values (?, ?, ?, ?, ?)
+        "#,
+        form.url,
+        form.event_types.join(","),
+        form.secret,
+        utc_now,
+        utc_now
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(WebhookRecord {
+        id: query.last_insert_id() as i32,
+        url: form.url.clone(),
+        event_types: form.event_types.clone(),
+        secret: form.secret.clone(),
+        created_at: utc_now,
+        updated_at: Some(utc_now),
+    })
+}
+
+#[tracing::instrument(skip(pool, form))]
+pub async fn update_webhook_by_id(
+    webhook_id: i32,
+    pool: &MySqlPool,
+    form: &UpdateWebhook,
+) -> Result<WebhookRecord, sqlx::Error> {
+    let _timer = query_timer("update_webhook_by_id");
+    let current = get_webhook_by_id(webhook_id, pool).await?;
+    let utc_now = Utc::now().trunc_subsecs(0);
+
+    let webhook = WebhookRecord {
+        url: form.url.clone().unwrap_or(current.url),
+        event_types: form.event_types.clone().unwrap_or(current.event_types),
+        secret: form.secret.clone().unwrap_or(current.secret),
+        updated_at: Some(utc_now),
+        ..current
+    };
+
+    sqlx::query!(
+        "update webhooks set url = ?, event_types = ?, secret = ?, updated_at = ? where id = ?",
+        webhook.url,
+        webhook.event_types.join(","),
+        webhook.secret,
+        utc_now,
+        webhook_id
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(webhook)
+}
+
+/// Deletes a webhook along with its delivery log.
+#[tracing::instrument(skip(pool))]
+pub async fn delete_webhook_by_id(webhook_id: i32, pool: &MySqlPool) -> Result<(), sqlx::Error> {
+    let _timer = query_timer("delete_webhook_by_id");
+    let _record = get_webhook_by_id(webhook_id, pool).await?;
+    sqlx::query!(
+        "delete from webhook_deliveries where webhook_id = ?",
+        webhook_id
+    )
+    .in_span(|query| query.execute(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    sqlx::query!("delete from webhooks where id = ?", webhook_id)
+        .in_span(|query| query.execute(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+    Ok(())
+}
+
+/// Queues a delivery of `stored` to every webhook subscribed to it, returning
+/// how many were queued.
+#[tracing::instrument(skip_all)]
+pub async fn enqueue_deliveries(
+    stored: &StoredEvent,
+    pool: &MySqlPool,
+) -> Result<usize, sqlx::Error> {
+    let _timer = query_timer("enqueue_deliveries");
+    let event_type = stored.event.name();
+    let webhooks = get_webhooks(pool)
+        .await?
+        .into_iter()
+        .filter(|webhook| webhook.wants(event_type))
+        .collect::<Vec<_>>();
+    if webhooks.is_empty() {
+        return Ok(0);
+    }
+
+    let payload = serde_json::to_string(&WebhookPayload {
+        event: event_type.into(),
+        event_id: stored.id,
+        data: stored.event.clone(),
+    })
+    .expect("webhook payloads serialize to JSON");
+    let utc_now = Utc::now().trunc_subsecs(0);
+
+    for webhook in &webhooks {
+        sqlx::query!(
+            r#"
+            insert into webhook_deliveries (webhook_id, event_type, payload, next_attempt_at, created_at)
+            values (?, ?, ?, ?, ?)
+            "#,
+            webhook.id,
+            event_type,
+            payload,
+            utc_now,
+            utc_now
+        )
+        .in_span(|query| query.execute(TimedPool(pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    }
+
+    Ok(webhooks.len())
+}
+
+/// The most recent deliveries of a webhook, newest first.
+#[tracing::instrument(skip(pool))]
+pub async fn get_webhook_deliveries(
+    webhook_id: i32,
+    limit: i64,
+    pool: &MySqlPool,
+) -> Result<Vec<WebhookDeliveryRecord>, sqlx::Error> {
+    let _timer = query_timer("get_webhook_deliveries");
+    let query = sqlx::query!(
+        r#"
+        select id, webhook_id, event_type, status, attempts, response_status, last_error,
+        next_attempt_at, created_at, delivered_at
+        from webhook_deliveries
+        where webhook_id = ?
+        order by id desc
+        limit ?
+        "#,
+        webhook_id,
+        limit
+    )
+    .in_span(|query| query.fetch_all(TimedPool(pool)))
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(query
+        .into_iter()
+        .map(|q| WebhookDeliveryRecord {
+            id: q.id,
+            webhook_id: q.webhook_id,
+            event_type: q.event_type,
+            status: match q.status.as_str() {
+                "succeeded" => DeliveryStatus::Succeeded,
+                "failed" => DeliveryStatus::Failed,
+                _ => DeliveryStatus::Pending,
+            },
+            attempts: q.attempts,
+            response_status: q.response_status,
+            last_error: q.last_error,
+            next_attempt_at: Utc.from_utc_datetime(&q.next_attempt_at),
+            created_at: Utc.from_utc_datetime(&q.created_at),
+            delivered_at: q
+                .delivered_at
+                .map(|delivered_at| Utc.from_utc_datetime(&delivered_at)),
+        })
+        .collect())
+}
@@ -0,0 +1,324 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, SubsecRound, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::MySqlPool;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Notify;
+use tracing::Instrument;
+
+use crate::configuration::WebhookSettings;
+use crate::metrics::{query_timer, TimedPool};
+use crate::services::{enqueue_deliveries, EventBus, Subscription};
+use crate::telemetry::QuerySpan;
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+/// Deliveries claimed from the table per round.
+const DELIVERY_BATCH: i64 = 20;
+
+/// Longest response excerpt or error kept in `last_error`.
+const MAX_ERROR_LEN: usize = 1024;
+
+/// `sha256=` followed by the hex HMAC-SHA256 of `body` keyed with `secret`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// How long to wait after the `attempts`-th failed attempt: the base delay,
+/// doubled for every attempt after the first, capped at the maximum.
+pub fn retry_delay(settings: &WebhookSettings, attempts: i32) -> Duration {
+    let doublings = attempts.saturating_sub(1).clamp(0, 31) as u32;
+    let seconds = settings
+        .retry_base_seconds
+        .saturating_mul(2u64.saturating_pow(doublings))
+        .min(settings.retry_max_seconds);
+    Duration::from_secs(seconds)
+}
+
+/// The result of POSTing one payload.
+#[derive(Debug, PartialEq)]
+pub enum Attempt {
+    Delivered { status: u16 },
+    Rejected { status: u16, body: String },
+    Unreachable { error: String },
+}
+
+/// POSTs a signed payload. Any 2xx answer counts as delivered.
+#[tracing::instrument(skip(client, secret, payload))]
+pub async fn send_webhook(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    event_type: &str,
+    delivery_id: i64,
+    payload: &str,
+) -> Attempt {
+    let response = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header(SIGNATURE_HEADER, sign(secret, payload.as_bytes()))
+        .header(EVENT_HEADER, event_type)
+        .header(DELIVERY_HEADER, delivery_id.to_string())
+        .body(payload.to_string())
+        .send()
+        .await;
+
+    match response {
+        Ok(response) if response.status().is_success() => Attempt::Delivered {
+            status: response.status().as_u16(),
+        },
+        Ok(response) => Attempt::Rejected {
+            status: response.status().as_u16(),
+            body: response.text().await.unwrap_or_default(),
+        },
+        Err(e) => Attempt::Unreachable {
+            error: e.to_string(),
+        },
+    }
+}
+
+/// Queues deliveries for every event on `subscription`, waking the worker
+/// afterwards. Subscribe before serving requests so no event is missed.
+pub async fn dispatch_webhooks(
+    events: Arc<EventBus>,
+    subscription: Subscription,
+    pool: MySqlPool,
+    wake: Arc<Notify>,
+) {
+    let mut cursor = subscription.newest_id;
+    let mut receiver = subscription.receiver;
+    loop {
+        let stored = match receiver.recv().await {
+            Ok(stored) => vec![stored],
+            // Fell behind the channel: pick up again from the buffer.
+            Err(RecvError::Lagged(_)) => {
+                let subscription = events.subscribe(Some(cursor));
+                receiver = subscription.receiver;
+                subscription.missed
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        for stored in stored {
+            cursor = stored.id;
+            let span = tracing::info_span!(
+                "Queueing webhook deliveries",
+                event_id = stored.id,
+                request_id = tracing::field::Empty
+            );
+            if let Some(request_id) = &stored.request_id {
+                span.record("request_id", tracing::field::display(request_id));
+            }
+            match enqueue_deliveries(&stored, &pool).instrument(span).await {
+                Ok(0) => {}
+                Ok(_) => wake.notify_one(),
+                Err(e) => tracing::error!("Failed to queue webhook deliveries: {:?}", e),
+            }
+        }
+    }
+}
+
+/// Sends due deliveries, retrying failures with exponential backoff.
+pub struct WebhookWorker {
+    pool: MySqlPool,
+    client: reqwest::Client,
+    settings: WebhookSettings,
+    wake: Arc<Notify>,
+}
+
+impl WebhookWorker {
+    pub fn new(pool: MySqlPool, settings: WebhookSettings) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(settings.timeout_seconds))
+            .build()
+            .expect("Failed to build the webhook HTTP client");
+        Self {
+            pool,
+            client,
+            settings,
+            wake: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Notified when new deliveries are queued, so they don't wait for the
+    /// next poll.
+    pub fn waker(&self) -> Arc<Notify> {
+        self.wake.clone()
+    }
+
+    pub async fn run(self) {
+        let poll_interval = Duration::from_millis(self.settings.poll_interval_milliseconds);
+        loop {
+            if let Err(e) = self.deliver_due().await {
+                tracing::error!("Failed to deliver webhooks: {:?}", e);
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(poll_interval) => {}
+                _ = self.wake.notified() => {}
+            }
+        }
+    }
+
+    /// Attempts every due delivery once, returning how many were attempted.
+    #[tracing::instrument(skip_all)]
+    pub async fn deliver_due(&self) -> Result<usize, sqlx::Error> {
+        let _timer = query_timer("WebhookWorker::deliver_due");
+        let now = Utc::now();
+        let due = sqlx::query!(
+            r#"
+            select d.id, d.event_type, d.payload, d.attempts, d.next_attempt_at, w.url, w.secret
+            from webhook_deliveries d
+            join webhooks w on w.id = d.webhook_id
+            where d.status = 'pending' and d.next_attempt_at <= ?
+            order by d.next_attempt_at
+            limit ?
+            "#,
+            now,
+            DELIVERY_BATCH
+        )
+        .in_span(|query| query.fetch_all(TimedPool(&self.pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+        let mut attempted = 0;
+        for delivery in due {
+            // Push the delivery past the request timeout first, so another
+            // instance polling the same table leaves it alone meanwhile.
+            let lease = Utc::now().trunc_subsecs(0)
+                + chrono::Duration::seconds(2 * self.settings.timeout_seconds as i64 + 1);
+            let claimed = sqlx::query!(
+                r#"
+                update webhook_deliveries set next_attempt_at = ?
+                where id = ? and status = 'pending' and next_attempt_at = ?
+                "#,
+                lease,
+                delivery.id,
+                delivery.next_attempt_at
+            )
+            .in_span(|query| query.execute(TimedPool(&self.pool)))
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to execute query: {:?}", e);
+                e
+            })?;
+            if claimed.rows_affected() != 1 {
+                continue;
+            }
+
+            let attempt = send_webhook(
+                &self.client,
+                &delivery.url,
+                &delivery.secret,
+                &delivery.event_type,
+                delivery.id,
+                &delivery.payload,
+            )
+            .await;
+            self.record(delivery.id, delivery.attempts + 1, attempt)
+                .await?;
+            attempted += 1;
+        }
+
+        Ok(attempted)
+    }
+
+    #[tracing::instrument(skip(self, attempt))]
+    async fn record(
+        &self,
+        delivery_id: i64,
+        attempts: i32,
+        attempt: Attempt,
+    ) -> Result<(), sqlx::Error> {
+        let _timer = query_timer("WebhookWorker::record");
+        let utc_now = Utc::now().trunc_subsecs(0);
+        let outcome = match attempt {
+            Attempt::Delivered { status } => Outcome {
+                status: "succeeded",
+                response_status: Some(status.into()),
+                last_error: None,
+                next_attempt_at: utc_now,
+                delivered_at: Some(utc_now),
+            },
+            Attempt::Rejected { status, body } => {
+                let error = format!("HTTP {}: {}", status, body);
+                self.failure(attempts, Some(status.into()), error, utc_now)
+            }
+            Attempt::Unreachable { error } => self.failure(attempts, None, error, utc_now),
+        };
+        if let Some(error) = &outcome.last_error {
+            tracing::warn!(
+                "Webhook delivery {} attempt {} failed: {}",
+                delivery_id,
+                attempts,
+                error
+            );
+        }
+
+        sqlx::query!(
+            r#"
+            update webhook_deliveries
+            set status = ?, attempts = ?, response_status = ?, last_error = ?,
+            next_attempt_at = ?, delivered_at = ?
+            where id = ?
+            "#,
+            outcome.status,
+            attempts,
+            outcome.response_status,
+            outcome.last_error,
+            outcome.next_attempt_at,
+            outcome.delivered_at,
+            delivery_id
+        )
+        .in_span(|query| query.execute(TimedPool(&self.pool)))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+        Ok(())
+    }
+
+    fn failure(
+        &self,
+        attempts: i32,
+        response_status: Option<i32>,
+        error: String,
+        utc_now: DateTime<Utc>,
+    ) -> Outcome {
+        let delay = retry_delay(&self.settings, attempts);
+        Outcome {
+            status: if attempts >= self.settings.max_attempts {
+                "failed"
+            } else {
+                "pending"
+            },
+            response_status,
+            last_error: Some(error.chars().take(MAX_ERROR_LEN).collect()),
+            next_attempt_at: utc_now
+                + chrono::Duration::from_std(delay)
+                    .expect("retry delays fit in a chrono::Duration"),
+            delivered_at: None,
+        }
+    }
+}
+
+/// What an attempt leaves in the delivery's row.
+struct Outcome {
+    status: &'static str,
+    response_status: Option<i32>,
+    last_error: Option<String>,
+    next_attempt_at: DateTime<Utc>,
+    delivered_at: Option<DateTime<Utc>>,
+}
@@ -1,37 +1,299 @@
 use std::net::TcpListener;
+use std::sync::Arc;
 
-use actix_web::dev::Server;
+use actix_web::dev::{Server, Service};
 use actix_web::{web, App, HttpServer};
+use secrecy::ExposeSecret;
+use sqlx::mysql::MySqlPoolOptions;
 use sqlx::MySqlPool;
 
-use crate::routes::health_check;
-// use crate::routes::subscribe;
+use crate::common::{RequestIdHeader, TimezoneConfig};
+use crate::configuration::{
+    default_base_url, DatabaseSettings, DigestSettings, EmailSettings, ReminderSettings, Settings,
+    WebhookSettings,
+};
+use crate::metrics::record_request;
+use crate::routes::search_list;
+use crate::routes::v2;
+use crate::routes::{activity_channel, digest_opt_out, digest_opt_out_confirm, event_stream};
+use crate::routes::{
+    activity_create, activity_destroy, activity_detail, activity_import_markdown, activity_list,
+    activity_markdown_export, activity_update,
+};
 use crate::routes::{
-    activity_create, activity_destroy, activity_detail, activity_list, activity_update,
+    caldav_routes, calendar_export, calendar_feed_create, calendar_feed_destroy, calendar_import,
 };
+use crate::routes::{export_data, import_data, import_todoist, import_trello, IMPORT_JSON_LIMIT};
+use crate::routes::{graphiql, graphql_execute};
+use crate::routes::{health_check, health_live, health_ready, prometheus_metrics};
+use crate::routes::{openapi_json, ApiDoc};
+use crate::routes::{reminder_create, reminder_destroy, reminder_list};
+use crate::routes::{subscribe, subscription_confirm};
 use crate::routes::{todo_create, todo_destroy, todo_detail, todo_list, todo_update};
+use crate::routes::{todo_import_csv, todo_list_csv, todo_txt_export, todo_txt_import};
+use crate::routes::{
+    webhook_create, webhook_deliveries, webhook_destroy, webhook_detail, webhook_list,
+    webhook_update,
+};
+use crate::services::{
+    check_schema_version, dispatch_webhooks, graphql_schema, run_migrations, wait_for_database,
+    DigestJob, EmailSender, EventBus, LogEmailSender, Mailer, Notifiers, Presence,
+    ReminderScheduler, WebhookWorker, MIGRATION_LOCK_TIMEOUT,
+};
+use crate::telemetry::RequestIdRootSpanBuilder;
 use tracing_actix_web::TracingLogger;
+use utoipa::OpenApi;
+use utoipa_redoc::{Redoc, Servable};
+
+/// Settings for the background work started next to the HTTP server.
+#[derive(Clone, Debug)]
+pub struct WorkerSettings {
+    pub webhooks: WebhookSettings,
+    pub reminders: ReminderSettings,
+    pub email: Option<EmailSettings>,
+    pub digest: DigestSettings,
+    pub base_url: String,
+    /// Serves GraphiQL at `GET /graphql`.
+    pub graphiql: bool,
+}
+
+impl Default for WorkerSettings {
+    fn default() -> Self {
+        Self {
+            webhooks: WebhookSettings::default(),
+            reminders: ReminderSettings::default(),
+            email: None,
+            digest: DigestSettings::default(),
+            base_url: default_base_url(),
+            graphiql: false,
+        }
+    }
+}
+
+impl From<&Settings> for WorkerSettings {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            webhooks: settings.webhooks.clone(),
+            reminders: settings.reminders.clone(),
+            email: settings.email.clone(),
+            digest: settings.digest.clone(),
+            base_url: settings.application.base_url.clone(),
+            graphiql: settings.application.graphiql,
+        }
+    }
+}
+
+/// A pool that connects on first use, so commands start even while MySQL is
+/// still coming up.
+pub fn get_connection_pool(settings: &DatabaseSettings) -> MySqlPool {
+    MySqlPoolOptions::new()
+        .connect_timeout(std::time::Duration::from_secs(2))
+        .connect_lazy(settings.connection_string().expose_secret())
+        .expect("Failed to connect to MySQL.")
+}
+
+/// How long `prepare_database` waits for MySQL to come up.
+pub const DATABASE_STARTUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Waits for MySQL, then migrates it or, with `migrate_on_startup` off,
+/// checks that its schema isn't newer than this build.
+pub async fn prepare_database(pool: &MySqlPool, settings: &DatabaseSettings) -> Result<(), String> {
+    wait_for_database(pool, DATABASE_STARTUP_TIMEOUT).await?;
+    if settings.migrate_on_startup {
+        let applied = run_migrations(pool, MIGRATION_LOCK_TIMEOUT).await?;
+        tracing::info!("Applied {} pending migrations", applied);
+        Ok(())
+    } else {
+        check_schema_version(pool).await
+    }
+}
 
 pub fn run(listener: TcpListener, pool: MySqlPool) -> Result<Server, std::io::Error> {
+    run_with_workers(listener, pool, WorkerSettings::default())
+}
+
+/// The public address of this service, for links in emails.
+pub struct ApplicationBaseUrl(pub String);
+
+pub fn run_with_workers(
+    listener: TcpListener,
+    pool: MySqlPool,
+    workers: WorkerSettings,
+) -> Result<Server, std::io::Error> {
+    start(listener, pool, workers, None)
+}
+
+/// Like `run_with_workers`, but handlers send email through `email_sender`
+/// instead of the configured mailer.
+pub fn run_with_email_sender(
+    listener: TcpListener,
+    pool: MySqlPool,
+    workers: WorkerSettings,
+    email_sender: Arc<dyn EmailSender>,
+) -> Result<Server, std::io::Error> {
+    start(listener, pool, workers, Some(email_sender))
+}
+
+fn start(
+    listener: TcpListener,
+    pool: MySqlPool,
+    workers: WorkerSettings,
+    email_sender: Option<Arc<dyn EmailSender>>,
+) -> Result<Server, std::io::Error> {
+    let events = web::Data::new(EventBus::default());
+
+    let webhook_worker = WebhookWorker::new(pool.clone(), workers.webhooks);
+    tokio::spawn(dispatch_webhooks(
+        events.clone().into_inner(),
+        events.subscribe(None),
+        pool.clone(),
+        webhook_worker.waker(),
+    ));
+    tokio::spawn(webhook_worker.run());
+
+    let mailer = workers
+        .email
+        .as_ref()
+        .and_then(|email| match Mailer::from_settings(email) {
+            Ok(mailer) => Some(mailer),
+            Err(e) => {
+                tracing::error!("Failed to set up email, none will be sent: {}", e);
+                None
+            }
+        });
+    let email_sender = web::Data::from(email_sender.unwrap_or_else(|| match &mailer {
+        Some(mailer) => Arc::new(mailer.clone()),
+        None => Arc::new(LogEmailSender),
+    }));
+    let base_url = web::Data::new(ApplicationBaseUrl(workers.base_url.clone()));
+    let notifiers = Notifiers::from_settings(&workers.reminders, mailer.clone());
+    tokio::spawn(ReminderScheduler::new(pool.clone(), notifiers, workers.reminders).run());
+    if let (Some(mailer), true) = (mailer, workers.digest.enabled) {
+        tokio::spawn(DigestJob::new(pool.clone(), mailer, workers.digest, workers.base_url).run());
+    }
+
+    let graphiql_enabled = workers.graphiql;
+    let schema = web::Data::new(graphql_schema(pool.clone(), events.clone().into_inner()));
     let pool = web::Data::new(pool);
+    let presence = web::Data::new(Presence::default());
     let server = HttpServer::new(move || {
         App::new()
-            .wrap(TracingLogger::default())
+            .wrap(TracingLogger::<RequestIdRootSpanBuilder>::new())
+            .wrap_fn(|req, srv| {
+                let started = std::time::Instant::now();
+                let method = req.method().to_string();
+                let response = srv.call(req);
+                async move {
+                    let response = response.await;
+                    match &response {
+                        Ok(res) => record_request(
+                            &method,
+                            res.request().match_pattern().as_deref(),
+                            res.status(),
+                            started.elapsed(),
+                        ),
+                        Err(e) => record_request(
+                            &method,
+                            None,
+                            e.as_response_error().status_code(),
+                            started.elapsed(),
+                        ),
+                    }
+                    response
+                }
+            })
+            .wrap(RequestIdHeader)
             .route("/health_check", web::get().to(health_check))
-            .service(activity_list)
-            .service(activity_detail)
-            .service(activity_create)
-            .service(activity_update)
-            .service(activity_destroy)
-            .service(todo_list)
-            .service(todo_create)
-            .service(todo_detail)
-            .service(todo_update)
-            .service(todo_destroy)
+            .service(health_live)
+            .service(health_ready)
+            .service(prometheus_metrics)
+            .service(openapi_json)
+            .service(Redoc::with_url("/docs", ApiDoc::openapi()))
+            .service(web::scope("/v1").configure(v1_routes))
+            .service(web::scope("/v2").configure(v2_routes))
+            .configure(caldav_routes)
+            .service(graphql_execute)
+            .configure(|cfg| {
+                if graphiql_enabled {
+                    cfg.service(graphiql);
+                }
+            })
+            // Unversioned paths keep serving v1 for existing clients.
+            .configure(v1_routes)
             .app_data(pool.clone())
+            .app_data(events.clone())
+            .app_data(presence.clone())
+            .app_data(email_sender.clone())
+            .app_data(base_url.clone())
+            .app_data(schema.clone())
     })
     .listen(listener)?
     .run();
 
     Ok(server)
 }
+
+fn v1_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(activity_list)
+        // Before `activity_detail`, whose `{activity_id}` would match `1.md`.
+        .service(activity_markdown_export)
+        .service(activity_import_markdown)
+        .service(activity_detail)
+        .service(activity_create)
+        .service(activity_update)
+        .service(activity_destroy)
+        .service(todo_list)
+        .service(todo_list_csv)
+        .service(todo_import_csv)
+        .service(todo_txt_export)
+        .service(todo_txt_import)
+        .service(calendar_export)
+        .service(calendar_import)
+        .service(calendar_feed_create)
+        .service(calendar_feed_destroy)
+        .service(todo_create)
+        .service(todo_detail)
+        .service(todo_update)
+        .service(todo_destroy)
+        .service(search_list)
+        .service(event_stream)
+        .service(activity_channel)
+        .service(webhook_list)
+        .service(webhook_create)
+        .service(webhook_detail)
+        .service(webhook_update)
+        .service(webhook_destroy)
+        .service(webhook_deliveries)
+        .service(reminder_list)
+        .service(reminder_create)
+        .service(reminder_destroy)
+        .service(digest_opt_out_confirm)
+        .service(digest_opt_out)
+        .service(subscribe)
+        .service(subscription_confirm)
+        .service(export_data)
+        .service(
+            web::scope("/import")
+                .app_data(web::JsonConfig::default().limit(IMPORT_JSON_LIMIT))
+                .service(import_data)
+                .service(import_trello)
+                .service(import_todoist),
+        );
+}
+
+fn v2_routes(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::JsonConfig::default().error_handler(v2::json_error_handler))
+        .app_data(web::PathConfig::default().error_handler(v2::path_error_handler))
+        .app_data(web::QueryConfig::default().error_handler(v2::query_error_handler))
+        .app_data(TimezoneConfig::default().error_handler(v2::timezone_error_handler))
+        .service(v2::activity_list)
+        .service(v2::activity_detail)
+        .service(v2::activity_create)
+        .service(v2::activity_update)
+        .service(v2::activity_destroy)
+        .service(v2::todo_list)
+        .service(v2::todo_create)
+        .service(v2::todo_detail)
+        .service(v2::todo_update)
+        .service(v2::todo_destroy);
+}
@@ -1,13 +1,44 @@
-use tracing::{subscriber::set_global_default, Subscriber};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::HeaderMap;
+use actix_web::{Error, HttpMessage};
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use sqlx::{Execute, MySql};
+use tracing::instrument::Instrumented;
+use tracing::{subscriber::set_global_default, Instrument, Span, Subscriber};
+use tracing_actix_web::{DefaultRootSpanBuilder, RootSpanBuilder};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, EnvFilter, Registry};
 
+use crate::common::RequestId;
+use crate::configuration::TelemetrySettings;
+
 pub fn get_subscriber<Sink>(
     name: String,
     env_filter: String,
     sink: Sink,
 ) -> impl Subscriber + Send + Sync
+where
+    Sink: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    get_subscriber_with_tracer(name, env_filter, sink, None)
+}
+
+/// Like `get_subscriber`, additionally exporting spans through `tracer`
+/// when there is one.
+pub fn get_subscriber_with_tracer<Sink>(
+    name: String,
+    env_filter: String,
+    sink: Sink,
+    tracer: Option<Tracer>,
+) -> impl Subscriber + Send + Sync
 where
     Sink: for<'a> MakeWriter<'a> + Send + Sync + 'static,
 {
@@ -16,11 +47,131 @@ where
     let formatting_layer = BunyanFormattingLayer::new(name, sink);
     Registry::default()
         .with(env_filter)
+        .with(tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)))
         .with(JsonStorageLayer)
         .with(formatting_layer)
 }
 
+/// Sets up OTLP/gRPC export to `settings.otlp_endpoint` and W3C
+/// `traceparent` propagation. Returns `None` when no endpoint is set.
+/// Must be called from within a Tokio runtime.
+pub fn get_otlp_tracer(settings: &TelemetrySettings) -> Result<Option<Tracer>, String> {
+    let endpoint = match &settings.otlp_endpoint {
+        Some(endpoint) => endpoint,
+        None => return Ok(None),
+    };
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| format!("Failed to build the OTLP exporter: {}", e))?;
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            settings.service_name.clone(),
+        )]))
+        .build();
+    let tracer = provider.tracer(settings.service_name.clone());
+    global::set_tracer_provider(provider);
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(Some(tracer))
+}
+
+/// Flushes spans not yet exported. Call before the process exits.
+pub fn shutdown_tracer() {
+    global::shutdown_tracer_provider();
+}
+
+/// Runs sqlx queries in a `db.query` span each, so every statement shows up
+/// under the service function that issued it.
+pub trait QuerySpan<'q>: Execute<'q, MySql> {
+    /// Instruments the future `run` makes from the query, e.g.
+    /// `sqlx::query!(..).in_span(|query| query.fetch_one(pool))`.
+    fn in_span<F, Fut>(self, run: F) -> Instrumented<Fut>
+    where
+        F: FnOnce(Self) -> Fut,
+    {
+        // Multi-line statements read better flattened in a trace viewer.
+        let statement = self.sql().split_whitespace().collect::<Vec<_>>().join(" ");
+        let span = tracing::info_span!(
+            "db.query",
+            db.system = "mysql",
+            db.statement = %statement,
+        );
+        run(self).instrument(span)
+    }
+}
+
+impl<'q, Q: Execute<'q, MySql>> QuerySpan<'q> for Q {}
+
 pub fn init_subscriber(subscriber: impl Subscriber + Send + Sync) {
     LogTracer::init().expect("Failed to set logger");
     set_global_default(subscriber).expect("Failed to set subscriber");
 }
+
+/// `TracingLogger`'s root span, with `request_id` set to the request's
+/// [`RequestId`] rather than a fresh UUID, so the id a caller sent in
+/// `X-Request-Id` shows up on every log line of the request.
+pub struct RequestIdRootSpanBuilder;
+
+impl RootSpanBuilder for RequestIdRootSpanBuilder {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        let request_id = request
+            .extensions()
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_else(RequestId::generate);
+        let http_route = request.match_pattern().unwrap_or_else(|| "default".into());
+        let user_agent = request
+            .headers()
+            .get("User-Agent")
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or("");
+        let connection_info = request.connection_info();
+        let span = tracing::info_span!(
+            "HTTP request",
+            http.method = %request.method(),
+            http.route = %http_route,
+            http.scheme = %connection_info.scheme(),
+            http.host = %connection_info.host(),
+            http.client_ip = %connection_info.realip_remote_addr().unwrap_or(""),
+            http.user_agent = %user_agent,
+            http.target = %request.uri().path_and_query().map(|p| p.as_str()).unwrap_or(""),
+            http.status_code = tracing::field::Empty,
+            otel.status_code = tracing::field::Empty,
+            request_id = %request_id,
+            trace_id = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.details = tracing::field::Empty,
+        );
+        drop(connection_info);
+
+        // Continue the caller's trace when it sent a `traceparent` header.
+        let parent = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(request.headers()))
+        });
+        span.set_parent(parent);
+        let trace_id = span.context().span().span_context().trace_id();
+        if trace_id != opentelemetry::trace::TraceId::INVALID {
+            span.record("trace_id", tracing::field::display(trace_id));
+        }
+        span
+    }
+
+    fn on_request_end<B>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
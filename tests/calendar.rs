@@ -0,0 +1,323 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+use zero2prod::common::Timezone;
+use zero2prod::services::{
+    calendar_feed, import_calendar, parse_calendar, render_calendar, CalendarImportError,
+    CalendarObject, CalendarTodo, ChangeAction, EventBus, ImportLineError, TodoRecord,
+};
+
+use common::{spawn_app, test_database};
+
+fn jakarta() -> Timezone {
+    Timezone::parse("Asia/Jakarta").unwrap()
+}
+
+fn line_errors(text: &str) -> Vec<ImportLineError> {
+    match parse_calendar(text, &jakarta()) {
+        Err(CalendarImportError::Lines(errors, _)) => errors,
+        other => panic!("expected invalid lines, got {:?}", other),
+    }
+}
+
+#[test]
+fn subscribe_urls_carry_the_token() {
+    let feed = calendar_feed("https://todo.example.com/", 7, "abc");
+    assert_eq!(
+        feed.url,
+        "https://todo.example.com/activity-groups/7/calendar.ics?token=abc"
+    );
+    assert_eq!(
+        feed.webcal_url,
+        "webcal://todo.example.com/activity-groups/7/calendar.ics?token=abc"
+    );
+}
+
+#[test]
+fn todos_render_as_vtodos() {
+    let created_at = Utc.with_ymd_and_hms(2026, 10, 1, 8, 0, 0).unwrap();
+    let todos = [
+        TodoRecord {
+            id: 1,
+            title: "Buy milk, eggs; bread".into(),
+            activity_group_id: 1,
+            is_active: true,
+            priority: "high".into(),
+            due_at: Some(Utc.with_ymd_and_hms(2026, 10, 20, 16, 59, 59).unwrap()),
+            created_at,
+            updated_at: None,
+        },
+        TodoRecord {
+            id: 2,
+            title: "Pay rent ".repeat(10),
+            activity_group_id: 1,
+            is_active: false,
+            priority: "very-low".into(),
+            due_at: None,
+            created_at,
+            updated_at: Some(Utc.with_ymd_and_hms(2026, 10, 2, 9, 30, 0).unwrap()),
+        },
+    ];
+    let now = Utc.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap();
+
+    let objects: Vec<CalendarObject> = todos
+        .iter()
+        .map(|todo| CalendarObject::new(todo.clone(), "todo.example.com"))
+        .collect();
+
+    let calendar = render_calendar("Groceries", &objects, now);
+    assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+    assert!(calendar
+        .split("\r\n")
+        .all(|line| line.len() <= 75 && !line.contains('\n')));
+    let unfolded = calendar.replace("\r\n ", "");
+    for line in [
+        "X-WR-CALNAME:Groceries",
+        "UID:todo-1@todo.example.com",
+        "DTSTAMP:20261019T120000Z",
+        "SUMMARY:Buy milk\\, eggs\\; bread",
+        "PRIORITY:3",
+        "STATUS:NEEDS-ACTION",
+        "CREATED:20261001T080000Z",
+        "DUE:20261020T165959Z",
+        "PRIORITY:9",
+        "STATUS:COMPLETED",
+        "COMPLETED:20261002T093000Z",
+        "LAST-MODIFIED:20261002T093000Z",
+    ] {
+        assert!(
+            unfolded.contains(&format!("\r\n{}\r\n", line)),
+            "missing {}",
+            line
+        );
+    }
+    assert!(unfolded.contains(&format!("SUMMARY:{}\r\n", "Pay rent ".repeat(10))));
+
+    let parsed = parse_calendar(&calendar, &jakarta()).unwrap();
+    assert_eq!(parsed.len(), 2);
+    for (todo, entry) in todos.iter().zip(parsed) {
+        assert_eq!(entry.title, todo.title.trim());
+        assert_eq!(entry.priority, todo.priority);
+        assert_eq!(entry.is_active, todo.is_active);
+        assert_eq!(entry.created_at, Some(todo.created_at));
+        assert_eq!(entry.updated_at, todo.updated_at);
+        assert_eq!(entry.due_at, todo.due_at);
+    }
+}
+
+#[test]
+fn vtodos_map_onto_todos() {
+    let calendar = "BEGIN:VCALENDAR\r\n\
+        VERSION:2.0\r\n\
+        BEGIN:VEVENT\r\n\
+        SUMMARY:Not a todo\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VTODO\r\n\
+        UID:abc@example.com\r\n\
+        SUMMARY:Fix the\r\n  tap\r\n\
+        PRIORITY:6\r\n\
+        DUE;VALUE=DATE:20261020\r\n\
+        CREATED;TZID=Europe/Paris:20261001T100000\r\n\
+        BEGIN:VALARM\r\n\
+        SUMMARY:Alarm\r\n\
+        END:VALARM\r\n\
+        END:VTODO\r\n\
+        BEGIN:VTODO\r\n\
+        SUMMARY:Paint the fence\r\n\
+        STATUS:COMPLETED\r\n\
+        LAST-MODIFIED:20261002T080000\r\n\
+        END:VTODO\r\n\
+        END:VCALENDAR\r\n";
+
+    assert_eq!(
+        parse_calendar(calendar, &jakarta()).unwrap(),
+        [
+            CalendarTodo {
+                uid: Some("abc@example.com".into()),
+                title: "Fix the tap".into(),
+                priority: "low".into(),
+                is_active: true,
+                created_at: Some(Utc.with_ymd_and_hms(2026, 10, 1, 8, 0, 0).unwrap()),
+                updated_at: None,
+                due_at: Some(Utc.with_ymd_and_hms(2026, 10, 20, 16, 59, 59).unwrap()),
+            },
+            CalendarTodo {
+                uid: None,
+                title: "Paint the fence".into(),
+                priority: "very-high".into(),
+                is_active: false,
+                created_at: None,
+                updated_at: Some(Utc.with_ymd_and_hms(2026, 10, 2, 1, 0, 0).unwrap()),
+                due_at: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn invalid_vtodos_are_reported_by_the_line_they_begin_on() {
+    assert_eq!(
+        line_errors("SUMMARY:Buy milk\n"),
+        [ImportLineError {
+            line: 1,
+            message: "the body is not an iCalendar object starting with BEGIN:VCALENDAR".into(),
+        }]
+    );
+
+    let errors = line_errors(
+        "BEGIN:VCALENDAR\n\
+        BEGIN:VTODO\nSUMMARY:Fine\nEND:VTODO\n\
+        BEGIN:VTODO\nSUMMARY:Someday\nPRIORITY:high\nEND:VTODO\n\
+        BEGIN:VTODO\nSUMMARY:Pay rent\nDUE;TZID=Mars/Olympus:20261020T090000\nEND:VTODO\n\
+        BEGIN:VTODO\nDUE:tomorrow\nEND:VTODO\n\
+        BEGIN:VTODO\nPRIORITY:1\nEND:VTODO\n\
+        BEGIN:VTODO\nSUMMARY:Unfinished\n",
+    );
+    assert_eq!(
+        errors,
+        [
+            ImportLineError {
+                line: 5,
+                message: "PRIORITY high is not a number from 0 to 9".into(),
+            },
+            ImportLineError {
+                line: 9,
+                message: "DUE has an unknown TZID \"Mars/Olympus\"".into(),
+            },
+            ImportLineError {
+                line: 13,
+                message: "DUE \"tomorrow\" is not a date or date-time".into(),
+            },
+            ImportLineError {
+                line: 16,
+                message: "the VTODO has no SUMMARY".into(),
+            },
+            ImportLineError {
+                line: 19,
+                message: "the VTODO has no END:VTODO".into(),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn calendar_feeds_need_their_token_and_imports_add_todos() {
+    let address = spawn_app(test_database().await);
+    let client = reqwest::Client::new();
+
+    let group: Value = client
+        .post(format!("{}/activity-groups", address))
+        .json(&json!({ "title": "Home repairs" }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    let calendar_url = format!(
+        "{}/activity-groups/{}/calendar.ics",
+        address, group["data"]["id"]
+    );
+
+    let response = client
+        .post(&calendar_url)
+        .body("BEGIN:VCALENDAR\nBEGIN:VTODO\nPRIORITY:1\nEND:VTODO\nEND:VCALENDAR\n")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["message"], "1 VTODO is invalid; nothing was imported");
+
+    let response = client
+        .post(&calendar_url)
+        .body(
+            "BEGIN:VCALENDAR\nBEGIN:VTODO\nSUMMARY:Fix the tap\nPRIORITY:2\n\
+            DUE;VALUE=DATE:20261020\nEND:VTODO\nEND:VCALENDAR\n",
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(201, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["data"][0]["title"], "Fix the tap");
+    assert_eq!(body["data"][0]["priority"], "very-high");
+
+    let response = client
+        .get(format!("{}?token=guess", calendar_url))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(404, response.status().as_u16());
+
+    let feed_url = format!(
+        "{}/activity-groups/{}/calendar-feed",
+        address, group["data"]["id"]
+    );
+    let feed: Value = client
+        .post(&feed_url)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    let url = feed["data"]["url"].as_str().unwrap();
+    let token = url.split("token=").nth(1).unwrap();
+
+    let response = client
+        .get(format!("{}?token={}", calendar_url, token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/calendar; charset=utf-8"
+    );
+    let calendar = response.text().await.unwrap();
+    assert!(calendar.contains("\r\nSUMMARY:Fix the tap\r\n"));
+    assert!(calendar.contains("\r\nDUE:20261020T235959Z\r\n"));
+
+    let response = client
+        .delete(&feed_url)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let response = client
+        .get(format!("{}?token={}", calendar_url, token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn imported_vtodos_are_published_once_committed() {
+    let pool = test_database().await;
+    let group_id = sqlx::query("insert into activities (title, created_at) values ('Home', now())")
+        .execute(&pool)
+        .await
+        .unwrap()
+        .last_insert_id() as i32;
+    let events = EventBus::default();
+    let mut subscription = events.subscribe(None);
+    let tz = jakarta();
+
+    let invalid = "BEGIN:VCALENDAR\nBEGIN:VTODO\nPRIORITY:1\nEND:VTODO\nEND:VCALENDAR\n";
+    assert!(import_calendar(invalid, group_id, &tz, &pool, &events)
+        .await
+        .is_err());
+    assert!(subscription.receiver.try_recv().is_err());
+
+    let calendar = "BEGIN:VCALENDAR\nBEGIN:VTODO\nSUMMARY:Fix the tap\nEND:VTODO\nEND:VCALENDAR\n";
+    let todos = import_calendar(calendar, group_id, &tz, &pool, &events)
+        .await
+        .unwrap();
+    let stored = subscription.receiver.try_recv().unwrap();
+    assert_eq!(stored.event.action, ChangeAction::Created);
+    assert_eq!(stored.event.id, todos[0].id);
+    assert_eq!(stored.event.todo.as_ref().unwrap().title, "Fix the tap");
+}
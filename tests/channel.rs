@@ -0,0 +1,111 @@
+use std::sync::Arc;
+use zero2prod::services::{Member, NewTodo, Presence, PresenceChange};
+use zero2prod_types::{ChannelCommand, ChannelMessage, TodoFormData};
+
+fn member(name: &str) -> Member {
+    Member {
+        connection_id: format!("{}-connection", name),
+        name: name.into(),
+    }
+}
+
+#[test]
+fn joining_lists_everyone_connected_to_the_group() {
+    let presence = Arc::new(Presence::default());
+    let _alice = presence.join(1, member("alice"));
+    let _carol = presence.join(2, member("carol"));
+
+    let bob = presence.join(1, member("bob"));
+
+    assert_eq!(bob.members, vec![member("alice"), member("bob")]);
+}
+
+#[test]
+fn members_are_told_when_others_join_and_leave() {
+    let presence = Arc::new(Presence::default());
+    let mut alice = presence.join(1, member("alice"));
+
+    let bob = presence.join(1, member("bob"));
+    drop(bob.guard);
+
+    assert_eq!(
+        alice.receiver.try_recv().unwrap(),
+        PresenceChange::Joined(member("bob"))
+    );
+    assert_eq!(
+        alice.receiver.try_recv().unwrap(),
+        PresenceChange::Left(member("bob"))
+    );
+    assert_eq!(presence.members(1), vec![member("alice")]);
+}
+
+#[test]
+fn groups_are_forgotten_once_empty() {
+    let presence = Arc::new(Presence::default());
+    let alice = presence.join(1, member("alice"));
+
+    drop(alice.guard);
+
+    assert!(presence.members(1).is_empty());
+}
+
+#[test]
+fn commands_use_the_todo_form_defaults() {
+    let command: ChannelCommand =
+        serde_json::from_str(r#"{"type": "create", "request_id": "r1", "title": "Milk"}"#).unwrap();
+
+    match command {
+        ChannelCommand::Create { request_id, todo } => {
+            assert_eq!(request_id.as_deref(), Some("r1"));
+            assert_eq!(todo.title, "Milk");
+            assert_eq!(todo.priority, "very-high");
+            assert!(todo.is_active);
+        }
+        other => panic!("expected a create command, got {:?}", other),
+    }
+
+    let command: ChannelCommand = serde_json::from_str(r#"{"type": "toggle", "id": 3}"#).unwrap();
+    assert!(matches!(
+        command,
+        ChannelCommand::Toggle {
+            request_id: None,
+            id: 3
+        }
+    ));
+}
+
+#[test]
+fn commands_are_validated_like_todo_create() {
+    let form = |title: &str, activity_group_id| TodoFormData {
+        title: title.into(),
+        activity_group_id,
+        priority: "high".into(),
+        is_active: true,
+        due_at: None,
+    };
+
+    assert_eq!(
+        NewTodo::parse(form(" ", Some(1))).err().as_deref(),
+        Some("title cannot be null")
+    );
+    assert_eq!(
+        NewTodo::parse(form("Milk", None)).err().as_deref(),
+        Some("activity_group_id cannot be null")
+    );
+    assert!(NewTodo::parse(form("Milk", Some(1))).is_ok());
+}
+
+#[test]
+fn server_messages_are_tagged_by_type() {
+    let message = ChannelMessage::Joined {
+        member: member("alice"),
+    };
+
+    assert_eq!(
+        serde_json::to_value(&message).unwrap(),
+        serde_json::json!({
+            "type": "joined",
+            "member": { "connection_id": "alice-connection", "name": "alice" }
+        })
+    );
+}
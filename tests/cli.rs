@@ -0,0 +1,108 @@
+mod common;
+
+use clap::Parser;
+use zero2prod::cli::{check_settings, Cli, Command};
+use zero2prod::configuration::get_configuration;
+use zero2prod::services::{export_all, purge_deleted, seed_sample_data, EventBus, ImportMode};
+
+use common::test_database;
+
+#[test]
+fn no_subcommand_means_serve() {
+    let cli = Cli::try_parse_from(["zero2prod"]).unwrap();
+    assert_eq!(cli.command, None);
+
+    let cli = Cli::try_parse_from(["zero2prod", "serve"]).unwrap();
+    assert_eq!(cli.command, Some(Command::Serve));
+    assert!(!Command::Serve.is_admin());
+}
+
+#[test]
+fn admin_subcommands_parse_their_options() {
+    let cases = [
+        (vec!["migrate"], Command::Migrate { wait: 60 }),
+        (vec!["migrate", "--wait", "5"], Command::Migrate { wait: 5 }),
+        (vec!["seed", "--force"], Command::Seed { force: true }),
+        (vec!["export"], Command::Export { output: None }),
+        (
+            vec!["export", "-o", "todos.json"],
+            Command::Export {
+                output: Some("todos.json".into()),
+            },
+        ),
+        (
+            vec!["import", "-"],
+            Command::Import {
+                input: "-".into(),
+                mode: ImportMode::Merge,
+            },
+        ),
+        (
+            vec!["import", "export.json", "--mode", "dry-run"],
+            Command::Import {
+                input: "export.json".into(),
+                mode: ImportMode::DryRun,
+            },
+        ),
+        (
+            vec!["purge-deleted", "--older-than-days", "30"],
+            Command::PurgeDeleted {
+                older_than_days: 30,
+            },
+        ),
+        (
+            vec!["check-config", "--connect"],
+            Command::CheckConfig { connect: true },
+        ),
+    ];
+
+    for (args, expected) in cases {
+        let cli = Cli::try_parse_from(std::iter::once("zero2prod").chain(args.clone()))
+            .unwrap_or_else(|e| panic!("{:?} did not parse: {}", args, e));
+        assert!(expected.is_admin());
+        assert_eq!(cli.command, Some(expected), "{:?}", args);
+    }
+
+    assert!(Cli::try_parse_from(["zero2prod", "import"]).is_err());
+    assert!(Cli::try_parse_from(["zero2prod", "import", "-", "--mode", "upsert"]).is_err());
+    assert!(
+        Cli::try_parse_from(["zero2prod", "purge-deleted", "--older-than-days", "-1"]).is_err()
+    );
+}
+
+#[test]
+fn check_config_reports_each_problem() {
+    let mut settings = get_configuration().expect("Failed to read configuration.");
+    assert_eq!(check_settings(&settings), Vec::<String>::new());
+
+    settings.application.base_url = "todo.example.com".into();
+    settings.telemetry.otlp_endpoint = Some("127.0.0.1:4317".into());
+    let problems = check_settings(&settings);
+
+    assert_eq!(problems.len(), 2, "{:?}", problems);
+    assert!(problems[0].starts_with("application.base_url is not a URL"));
+    assert!(problems[1].starts_with("telemetry.otlp_endpoint is not a URL"));
+}
+
+#[tokio::test]
+async fn purge_deleted_removes_only_soft_deleted_rows() {
+    let pool = test_database().await;
+    seed_sample_data(&pool, false, &EventBus::default())
+        .await
+        .unwrap();
+    sqlx::query("update activities set deleted_at = now() where title = 'Groceries'")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let yesterday = chrono::Utc::now() - chrono::Duration::days(1);
+    let summary = purge_deleted(yesterday, &pool).await.unwrap();
+    assert_eq!((summary.activity_groups, summary.todos), (0, 0));
+
+    let tomorrow = chrono::Utc::now() + chrono::Duration::days(1);
+    let summary = purge_deleted(tomorrow, &pool).await.unwrap();
+    assert_eq!((summary.activity_groups, summary.todos), (1, 2));
+    let remaining = export_all(&pool).await.unwrap();
+    assert_eq!(remaining.activity_groups.len(), 1);
+    assert_eq!(remaining.activity_groups[0].todos.len(), 3);
+}
@@ -0,0 +1,50 @@
+//! Fixtures shared by the integration tests. Each test binary uses only
+//! some of them.
+#![allow(dead_code)]
+
+use secrecy::ExposeSecret;
+use sqlx::{Connection, Executor, MySqlConnection, MySqlPool};
+use std::net::TcpListener;
+use uuid::Uuid;
+use zero2prod::configuration::{get_configuration, DatabaseSettings};
+use zero2prod::services::MIGRATOR;
+use zero2prod::startup::run;
+
+/// Serves the app on a random port, returning its address.
+pub fn spawn_app(pool: MySqlPool) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(run(listener, pool).expect("Failed to bind address"));
+
+    format!("http://127.0.0.1:{}", port)
+}
+
+/// A pool on a fresh database named after a new UUID, with every migration
+/// applied.
+pub async fn test_database() -> MySqlPool {
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.database_name = Uuid::new_v4().to_string();
+    configure_database(&configuration.database).await
+}
+
+/// Creates `config.database_name` and migrates it.
+pub async fn configure_database(config: &DatabaseSettings) -> MySqlPool {
+    let mut connection =
+        MySqlConnection::connect(config.connection_string_without_db().expose_secret())
+            .await
+            .expect("Failed to connect to MySQL");
+
+    connection
+        .execute(format!("CREATE DATABASE `{}`;", config.database_name).as_str())
+        .await
+        .expect("Failed to create database.");
+
+    let pool = MySqlPool::connect(config.connection_string().expose_secret())
+        .await
+        .expect("Failed to connect to MySQL.");
+    MIGRATOR
+        .run(&pool)
+        .await
+        .expect("Failed to migrate the database");
+    pool
+}
@@ -0,0 +1,195 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use serde_json::Value;
+use zero2prod::services::{
+    import_markdown, parse_markdown, render_markdown, ActivityRecord, ChangeAction, ChangeEntity,
+    EventBus, ImportLineError, MarkdownSection, MarkdownTodo, TodoRecord,
+};
+
+use common::{spawn_app, test_database};
+
+fn todo(title: &str, priority: &str, is_active: bool) -> MarkdownTodo {
+    MarkdownTodo {
+        title: title.into(),
+        priority: priority.into(),
+        is_active,
+    }
+}
+
+#[test]
+fn groups_render_as_a_heading_and_a_checklist() {
+    let created_at = Utc.with_ymd_and_hms(2026, 10, 1, 8, 0, 0).unwrap();
+    let activity = ActivityRecord {
+        id: 1,
+        title: "Home  repairs".into(),
+        email: None,
+        created_at,
+        updated_at: None,
+    };
+    let todo = |id, title: &str, priority: &str, is_active| TodoRecord {
+        id,
+        title: title.into(),
+        activity_group_id: 1,
+        is_active,
+        priority: priority.into(),
+        due_at: None,
+        created_at,
+        updated_at: None,
+    };
+
+    assert_eq!(render_markdown(&activity, &[]), "# Home repairs\n");
+    assert_eq!(
+        render_markdown(
+            &activity,
+            &[
+                todo(1, "Fix the tap", "high", true),
+                todo(2, "Paint the\nfence", "very-low", false),
+            ]
+        ),
+        "# Home repairs\n\n- [ ] Fix the tap !high\n- [x] Paint the fence !very-low\n"
+    );
+}
+
+#[test]
+fn checklists_under_headings_become_groups() {
+    let notes = "Weekly sync, 2026-10-19\n\
+        \n\
+        ## Attendees\n\
+        - Ana\n\
+        - Budi\n\
+        \n\
+        ## Action items ##\n\
+        Agreed on the following:\n\
+        - [ ] Book the venue !high\n\
+        * [x] Send the agenda\n\
+        \x20 1. [ ] Ask for   quotes !low\n\
+        - [ ]Not an item\n\
+        - [ ] Shout !urgent\n\
+        ```\n\
+        # Not a heading\n\
+        - [ ] Not a todo\n\
+        ```\n\
+        ### Later\n\
+        + [X] Celebrate\n";
+
+    assert_eq!(
+        parse_markdown(notes).unwrap(),
+        [
+            MarkdownSection {
+                title: "Action items".into(),
+                todos: vec![
+                    todo("Book the venue", "high", true),
+                    todo("Send the agenda", "very-high", false),
+                    todo("Ask for quotes", "low", true),
+                    todo("Shout !urgent", "very-high", true),
+                ],
+            },
+            MarkdownSection {
+                title: "Later".into(),
+                todos: vec![todo("Celebrate", "very-high", false)],
+            },
+        ]
+    );
+    assert_eq!(parse_markdown("Just prose\n").unwrap(), []);
+}
+
+#[test]
+fn invalid_lines_are_reported_by_number() {
+    assert_eq!(
+        parse_markdown("- [ ] Orphan\n#\n- [ ] Unnamed\n# Named\n- [x] !low\n- [ ] Fine\n")
+            .unwrap_err(),
+        [
+            ImportLineError {
+                line: 1,
+                message: "the checklist item is not below a heading".into(),
+            },
+            ImportLineError {
+                line: 2,
+                message: "the heading has no text".into(),
+            },
+            ImportLineError {
+                line: 5,
+                message: "the checklist item has no text".into(),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn markdown_checklists_round_trip_through_the_api() {
+    let address = spawn_app(test_database().await);
+    let client = reqwest::Client::new();
+    let import_url = format!("{}/activity-groups/import/markdown", address);
+
+    let response = client
+        .post(&import_url)
+        .body("# Errands\n- [ ]\n- [x] \n")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["message"], "2 lines are invalid; nothing was imported");
+    assert_eq!(body["data"][1]["line"], 3);
+
+    let text = "# Home repairs\n\n- [ ] Fix the tap !high\n- [x] Paint the fence !very-low\n";
+    let response = client
+        .post(&import_url)
+        .body(format!("Notes\n\n{}\n# Empty\n", text))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(201, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["title"], "Home repairs");
+    assert_eq!(body["data"][0]["todos"][0]["priority"], "high");
+    assert_eq!(body["data"][0]["todos"][1]["is_active"], false);
+
+    let response = client
+        .get(format!(
+            "{}/activity-groups/{}.md",
+            address, body["data"][0]["id"]
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/markdown; charset=utf-8"
+    );
+    assert_eq!(response.text().await.unwrap(), text);
+
+    let response = client
+        .get(format!("{}/activity-groups/0.md", address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn imported_checklists_are_published_once_committed() {
+    let pool = test_database().await;
+    let events = EventBus::default();
+    let mut subscription = events.subscribe(None);
+
+    assert!(import_markdown("- [ ] Laundry\n", &pool, &events)
+        .await
+        .is_err());
+    assert!(subscription.receiver.try_recv().is_err());
+
+    let groups = import_markdown("# Chores\n- [ ] Laundry\n", &pool, &events)
+        .await
+        .unwrap();
+    let (activity, todos) = &groups[0];
+    let stored = subscription.receiver.try_recv().unwrap();
+    assert_eq!(stored.event.entity, ChangeEntity::ActivityGroup);
+    assert_eq!(stored.event.action, ChangeAction::Created);
+    assert_eq!(stored.event.id, activity.id);
+    let stored = subscription.receiver.try_recv().unwrap();
+    assert_eq!(stored.event.entity, ChangeEntity::Todo);
+    assert_eq!(stored.event.id, todos[0].id);
+}
@@ -0,0 +1,76 @@
+use secrecy::ExposeSecret;
+use sqlx::{Connection, Executor, MySqlConnection, MySqlPool};
+use std::time::Duration;
+use uuid::Uuid;
+use zero2prod::configuration::{get_configuration, DatabaseSettings};
+use zero2prod::services::{check_schema_version, run_migrations, MIGRATOR};
+
+#[test]
+fn migrations_run_on_startup_by_default() {
+    let configuration = get_configuration().expect("Failed to read configuration.");
+    assert!(configuration.database.migrate_on_startup);
+}
+
+async fn create_database(config: &DatabaseSettings) -> MySqlPool {
+    let mut connection =
+        MySqlConnection::connect(config.connection_string_without_db().expose_secret())
+            .await
+            .expect("Failed to connect to MySQL");
+
+    connection
+        .execute(format!("CREATE DATABASE `{}`;", config.database_name).as_str())
+        .await
+        .expect("Failed to create database.");
+
+    MySqlPool::connect(config.connection_string().expose_secret())
+        .await
+        .expect("Failed to connect to MySQL.")
+}
+
+#[tokio::test]
+async fn replicas_booting_together_migrate_once() {
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.database_name = Uuid::new_v4().to_string();
+    let pool = create_database(&configuration.database).await;
+
+    let timeout = Duration::from_secs(30);
+    let (first, second) = tokio::join!(
+        run_migrations(&pool, timeout),
+        run_migrations(&pool, timeout)
+    );
+
+    let applied = first.unwrap() + second.unwrap();
+    assert_eq!(applied, MIGRATOR.iter().count());
+    assert_eq!(run_migrations(&pool, timeout).await, Ok(0));
+    assert_eq!(check_schema_version(&pool).await, Ok(()));
+}
+
+#[tokio::test]
+async fn startup_refuses_a_schema_newer_than_the_build() {
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.database_name = Uuid::new_v4().to_string();
+    let pool = create_database(&configuration.database).await;
+    let timeout = Duration::from_secs(30);
+    run_migrations(&pool, timeout).await.unwrap();
+
+    sqlx::query(
+        "insert into _sqlx_migrations (version, description, success, checksum, execution_time)
+        values (99990101000000, 'from the future', true, x'00', 0)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    for result in [
+        run_migrations(&pool, timeout).await,
+        check_schema_version(&pool).await.map(|()| 0),
+    ] {
+        let error = result.unwrap_err();
+        assert!(
+            error.starts_with("The database schema is newer than this build"),
+            "{}",
+            error
+        );
+        assert!(error.contains("99990101000000"), "{}", error);
+    }
+}
@@ -0,0 +1,311 @@
+mod common;
+
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use async_trait::async_trait;
+use chrono::{Duration, SubsecRound, TimeZone, Utc};
+use lettre::transport::stub::AsyncStubTransport;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use zero2prod::configuration::ReminderSettings;
+use zero2prod::services::{
+    sign, ActivityRecord, EmailNotifier, NewReminder, Notification, Notifier, Notifiers,
+    ReminderChannel, ReminderFormData, ReminderScheduler, TodoRecord, WebhookNotifier,
+};
+
+use common::test_database;
+
+fn todo(due_in_minutes: Option<i64>) -> TodoRecord {
+    TodoRecord {
+        id: 7,
+        title: "Ship the release".into(),
+        activity_group_id: 1,
+        is_active: true,
+        priority: "high".into(),
+        due_at: due_in_minutes.map(|minutes| Utc::now() + Duration::minutes(minutes)),
+        created_at: Utc::now(),
+        updated_at: None,
+    }
+}
+
+fn activity(email: Option<&str>) -> ActivityRecord {
+    ActivityRecord {
+        id: 1,
+        title: "Release".into(),
+        email: email.map(String::from),
+        created_at: Utc::now(),
+        updated_at: None,
+    }
+}
+
+fn notification(recipient: Option<&str>) -> Notification {
+    Notification {
+        reminder_id: 3,
+        todo_id: 7,
+        title: "Ship the release".into(),
+        activity_group_id: 1,
+        activity_group_title: "Release".into(),
+        due_at: Some(Utc.with_ymd_and_hms(2026, 10, 20, 9, 0, 0).unwrap()),
+        recipient: recipient.map(String::from),
+    }
+}
+
+#[test]
+fn reminders_are_validated() {
+    let at = |form: ReminderFormData| ReminderFormData {
+        remind_at: Some(Utc::now().into()),
+        ..form
+    };
+    let before = |minutes: i32, form: ReminderFormData| ReminderFormData {
+        offset_minutes: Some(minutes),
+        ..form
+    };
+    let via = |channel: ReminderChannel, recipient: Option<&str>| ReminderFormData {
+        channel: Some(channel),
+        recipient: recipient.map(String::from),
+        ..ReminderFormData::default()
+    };
+    let with_email = activity(Some("team@example.com"));
+    let without_email = activity(None);
+
+    let test_cases = vec![
+        (
+            ReminderFormData::default(),
+            todo(Some(60)),
+            &with_email,
+            "remind_at or offset_minutes cannot be null",
+        ),
+        (
+            at(before(10, ReminderFormData::default())),
+            todo(Some(60)),
+            &with_email,
+            "remind_at and offset_minutes cannot both be set",
+        ),
+        (
+            before(-5, ReminderFormData::default()),
+            todo(Some(60)),
+            &with_email,
+            "offset_minutes cannot be negative",
+        ),
+        (
+            before(10, ReminderFormData::default()),
+            todo(None),
+            &with_email,
+            "offset_minutes needs a todo with a due_at",
+        ),
+        (
+            at(ReminderFormData::default()),
+            todo(None),
+            &without_email,
+            "recipient cannot be null when the activity group has no email",
+        ),
+        (
+            at(via(ReminderChannel::Email, Some("not an address"))),
+            todo(None),
+            &with_email,
+            "not an address is not an email address",
+        ),
+        (
+            at(via(ReminderChannel::Webhook, None)),
+            todo(None),
+            &with_email,
+            "recipient cannot be null",
+        ),
+        (
+            at(via(ReminderChannel::Webhook, Some("ftp://example.com"))),
+            todo(None),
+            &with_email,
+            "ftp://example.com is not an http or https URL",
+        ),
+    ];
+
+    for (form, todo, activity, message) in test_cases {
+        assert_eq!(
+            NewReminder::parse(form, &todo, activity).err().as_deref(),
+            Some(message)
+        );
+    }
+
+    let reminder = NewReminder::parse(
+        before(30, ReminderFormData::default()),
+        &todo(Some(60)),
+        &with_email,
+    )
+    .unwrap();
+    assert_eq!(reminder.channel, ReminderChannel::Email);
+    assert_eq!(reminder.recipient, None);
+    assert!(NewReminder::parse(
+        at(via(ReminderChannel::Log, None)),
+        &todo(None),
+        &without_email
+    )
+    .is_ok());
+}
+
+#[tokio::test]
+async fn email_reminders_are_mailed_to_the_recipient() {
+    let transport = AsyncStubTransport::new_ok();
+    let notifier = EmailNotifier::new(
+        transport.clone(),
+        "Todo List <todo@example.com>".parse().unwrap(),
+    );
+
+    notifier
+        .notify(&notification(Some("team@example.com")))
+        .await
+        .unwrap();
+
+    let messages = transport.messages().await;
+    assert_eq!(messages.len(), 1);
+    let (envelope, message) = &messages[0];
+    assert_eq!(envelope.to()[0].to_string(), "team@example.com");
+    assert!(message.contains("Subject: Reminder: Ship the release"));
+    assert!(
+        message.contains("\"Ship the release\" in Release is due at 2026-10-20T09:00:00+00:00.")
+    );
+}
+
+#[tokio::test]
+async fn email_reminders_without_a_recipient_fail() {
+    let transport = AsyncStubTransport::new_ok();
+    let notifier = EmailNotifier::new(transport.clone(), "todo@example.com".parse().unwrap());
+
+    let result = notifier.notify(&notification(None)).await;
+
+    assert_eq!(
+        result,
+        Err("no recipient: the activity group has no email".into())
+    );
+    assert!(transport.messages().await.is_empty());
+}
+
+#[derive(Clone, Debug)]
+struct Received {
+    signature: String,
+    event: String,
+    body: String,
+}
+
+type Log = Arc<Mutex<Vec<Received>>>;
+
+async fn record(req: HttpRequest, body: String, log: web::Data<Log>) -> HttpResponse {
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+    log.lock().unwrap().push(Received {
+        signature: header("X-Webhook-Signature"),
+        event: header("X-Webhook-Event"),
+        body,
+    });
+    HttpResponse::NoContent().finish()
+}
+
+/// Local HTTP server standing in for the reminder URL.
+fn spawn_stub() -> (String, Log) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
+    let port = listener.local_addr().unwrap().port();
+    let received = Log::default();
+
+    let log = web::Data::new(received.clone());
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(log.clone())
+            .default_service(web::post().to(record))
+    })
+    .listen(listener)
+    .expect("Failed to listen")
+    .run();
+    tokio::spawn(server);
+
+    (format!("http://127.0.0.1:{}/remind", port), received)
+}
+
+#[tokio::test]
+async fn webhook_reminders_are_signed_json_posts() {
+    let (url, received) = spawn_stub();
+    let notifier = WebhookNotifier::new(std::time::Duration::from_secs(2), Some("s3cret".into()));
+
+    notifier.notify(&notification(Some(&url))).await.unwrap();
+
+    let received = received.lock().unwrap().clone();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].event, "reminder.due");
+    assert_eq!(
+        received[0].signature,
+        sign("s3cret", received[0].body.as_bytes())
+    );
+    let body: serde_json::Value = serde_json::from_str(&received[0].body).unwrap();
+    assert_eq!(body["reminder_id"], 3);
+    assert_eq!(body["title"], "Ship the release");
+    assert!(body.get("recipient").is_none());
+}
+
+/// Keeps every notification instead of sending it.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<Notification>>>);
+
+#[async_trait]
+impl Notifier for Recorder {
+    async fn notify(&self, notification: &Notification) -> Result<(), String> {
+        self.0.lock().unwrap().push(notification.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn due_reminders_fire_exactly_once() {
+    let pool = test_database().await;
+
+    let now = Utc::now().trunc_subsecs(0);
+    let activity_id = sqlx::query(
+        "insert into activities (title, email, created_at) values ('Release', 'team@example.com', ?)",
+    )
+    .bind(now)
+    .execute(&pool)
+    .await
+    .unwrap()
+    .last_insert_id();
+    let todo_id = sqlx::query(
+        "insert into todos (title, activity_group_id, due_at, created_at) values ('Ship it', ?, ?, ?)",
+    )
+    .bind(activity_id)
+    .bind(now + Duration::minutes(10))
+    .bind(now)
+    .execute(&pool)
+    .await
+    .unwrap()
+    .last_insert_id();
+    for (remind_at, offset_minutes) in [
+        (None, Some(15)),                         // due: 5 minutes ago
+        (None, Some(5)),                          // not due for 5 minutes
+        (Some(now - Duration::minutes(1)), None), // due
+    ] {
+        sqlx::query(
+            "insert into reminders (todo_id, remind_at, offset_minutes, channel, created_at) values (?, ?, ?, 'email', ?)",
+        )
+        .bind(todo_id)
+        .bind(remind_at)
+        .bind(offset_minutes)
+        .bind(now)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let recorder = Recorder::default();
+    let notifiers =
+        Notifiers::new(&ReminderSettings::default()).with(ReminderChannel::Email, recorder.clone());
+    let scheduler = ReminderScheduler::new(pool, notifiers, ReminderSettings::default());
+
+    assert_eq!(scheduler.fire_due().await.unwrap(), 2);
+    assert_eq!(scheduler.fire_due().await.unwrap(), 0);
+
+    let sent = recorder.0.lock().unwrap().clone();
+    assert_eq!(sent.len(), 2);
+    assert!(sent
+        .iter()
+        .all(|notification| notification.recipient.as_deref() == Some("team@example.com")));
+}
@@ -0,0 +1,60 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use zero2prod::common::Timezone;
+
+#[test]
+fn timestamps_default_to_utc() {
+    let instant = Utc.with_ymd_and_hms(2023, 5, 1, 10, 0, 0).unwrap();
+
+    let rendered = Timezone::default().localize(instant);
+
+    assert_eq!(rendered.to_rfc3339(), "2023-05-01T10:00:00+00:00");
+}
+
+#[test]
+fn timestamps_are_rendered_in_the_requested_zone() {
+    let instant = Utc.with_ymd_and_hms(2023, 5, 1, 20, 30, 0).unwrap();
+    let tz = Timezone::parse("Asia/Jakarta").unwrap();
+
+    let rendered = tz.localize(instant);
+
+    assert_eq!(rendered.to_rfc3339(), "2023-05-02T03:30:00+07:00");
+    assert_eq!(rendered, instant);
+}
+
+#[test]
+fn date_filters_cover_the_local_day() {
+    let tz = Timezone::parse("Asia/Jakarta").unwrap();
+    let date = NaiveDate::from_ymd_opt(2023, 5, 2).unwrap();
+
+    assert_eq!(
+        tz.start_of_day(date),
+        Utc.with_ymd_and_hms(2023, 5, 1, 17, 0, 0).unwrap()
+    );
+    assert_eq!(
+        tz.end_of_day(date),
+        Utc.with_ymd_and_hms(2023, 5, 2, 17, 0, 0).unwrap()
+    );
+}
+
+#[test]
+fn a_day_starting_inside_a_dst_gap_starts_after_the_gap() {
+    // Santiago skipped from 00:00 to 01:00 on 2022-09-11.
+    let tz = Timezone::parse("America/Santiago").unwrap();
+    let date = NaiveDate::from_ymd_opt(2022, 9, 11).unwrap();
+
+    assert_eq!(
+        tz.start_of_day(date),
+        Utc.with_ymd_and_hms(2022, 9, 11, 4, 0, 0).unwrap()
+    );
+}
+
+#[test]
+fn unknown_zones_are_rejected() {
+    for invalid in ["", "Mars/Olympus_Mons", "+07:00"] {
+        assert!(
+            Timezone::parse(invalid).is_err(),
+            "{:?} was accepted",
+            invalid
+        );
+    }
+}
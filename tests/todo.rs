@@ -38,7 +38,7 @@ async fn spawn_app() -> TestApp {
     let connection_pool = configure_database(&configuration.database).await;
     let server = run(listener, connection_pool.clone()).expect("Failed to bind address");
 
-    let _ = tokio::spawn(server);
+    tokio::spawn(server);
 
     TestApp {
         address,
@@ -49,18 +49,18 @@ async fn spawn_app() -> TestApp {
 pub async fn configure_database(config: &DatabaseSettings) -> MySqlPool {
     // Create database
     let mut connection =
-        MySqlConnection::connect(&config.connection_string_without_db().expose_secret())
+        MySqlConnection::connect(config.connection_string_without_db().expose_secret())
             .await
-            .expect("Failed to connect to Postgres");
+            .expect("Failed to connect to MySQL");
 
     connection
-        .execute(format!(r#"CREATE DATABASE "{}";"#, config.database_name).as_str())
+        .execute(format!("CREATE DATABASE `{}`;", config.database_name).as_str())
         .await
         .expect("Failed to create database.");
     // Migrate database
-    let connection_pool = MySqlPool::connect(&config.connection_string().expose_secret())
+    let connection_pool = MySqlPool::connect(config.connection_string().expose_secret())
         .await
-        .expect("Failed to connect to Postgres.");
+        .expect("Failed to connect to MySQL.");
 
     sqlx::migrate!("./migrations")
         .run(&connection_pool)
@@ -76,7 +76,7 @@ async fn health_check_workss() {
     let client = reqwest::Client::new();
 
     let response = client
-        .get(&format!("{}/health_check", &app.address))
+        .get(format!("{}/health_check", app.address))
         .send()
         .await
         .expect("Failed to execute request");
@@ -95,7 +95,7 @@ async fn subscribe_returns_a_200_for_valid_form_input() {
     // Act
     let body = "email=iam.wahidn%40gmail.com&name=Niam";
     let response = client
-        .post(&format!("{}/subscribe", &app.address))
+        .post(format!("{}/subscriptions", app.address))
         .header("Content-Type", "application/x-www-form-urlencoded")
         .body(body)
         .send()
@@ -104,7 +104,7 @@ async fn subscribe_returns_a_200_for_valid_form_input() {
 
     assert_eq!(200, response.status().as_u16());
 
-    let saved = sqlx::query!("SELECT email, name FROM subscriptions",)
+    let saved = sqlx::query!("SELECT email, name FROM subscriptions")
         .fetch_one(&app.db_pool)
         .await
         .expect("Failed to fetch saved subscription.");
@@ -127,7 +127,7 @@ async fn subscribe_returns_a_400_when_data_is_missing() {
     for (invalid_body, error_message) in test_cases {
         // Act
         let response = client
-            .post(&format!("{}/subscribe", &app.address))
+            .post(format!("{}/subscriptions", app.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(invalid_body)
             .send()
@@ -0,0 +1,214 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+use zero2prod::common::Timezone;
+use zero2prod::services::{
+    format_todo_txt, import_todo_txt, todo_txt_project, ChangeAction, EventBus, TodoRecord,
+    TodoTxtTask,
+};
+
+use common::{spawn_app, test_database};
+
+fn jakarta() -> Timezone {
+    Timezone::parse("Asia/Jakarta").unwrap()
+}
+
+#[test]
+fn projects_are_activity_group_titles_without_spaces() {
+    assert_eq!(todo_txt_project("Groceries"), "Groceries");
+    assert_eq!(todo_txt_project(" Home  repairs "), "Home-repairs");
+}
+
+#[test]
+fn lines_map_onto_todos() {
+    let tz = jakarta();
+    let parse = |line: &str| TodoTxtTask::parse(line, "Groceries", &tz);
+
+    assert_eq!(
+        parse("(B) 2026-10-01 Buy milk @store +Groceries +Weekly due:2026-10-20"),
+        Ok(TodoTxtTask {
+            title: "Buy milk @store +Weekly".into(),
+            priority: "high".into(),
+            is_active: true,
+            created_at: Some(Utc.with_ymd_and_hms(2026, 9, 30, 17, 0, 0).unwrap()),
+            completed_at: None,
+            due_at: Some(Utc.with_ymd_and_hms(2026, 10, 20, 16, 59, 59).unwrap()),
+        })
+    );
+    assert_eq!(
+        parse("x 2026-10-19 2026-10-01 Buy bread pri:E"),
+        Ok(TodoTxtTask {
+            title: "Buy bread".into(),
+            priority: "very-low".into(),
+            is_active: false,
+            created_at: Some(Utc.with_ymd_and_hms(2026, 9, 30, 17, 0, 0).unwrap()),
+            completed_at: Some(Utc.with_ymd_and_hms(2026, 10, 18, 17, 0, 0).unwrap()),
+            due_at: None,
+        })
+    );
+
+    let plain = parse("Call mum").unwrap();
+    assert_eq!(plain.priority, "very-high");
+    assert!(plain.is_active);
+    assert_eq!(plain.created_at, None);
+    // Only a leading `x ` marks a todo as done.
+    assert!(parse("xylophone lessons").unwrap().is_active);
+    assert_eq!(parse("(a) lowercase").unwrap().title, "(a) lowercase");
+
+    assert_eq!(parse("(F) Someday").unwrap().priority, "very-low");
+    assert_eq!(parse("Someday pri:Z").unwrap().priority, "very-low");
+    assert_eq!(
+        parse("Someday pri:1"),
+        Err("priority (1) is not one of (A) to (Z)".into())
+    );
+    assert_eq!(
+        parse("Pay rent due:tomorrow"),
+        Err("due:tomorrow is not a date like 2026-10-19".into())
+    );
+    assert_eq!(
+        parse("(A) 2026-10-01 +Groceries"),
+        Err("the task has no description".into())
+    );
+}
+
+#[test]
+fn todos_survive_a_round_trip_through_todo_txt() {
+    let tz = jakarta();
+    let created_at = Utc.with_ymd_and_hms(2026, 9, 30, 17, 0, 0).unwrap();
+    let todos = [
+        TodoRecord {
+            id: 1,
+            title: "Buy milk @store".into(),
+            activity_group_id: 1,
+            is_active: true,
+            priority: "normal".into(),
+            due_at: Some(Utc.with_ymd_and_hms(2026, 10, 20, 16, 59, 59).unwrap()),
+            created_at,
+            updated_at: Some(created_at),
+        },
+        TodoRecord {
+            id: 2,
+            title: "Buy bread".into(),
+            activity_group_id: 1,
+            is_active: false,
+            priority: "low".into(),
+            due_at: None,
+            created_at,
+            updated_at: Some(Utc.with_ymd_and_hms(2026, 10, 18, 17, 0, 0).unwrap()),
+        },
+    ];
+
+    let lines: Vec<String> = todos
+        .iter()
+        .map(|todo| format_todo_txt(todo, "Groceries", &tz))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "(C) 2026-10-01 Buy milk @store +Groceries due:2026-10-20",
+            "x 2026-10-19 2026-10-01 Buy bread +Groceries pri:D",
+        ]
+    );
+
+    for (todo, line) in todos.iter().zip(&lines) {
+        let task = TodoTxtTask::parse(line, "Groceries", &tz).unwrap();
+        assert_eq!(task.title, todo.title);
+        assert_eq!(task.priority, todo.priority);
+        assert_eq!(task.is_active, todo.is_active);
+        assert_eq!(task.created_at, Some(todo.created_at));
+        assert_eq!(task.due_at, todo.due_at);
+        if !todo.is_active {
+            assert_eq!(task.completed_at, todo.updated_at);
+        }
+    }
+}
+
+#[tokio::test]
+async fn todo_txt_round_trips_through_the_api() {
+    let address = spawn_app(test_database().await);
+    let client = reqwest::Client::new();
+
+    let group: Value = client
+        .post(format!("{}/activity-groups", address))
+        .json(&json!({ "title": "Home repairs" }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    let url = format!(
+        "{}/activity-groups/{}/todo.txt?tz=Asia/Jakarta",
+        address, group["data"]["id"]
+    );
+    let text = "(A) 2026-10-01 Fix the tap @kitchen +Home-repairs due:2026-10-20\n\
+        x 2026-10-19 2026-10-02 Paint the fence +Home-repairs pri:C\n";
+
+    let response = client
+        .post(&url)
+        .body("(G) Later\n\nFix it due:soon\n")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["message"], "2 lines are invalid; nothing was imported");
+    assert_eq!(body["data"][1]["line"], 3);
+
+    let response = client
+        .post(&url)
+        .body(text)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(201, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["data"][0]["title"], "Fix the tap @kitchen");
+    assert_eq!(body["data"][1]["is_active"], false);
+
+    let exported = client
+        .get(&url)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(exported, text);
+}
+
+#[tokio::test]
+async fn imported_lines_are_published_once_committed() {
+    let pool = test_database().await;
+    let group_id =
+        sqlx::query("insert into activities (title, created_at) values ('Groceries', now())")
+            .execute(&pool)
+            .await
+            .unwrap()
+            .last_insert_id() as i32;
+    let events = EventBus::default();
+    let mut subscription = events.subscribe(None);
+    let tz = jakarta();
+
+    let result = import_todo_txt("Milk pri:1\n", group_id, "Groceries", &tz, &pool, &events).await;
+    assert!(result.is_err());
+    assert!(subscription.receiver.try_recv().is_err());
+
+    let todos = import_todo_txt(
+        "(A) Milk\nx Bread\n",
+        group_id,
+        "Groceries",
+        &tz,
+        &pool,
+        &events,
+    )
+    .await
+    .unwrap();
+    for todo in &todos {
+        let stored = subscription.receiver.try_recv().unwrap();
+        assert_eq!(stored.event.action, ChangeAction::Created);
+        assert_eq!(stored.event.id, todo.id);
+    }
+    assert!(subscription.receiver.try_recv().is_err());
+}
@@ -0,0 +1,21 @@
+use chrono::{DateTime, FixedOffset};
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Activity {
+    pub id: i32,
+    pub title: String,
+    pub email: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<FixedOffset>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ActivityFormData {
+    #[serde(default)]
+    pub title: String,
+    pub email: Option<String>,
+}
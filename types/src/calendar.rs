@@ -0,0 +1,11 @@
+/// The subscribe URL of an activity group's calendar.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CalendarFeed {
+    /// `calendar.ics` with the feed's token; anyone holding it can read the
+    /// group's todos.
+    pub url: String,
+    /// The same feed as a `webcal://` link, which calendar apps open as a
+    /// subscription.
+    pub webcal_url: String,
+}
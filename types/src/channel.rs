@@ -0,0 +1,72 @@
+use crate::{ChangeEvent, Todo, TodoFormData, TodoFormUpdateData};
+
+/// Someone connected to an activity group's channel.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Member {
+    pub connection_id: String,
+    pub name: String,
+}
+
+/// A command sent by a client over `/ws/activity-groups/{id}`.
+///
+/// `request_id` is optional and echoed back on the matching `result` or
+/// `error` message.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ChannelCommand {
+    /// Creates a todo in the channel's group; `activity_group_id` is ignored.
+    Create {
+        #[serde(default)]
+        request_id: Option<String>,
+        #[serde(flatten)]
+        todo: TodoFormData,
+    },
+    /// Changes a todo of the channel's group. `activity_group_id`, when
+    /// set, must be that group: todos can't be moved out of it.
+    Update {
+        #[serde(default)]
+        request_id: Option<String>,
+        id: i32,
+        #[serde(flatten)]
+        changes: TodoFormUpdateData,
+    },
+    /// Flips `is_active`.
+    Toggle {
+        #[serde(default)]
+        request_id: Option<String>,
+        id: i32,
+    },
+}
+
+/// A message sent by the server over `/ws/activity-groups/{id}`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ChannelMessage {
+    /// First message on every connection; `members` includes the caller.
+    Welcome {
+        connection_id: String,
+        members: Vec<Member>,
+    },
+    Joined {
+        member: Member,
+    },
+    Left {
+        member: Member,
+    },
+    /// A change to the group or one of its todos, whoever made it.
+    Change {
+        event: ChangeEvent,
+    },
+    /// The todo after a successful command.
+    Result {
+        request_id: Option<String>,
+        todo: Todo,
+    },
+    Error {
+        request_id: Option<String>,
+        message: String,
+    },
+}
@@ -0,0 +1,49 @@
+use crate::{Activity, Todo};
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeEntity {
+    Todo,
+    ActivityGroup,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// A todo or activity group mutation, as pushed by `GET /events`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ChangeEvent {
+    pub entity: ChangeEntity,
+    pub action: ChangeAction,
+    pub id: i32,
+    /// The todo's group, or the group's own id for activity group changes.
+    pub activity_group_id: i32,
+    /// The todo after the change, in UTC. Unset for deletions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub todo: Option<Todo>,
+    /// The activity group after the change, in UTC. Unset for deletions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activity_group: Option<Activity>,
+}
+
+impl ChangeEvent {
+    /// The SSE event name, e.g. `todo.created`.
+    pub fn name(&self) -> &'static str {
+        match (self.entity, self.action) {
+            (ChangeEntity::Todo, ChangeAction::Created) => "todo.created",
+            (ChangeEntity::Todo, ChangeAction::Updated) => "todo.updated",
+            (ChangeEntity::Todo, ChangeAction::Deleted) => "todo.deleted",
+            (ChangeEntity::ActivityGroup, ChangeAction::Created) => "activity-group.created",
+            (ChangeEntity::ActivityGroup, ChangeAction::Updated) => "activity-group.updated",
+            (ChangeEntity::ActivityGroup, ChangeAction::Deleted) => "activity-group.deleted",
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use chrono::{DateTime, FixedOffset};
+
+use crate::{Activity, Todo};
+
+/// Every activity group with its todos, in UTC, as written by
+/// `zero2prod export`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Export {
+    /// Format version; imports refuse versions they don't know.
+    pub version: u32,
+    pub exported_at: DateTime<FixedOffset>,
+    pub activity_groups: Vec<ExportedActivityGroup>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ExportedActivityGroup {
+    #[serde(flatten)]
+    pub activity_group: Activity,
+    pub todos: Vec<Todo>,
+}
+
+/// How `POST /import` treats data already in the database.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportMode {
+    /// Keep existing data. Activity groups with a title that already exists
+    /// are merged into it, and todos it already has by title are skipped.
+    #[default]
+    Merge,
+    /// Delete every activity group and todo first.
+    Replace,
+    /// Report what `merge` would do without writing anything.
+    DryRun,
+}
+
+/// An id in the imported document and the row it ended up as.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct IdMapping {
+    pub from: i32,
+    pub to: i32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ImportEntity {
+    ActivityGroup,
+    Todo,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// The activity group's todos went into the existing one.
+    Merged,
+    /// The todo was left out.
+    Skipped,
+}
+
+/// An imported entry that matched existing data instead of being created.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ImportConflict {
+    pub entity: ImportEntity,
+    /// Id in the imported document.
+    pub id: i32,
+    pub existing_id: i32,
+    pub resolution: ConflictResolution,
+}
+
+/// The outcome of an import. Ids reported by a dry run were never committed.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ImportReport {
+    pub mode: ImportMode,
+    /// Where every activity group in the document went, created or merged.
+    pub activity_groups: Vec<IdMapping>,
+    /// Where every todo in the document went, created or skipped.
+    pub todos: Vec<IdMapping>,
+    pub conflicts: Vec<ImportConflict>,
+}
+
+/// What an import from another tool's export created, and what it left out.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ExternalImportReport {
+    /// The activity groups created, with their todos in the source's order.
+    pub activity_groups: Vec<ExportedActivityGroup>,
+    /// Archived or deleted entries, which are not imported.
+    pub skipped: u64,
+    /// Fields holding data that has no place here.
+    pub unmapped: Vec<UnmappedField>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UnmappedField {
+    /// Path of the field in the source format, such as `cards.labels`.
+    pub field: String,
+    /// How many imported entries had it set.
+    pub count: u64,
+}
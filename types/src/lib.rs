@@ -0,0 +1,31 @@
+//! Request and response bodies shared by the server and the client.
+//!
+//! Timestamps are RFC 3339 strings, in UTC unless the request asked for
+//! another zone through `X-Timezone` or `?tz=`.
+
+mod activity;
+mod calendar;
+mod channel;
+mod event;
+mod export;
+mod health;
+mod reminder;
+mod response;
+mod search;
+mod subscription;
+mod todo;
+pub mod v2;
+mod webhook;
+
+pub use activity::*;
+pub use calendar::*;
+pub use channel::*;
+pub use event::*;
+pub use export::*;
+pub use health::*;
+pub use reminder::*;
+pub use response::*;
+pub use search::*;
+pub use subscription::*;
+pub use todo::*;
+pub use webhook::*;
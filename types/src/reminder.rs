@@ -0,0 +1,51 @@
+use chrono::{DateTime, FixedOffset};
+
+/// Where a reminder is sent.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum ReminderChannel {
+    /// Written to the server log; handy for development.
+    Log,
+    /// Mailed to the recipient, or the activity group's email.
+    Email,
+    /// POSTed as JSON to the recipient URL.
+    Webhook,
+}
+
+/// A reminder on a todo. It fires once, at `remind_at` or `offset_minutes`
+/// before the todo's `due_at`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Reminder {
+    pub id: i32,
+    pub todo_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remind_at: Option<DateTime<FixedOffset>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset_minutes: Option<i32>,
+    pub channel: ReminderChannel,
+    /// Email address or URL; email reminders without one go to the activity
+    /// group's email.
+    pub recipient: Option<String>,
+    pub fired_at: Option<DateTime<FixedOffset>>,
+    /// Why sending failed, if it did. Reminders are not retried.
+    pub last_error: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<FixedOffset>,
+}
+
+/// Creates a reminder. Set exactly one of `remind_at` and `offset_minutes`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ReminderFormData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remind_at: Option<DateTime<FixedOffset>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset_minutes: Option<i32>,
+    /// `email` when left out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<ReminderChannel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<String>,
+}
@@ -0,0 +1,23 @@
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum SearchKind {
+    Todo,
+    ActivityGroup,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchHit {
+    pub kind: SearchKind,
+    pub id: i32,
+    /// The todo's group, or the group's own id for activity group hits.
+    pub activity_group_id: i32,
+    pub title: String,
+    /// Only set for todos.
+    pub is_active: Option<bool>,
+    /// Relevance, higher is better. Only comparable within one response.
+    pub score: f64,
+    /// HTML-escaped excerpt with the matched terms wrapped in `<mark>`.
+    pub snippet: String,
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, FixedOffset};
+
+pub fn default_as_true() -> bool {
+    true
+}
+
+pub fn default_as_very_high() -> String {
+    "very-high".into()
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Todo {
+    pub id: i32,
+    pub title: String,
+    pub priority: Option<String>,
+    pub activity_group_id: i32,
+    #[serde(default)]
+    pub is_active: Option<bool>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<FixedOffset>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<FixedOffset>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TodoFormData {
+    #[serde(default)]
+    pub title: String,
+    pub activity_group_id: Option<i32>,
+    #[serde(default = "default_as_very_high")]
+    pub priority: String,
+    #[serde(default = "default_as_true")]
+    pub is_active: bool,
+    /// Reminders with an offset fire that long before this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TodoFormUpdateData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activity_group_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_active: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<FixedOffset>>,
+}
+
+/// A row of an uploaded file that could not be imported, by its line.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ImportLineError {
+    pub line: u64,
+    pub message: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CsvImportSummary {
+    /// Todos created, one per data row.
+    pub created: u64,
+}
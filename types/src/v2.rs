@@ -0,0 +1,52 @@
+//! Bodies of the `/v2` API: snake_case fields, a bare `data` envelope on
+//! success and an `error` object on failure.
+
+use chrono::{DateTime, FixedOffset};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = DataV2))]
+pub struct Data<T> {
+    pub data: T,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = ErrorBodyV2))]
+pub struct ErrorBody {
+    pub error: Error,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = ErrorV2))]
+pub struct Error {
+    /// Machine readable code, e.g. `not_found` or `validation_failed`.
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = ActivityV2))]
+pub struct Activity {
+    pub id: i32,
+    pub title: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = TodoV2))]
+pub struct Todo {
+    pub id: i32,
+    pub title: String,
+    pub activity_group_id: i32,
+    pub is_active: bool,
+    pub priority: String,
+    pub due_at: Option<DateTime<FixedOffset>>,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: Option<DateTime<FixedOffset>>,
+}
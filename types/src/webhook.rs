@@ -0,0 +1,70 @@
+use chrono::{DateTime, FixedOffset};
+
+use crate::ChangeEvent;
+
+/// A webhook subscription. The signing secret is never returned.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    /// Event names such as `todo.created`, or `*` for every event.
+    pub event_types: Vec<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<FixedOffset>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<FixedOffset>>,
+}
+
+/// Creates a webhook, or updates one when fields are left out.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WebhookFormData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_types: Option<Vec<String>>,
+    /// Key for the `X-Webhook-Signature` HMAC-SHA256 of each payload.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum DeliveryStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// One payload queued for a webhook, with the outcome of its last attempt.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i32,
+    pub event_type: String,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    /// HTTP status of the last attempt, if the endpoint answered.
+    pub response_status: Option<i32>,
+    /// Transport error or response excerpt of the last failed attempt.
+    pub last_error: Option<String>,
+    /// When a pending delivery is next tried.
+    pub next_attempt_at: DateTime<FixedOffset>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<FixedOffset>,
+    pub delivered_at: Option<DateTime<FixedOffset>>,
+}
+
+/// The JSON body POSTed to webhook URLs.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WebhookPayload {
+    /// The event name, e.g. `todo.created`.
+    pub event: String,
+    /// Same id as the event has on `GET /events`.
+    pub event_id: u64,
+    pub data: ChangeEvent,
+}